@@ -0,0 +1,51 @@
+//! Renders a `.vox` file from an orbiting camera into a PPM image.
+//!
+//! Usage: `cargo run --example raycast [input.vox] [output.ppm]`
+
+use dot_vox::render::{shade, RayCamera, VoxelGrid};
+use std::io::Write;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let input = args
+        .next()
+        .unwrap_or_else(|| "src/resources/axes.vox".to_owned());
+    let output = args.next().unwrap_or_else(|| "raycast.ppm".to_owned());
+
+    let data = dot_vox::load(&input).expect("Expected a valid vox file");
+    let grid = VoxelGrid::from_scene(&data);
+
+    // Frame the whole scene from a corner above it.
+    let center = [0, 1, 2].map(|axis| grid.min[axis] as f32 + grid.extent[axis] as f32 / 2.0);
+    let radius = grid.extent.iter().copied().max().unwrap_or(1) as f32 * 1.5;
+    let camera = RayCamera {
+        position: [center[0] - radius, center[1] - radius, center[2] + radius],
+        target: center,
+        up: [0.0, 0.0, 1.0],
+        fov_y: 50f32.to_radians(),
+    };
+
+    let mut image = Vec::with_capacity((WIDTH * HEIGHT * 3) as usize);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let color = match grid.raycast(&camera.ray(x, y, WIDTH, HEIGHT)) {
+                Some(hit) => shade(&hit, &data.palette),
+                None => dot_vox::Color {
+                    r: 32,
+                    g: 32,
+                    b: 40,
+                    a: 255,
+                },
+            };
+            image.extend_from_slice(&[color.r, color.g, color.b]);
+        }
+    }
+
+    let mut file = std::fs::File::create(&output).expect("Unable to create output file");
+    write!(file, "P6\n{} {}\n255\n", WIDTH, HEIGHT).unwrap();
+    file.write_all(&image).unwrap();
+    println!("Rendered {} to {}", input, output);
+}
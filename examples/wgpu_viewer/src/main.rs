@@ -0,0 +1,456 @@
+//! Renders a `.vox` scene with wgpu, orbiting the camera around it.
+//!
+//! Usage: `cargo run --release [input.vox]` from this directory.
+//!
+//! This is meant as executable documentation of how MagicaVoxel places models:
+//!
+//! - Each model is meshed once, in model space, where the voxel `(x, y, z)`
+//!   spans `x..x + 1`, `y..y + 1` and `z..z + 1`. The mesh is built from the
+//!   8×8×8 bricks of [`Model::bricks`], the layout a GPU voxel store would
+//!   use: only faces whose neighbor is empty in the brick holding it are
+//!   emitted.
+//! - Every placement of a model in the scene graph is drawn as an instance,
+//!   with the transform from [`DotVoxData::gpu_instances`]. The transform
+//!   already accounts for the pivot at the center of the model, and for the
+//!   half voxel offset of models with an odd size.
+//! - World space stays Z up as in MagicaVoxel, up to the view matrix, which
+//!   converts to the Y up convention of `glam::Mat4::look_at_rh`.
+
+use dot_vox::{
+    gpu::{Brick, GpuInstance, BRICK_SIZE},
+    DotVoxData, Model,
+};
+use glam::{Mat4, Vec3};
+use std::{collections::HashMap, mem::size_of, sync::Arc, time::Instant};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::EventLoop,
+    window::{Window, WindowBuilder},
+};
+
+/// Maps MagicaVoxel's right-handed Z up world space to the right-handed Y up
+/// space most engines and math libraries expect: `(x, y, z) -> (x, z, -y)`.
+const Z_UP_TO_Y_UP: Mat4 = Mat4::from_cols_array(&[
+    1.0, 0.0, 0.0, 0.0, //
+    0.0, 0.0, -1.0, 0.0, //
+    0.0, 1.0, 0.0, 0.0, //
+    0.0, 0.0, 0.0, 1.0,
+]);
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    color: dot_vox::Color,
+}
+
+/// The mesh of a model, and the range of instances drawing it.
+struct ModelMesh {
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    index_count: u32,
+    instances: std::ops::Range<u32>,
+}
+
+/// Builds a quad for every exposed face of the model, in model space, from
+/// its bricks.
+fn mesh(model: &Model, palette: &[dot_vox::Color]) -> (Vec<Vertex>, Vec<u32>) {
+    const NORMALS: [[i32; 3]; 6] = [
+        [1, 0, 0],
+        [-1, 0, 0],
+        [0, 1, 0],
+        [0, -1, 0],
+        [0, 0, 1],
+        [0, 0, -1],
+    ];
+
+    let bricks = model.bricks();
+    let by_origin: HashMap<[u32; 3], &Brick> =
+        bricks.iter().map(|brick| (brick.origin, brick)).collect();
+    // Whether the voxel at a model space position is occupied, looking it up
+    // in the brick holding it.
+    let occupied = |position: [i32; 3]| {
+        if position.iter().any(|c| *c < 0) {
+            return false;
+        }
+        let position = position.map(|c| c as u32);
+        let origin = [0, 1, 2].map(|axis| position[axis] / BRICK_SIZE * BRICK_SIZE);
+        by_origin.get(&origin).is_some_and(|brick| {
+            let [x, y, z] = [0, 1, 2].map(|axis| position[axis] - origin[axis]);
+            brick.get(x, y, z).is_some()
+        })
+    };
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for brick in bricks.iter() {
+        for z in 0..BRICK_SIZE {
+            for y in 0..BRICK_SIZE {
+                for x in 0..BRICK_SIZE {
+                    let Some(index) = brick.get(x, y, z) else {
+                        continue;
+                    };
+                    let offset = [x, y, z];
+                    let voxel = [0, 1, 2].map(|axis| (brick.origin[axis] + offset[axis]) as i32);
+                    let color = palette
+                        .get(index as usize)
+                        .copied()
+                        .unwrap_or(dot_vox::Color {
+                            r: 255,
+                            g: 0,
+                            b: 255,
+                            a: 255,
+                        });
+                    for normal in NORMALS.iter() {
+                        let neighbor = [0, 1, 2].map(|axis| voxel[axis] + normal[axis]);
+                        if !occupied(neighbor) {
+                            quad(&mut vertices, &mut indices, voxel, *normal, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    (vertices, indices)
+}
+
+/// Appends the face of the voxel `voxel` facing `normal`.
+fn quad(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    voxel: [i32; 3],
+    normal: [i32; 3],
+    color: dot_vox::Color,
+) {
+    let axis = normal.iter().position(|n| *n != 0).unwrap();
+    let positive = normal[axis] > 0;
+    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+    let origin = voxel.map(|c| c as f32);
+    let corner = |du: f32, dv: f32| {
+        let mut position = origin;
+        position[axis] += if positive { 1.0 } else { 0.0 };
+        position[u] += du;
+        position[v] += dv;
+        Vertex {
+            position,
+            normal: normal.map(|n| n as f32),
+            color,
+        }
+    };
+
+    let base = vertices.len() as u32;
+    vertices.extend([
+        corner(0.0, 0.0),
+        corner(1.0, 0.0),
+        corner(1.0, 1.0),
+        corner(0.0, 1.0),
+    ]);
+    // Counter-clockwise when seen from outside the voxel.
+    if positive {
+        indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    } else {
+        indices.extend([base, base + 2, base + 1, base, base + 3, base + 2]);
+    }
+}
+
+/// Smallest and largest world space corners of the voxels of the scene.
+fn bounds(data: &DotVoxData) -> (Vec3, Vec3) {
+    let mut min = Vec3::splat(f32::INFINITY);
+    let mut max = Vec3::splat(f32::NEG_INFINITY);
+    for voxel in data.world_voxels() {
+        let position = Vec3::new(
+            voxel.position.x as f32,
+            voxel.position.y as f32,
+            voxel.position.z as f32,
+        );
+        min = min.min(position);
+        max = max.max(position + 1.0);
+    }
+    if min.x > max.x {
+        (Vec3::ZERO, Vec3::ONE)
+    } else {
+        (min, max)
+    }
+}
+
+fn depth_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn main() {
+    let input = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "../../src/resources/transforms.vox".to_owned());
+    let data = dot_vox::load(&input).expect("Expected a valid vox file");
+
+    let event_loop = EventLoop::new().unwrap();
+    let window = Arc::new(
+        WindowBuilder::new()
+            .with_title(format!("dot_vox - {}", input))
+            .build(&event_loop)
+            .unwrap(),
+    );
+    pollster::block_on(run(event_loop, window, data));
+}
+
+async fn run(event_loop: EventLoop<()>, window: Arc<Window>, data: DotVoxData) {
+    let instance = wgpu::Instance::default();
+    let surface = instance.create_surface(window.clone()).unwrap();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            compatible_surface: Some(&surface),
+            ..Default::default()
+        })
+        .await
+        .expect("No suitable graphics adapter");
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await
+        .unwrap();
+
+    let size = window.inner_size();
+    let format = surface
+        .get_capabilities(&adapter)
+        .formats
+        .into_iter()
+        .find(|format| format.is_srgb())
+        .expect("No sRGB surface format");
+    let mut config = surface
+        .get_default_config(&adapter, size.width.max(1), size.height.max(1))
+        .unwrap();
+    config.format = format;
+    surface.configure(&device, &config);
+    let mut depth = depth_view(&device, config.width, config.height);
+
+    // Group the instances by model, so each model is drawn with one call.
+    let mut instances = data.gpu_instances();
+    instances.sort_by_key(|instance| instance.model_id);
+    let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("instances"),
+        contents: bytemuck::cast_slice(&instances),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let meshes: Vec<ModelMesh> = data
+        .models
+        .iter()
+        .enumerate()
+        .filter_map(|(model_id, model)| {
+            let start = instances.partition_point(|i| (i.model_id as usize) < model_id);
+            let end = instances.partition_point(|i| (i.model_id as usize) <= model_id);
+            let (vertices, indices) = mesh(model, &data.palette);
+            if start == end || indices.is_empty() {
+                return None;
+            }
+            Some(ModelMesh {
+                vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("vertices"),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }),
+                indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("indices"),
+                    contents: bytemuck::cast_slice(&indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                }),
+                index_count: indices.len() as u32,
+                instances: start as u32..end as u32,
+            })
+        })
+        .collect();
+
+    let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("uniforms"),
+        size: size_of::<Mat4>() as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform_buffer.as_entire_binding(),
+        }],
+    });
+
+    let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[
+                wgpu::VertexBufferLayout {
+                    array_stride: size_of::<Vertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x3,
+                        1 => Float32x3,
+                        2 => Unorm8x4,
+                    ],
+                },
+                wgpu::VertexBufferLayout {
+                    array_stride: size_of::<GpuInstance>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    // The model id and padding that follow are not needed.
+                    attributes: &wgpu::vertex_attr_array![
+                        3 => Float32x4,
+                        4 => Float32x4,
+                        5 => Float32x4,
+                        6 => Float32x4,
+                    ],
+                },
+            ],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            // Some rotations of the scene graph are reflections, which flip
+            // the winding of the triangles of their instances.
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: Default::default(),
+            bias: Default::default(),
+        }),
+        multisample: Default::default(),
+        multiview: None,
+    });
+
+    let (min, max) = bounds(&data);
+    let center = (min + max) / 2.0;
+    let radius = (max - min).length().max(1.0);
+    let start = Instant::now();
+
+    event_loop
+        .run(move |event, target| {
+            let Event::WindowEvent { event, .. } = event else {
+                return;
+            };
+            match event {
+                WindowEvent::CloseRequested => target.exit(),
+                WindowEvent::Resized(size) => {
+                    config.width = size.width.max(1);
+                    config.height = size.height.max(1);
+                    surface.configure(&device, &config);
+                    depth = depth_view(&device, config.width, config.height);
+                }
+                WindowEvent::RedrawRequested => {
+                    // Orbit around the scene in Z up world space.
+                    let angle = start.elapsed().as_secs_f32() * 0.5;
+                    let eye = center
+                        + Vec3::new(angle.cos(), angle.sin(), 0.6).normalize() * radius * 1.2;
+                    let view = Mat4::look_at_rh(
+                        Z_UP_TO_Y_UP.transform_point3(eye),
+                        Z_UP_TO_Y_UP.transform_point3(center),
+                        Vec3::Y,
+                    ) * Z_UP_TO_Y_UP;
+                    let aspect = config.width as f32 / config.height as f32;
+                    let projection =
+                        Mat4::perspective_rh(50f32.to_radians(), aspect, 0.1, radius * 10.0);
+                    queue.write_buffer(
+                        &uniform_buffer,
+                        0,
+                        bytemuck::bytes_of(&(projection * view)),
+                    );
+
+                    let frame = surface.get_current_texture().unwrap();
+                    let view = frame
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default());
+                    let mut encoder = device.create_command_encoder(&Default::default());
+                    {
+                        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                            label: None,
+                            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                                view: &view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                                        r: 0.02,
+                                        g: 0.02,
+                                        b: 0.03,
+                                        a: 1.0,
+                                    }),
+                                    store: wgpu::StoreOp::Store,
+                                },
+                            })],
+                            depth_stencil_attachment: Some(
+                                wgpu::RenderPassDepthStencilAttachment {
+                                    view: &depth,
+                                    depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(1.0),
+                                        store: wgpu::StoreOp::Store,
+                                    }),
+                                    stencil_ops: None,
+                                },
+                            ),
+                            timestamp_writes: None,
+                            occlusion_query_set: None,
+                        });
+                        pass.set_pipeline(&pipeline);
+                        pass.set_bind_group(0, &bind_group, &[]);
+                        pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                        for mesh in meshes.iter() {
+                            pass.set_vertex_buffer(0, mesh.vertices.slice(..));
+                            pass.set_index_buffer(
+                                mesh.indices.slice(..),
+                                wgpu::IndexFormat::Uint32,
+                            );
+                            pass.draw_indexed(0..mesh.index_count, 0, mesh.instances.clone());
+                        }
+                    }
+                    queue.submit([encoder.finish()]);
+                    frame.present();
+                    window.request_redraw();
+                }
+                _ => {}
+            }
+        })
+        .unwrap();
+}
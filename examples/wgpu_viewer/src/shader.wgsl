@@ -0,0 +1,52 @@
+struct Uniforms {
+    view_projection: mat4x4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+struct VertexInput {
+    // Model space, Z up, as stored in the file.
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+// `dot_vox::gpu::GpuInstance`, one column of the transform per attribute.
+struct InstanceInput {
+    @location(3) transform_0: vec4<f32>,
+    @location(4) transform_1: vec4<f32>,
+    @location(5) transform_2: vec4<f32>,
+    @location(6) transform_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let transform = mat4x4<f32>(
+        instance.transform_0,
+        instance.transform_1,
+        instance.transform_2,
+        instance.transform_3,
+    );
+    var out: VertexOutput;
+    out.clip_position = uniforms.view_projection * transform * vec4<f32>(vertex.position, 1.0);
+    out.normal = (transform * vec4<f32>(vertex.normal, 0.0)).xyz;
+    out.color = vertex.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // World space is still Z up here: light from above and to the side.
+    let light = normalize(vec3<f32>(0.4, 0.3, 1.0));
+    let diffuse = 0.35 + 0.65 * max(dot(normalize(in.normal), light), 0.0);
+    // Palette colors are sRGB, while the surface expects linear values.
+    let albedo = pow(in.color.rgb, vec3<f32>(2.2));
+    return vec4<f32>(albedo * diffuse, 1.0);
+}
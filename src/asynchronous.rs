@@ -0,0 +1,118 @@
+use crate::{load_bytes_with, DotVoxData, Error, ParseOptions};
+use std::{io, path::Path};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Loads a `.vox` file without blocking the async executor. Requires the
+/// `async` feature and a tokio runtime.
+///
+/// The file is read asynchronously, then parsed on tokio's blocking thread
+/// pool, as parsing large files takes a while.
+///
+/// # Errors
+///
+/// Returns an [`Error`] describing why the file could not be read or parsed.
+pub async fn load_async(path: impl AsRef<Path>) -> Result<DotVoxData, Error> {
+    let bytes = tokio::fs::read(path).await?;
+    parse_blocking(bytes, ParseOptions::default()).await
+}
+
+/// Reads a `.vox` file from `reader` without blocking the async executor.
+/// Requires the `async` feature and a tokio runtime.
+///
+/// The reader is read until the end, then its content parsed on tokio's
+/// blocking thread pool.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if reading fails or the data could not be parsed.
+pub async fn load_reader_async<R: AsyncRead + Unpin>(reader: R) -> Result<DotVoxData, Error> {
+    load_reader_async_with(reader, ParseOptions::default()).await
+}
+
+/// Reads a `.vox` file from `reader`, using the given [`ParseOptions`]. See
+/// [`load_reader_async`].
+///
+/// # Errors
+///
+/// Returns an [`Error`] if reading fails, or as
+/// [`load_bytes_with`](crate::load_bytes_with) if the data could not be
+/// parsed.
+pub async fn load_reader_async_with<R: AsyncRead + Unpin>(
+    mut reader: R,
+    options: ParseOptions,
+) -> Result<DotVoxData, Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    parse_blocking(bytes, options).await
+}
+
+impl DotVoxData {
+    /// Serializes `self` in the `.vox` format to `writer` without blocking the
+    /// async executor. Requires the `async` feature.
+    ///
+    /// The file is serialized in memory as by
+    /// [`to_bytes`](DotVoxData::to_bytes), then written asynchronously and
+    /// flushed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails, or as
+    /// [`write_vox`](DotVoxData::write_vox) if `self` cannot be serialized.
+    pub async fn write_vox_async<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> io::Result<()> {
+        let bytes = self.to_bytes()?;
+        writer.write_all(&bytes).await?;
+        writer.flush().await
+    }
+}
+
+async fn parse_blocking(bytes: Vec<u8>, options: ParseOptions) -> Result<DotVoxData, Error> {
+    match tokio::task::spawn_blocking(move || load_bytes_with(&bytes, options)).await {
+        Ok(result) => result,
+        Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+        Err(error) => Err(io::Error::from(error).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn async_loading_matches_load() {
+        let expected = crate::load("src/resources/placeholder.vox").unwrap();
+        let loaded = block_on(load_async("src/resources/placeholder.vox")).unwrap();
+        assert_eq!(loaded, expected);
+
+        let bytes = include_bytes!("resources/placeholder.vox");
+        let read = block_on(load_reader_async(&bytes[..])).unwrap();
+        assert_eq!(read, expected);
+    }
+
+    #[test]
+    fn async_writing_matches_write_vox() {
+        let data = crate::load("src/resources/placeholder.vox").unwrap();
+        let mut written = Vec::new();
+        block_on(data.write_vox_async(&mut written)).unwrap();
+        assert_eq!(written, data.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn async_loading_reports_errors() {
+        assert!(matches!(
+            block_on(load_async("src/resources/not_here.vox")),
+            Err(Error::Io(_))
+        ));
+        let bytes = include_bytes!("resources/not_a.vox");
+        assert!(matches!(
+            block_on(load_reader_async(&bytes[..])),
+            Err(Error::InvalidMagic)
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{DotVoxData, Error, ParseOptions, Warning};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// A file visited by [`load_dir`], along with the result of loading it.
+#[derive(Debug)]
+pub struct LoadDirEntry {
+    /// The path of the file, or of a subdirectory that could not be read.
+    pub path: PathBuf,
+    /// The data loaded, or the reason the file could not be loaded.
+    pub result: Result<DotVoxData, Error>,
+    /// Problems that did not stop the file from loading, such as chunks
+    /// that could not be parsed and were skipped.
+    pub warnings: Vec<Warning>,
+}
+
+/// Options controlling which files [`load_dir`] picks up, and how they are
+/// parsed.
+#[derive(Clone, Debug)]
+pub struct LoadDirOptions {
+    /// Whether to descend into subdirectories.
+    pub recursive: bool,
+    /// File extension (without the leading dot) of the files to load. Matched
+    /// case-insensitively.
+    pub extension: String,
+    /// Options each file is parsed with. Limits apply to each file on its
+    /// own.
+    pub parse: ParseOptions,
+}
+
+impl Default for LoadDirOptions {
+    fn default() -> Self {
+        LoadDirOptions {
+            recursive: false,
+            extension: "vox".to_owned(),
+            parse: ParseOptions::default(),
+        }
+    }
+}
+
+/// Loads every `.vox` file in a directory.
+///
+/// Each matching file is read like [`load`](crate::load) does, decompressed
+/// or memory-mapped depending on the features enabled, and parsed with
+/// [`LoadDirOptions::parse`], in parallel when the `rayon` feature is
+/// enabled. A file failing to load does not stop the others from
+/// loading: the result of every file is reported alongside its path and the
+/// [`Warning`]s raised while loading it, sorted by path. Subdirectories that
+/// cannot be read while recursing are reported in the same way.
+///
+/// # Errors
+///
+/// Returns an [`io::Error`] if `path` itself cannot be read as a directory.
+///
+/// # Examples
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let entries = load_dir("src/resources", &LoadDirOptions::default()).unwrap();
+/// for entry in entries {
+///     let path = entry.path.display();
+///     match entry.result {
+///         Ok(data) => println!("{}: {} models", path, data.models.len()),
+///         Err(error) => println!("{}: {}", path, error),
+///     }
+///     for warning in entry.warnings {
+///         println!("{}: {}", path, warning);
+///     }
+/// }
+/// ```
+pub fn load_dir<P: AsRef<Path>>(
+    path: P,
+    options: &LoadDirOptions,
+) -> Result<Vec<LoadDirEntry>, io::Error> {
+    let mut files = Vec::new();
+    let mut failures = Vec::new();
+    collect_files(path.as_ref(), options, &mut files, &mut failures)?;
+
+    #[cfg(feature = "rayon")]
+    let files = files.into_par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let files = files.into_iter();
+
+    let mut entries: Vec<_> = files
+        .map(|path| {
+            let loaded = crate::parse_file(&path, |bytes| {
+                crate::load_bytes_with_warnings(bytes, &options.parse)
+            });
+            match loaded {
+                Ok((data, warnings)) => LoadDirEntry {
+                    path,
+                    result: Ok(data),
+                    warnings,
+                },
+                Err(error) => LoadDirEntry {
+                    path,
+                    result: Err(error),
+                    warnings: Vec::new(),
+                },
+            }
+        })
+        .collect();
+
+    entries.extend(failures);
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+fn collect_files(
+    dir: &Path,
+    options: &LoadDirOptions,
+    files: &mut Vec<PathBuf>,
+    failures: &mut Vec<LoadDirEntry>,
+) -> Result<(), io::Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if options.recursive {
+                if let Err(error) = collect_files(&path, options, files, failures) {
+                    failures.push(LoadDirEntry {
+                        path,
+                        result: Err(Error::Io(error)),
+                        warnings: Vec::new(),
+                    });
+                }
+            }
+        } else if has_extension(&path, &options.extension) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_every_vox_file_in_directory() {
+        let entries = load_dir("src/resources", &LoadDirOptions::default()).unwrap();
+        let names: Vec<_> = entries
+            .iter()
+            .map(|entry| entry.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "axes.vox",
+                "metal-material.vox",
+                "not_a.vox",
+                "placeholder-with-materials.vox",
+                "placeholder.vox",
+                "single-voxel-with-material.vox",
+                "transforms.vox",
+            ]
+        );
+
+        for entry in entries {
+            if entry.path.ends_with("not_a.vox") {
+                assert!(matches!(entry.result, Err(Error::InvalidMagic)));
+            } else {
+                assert!(entry.result.is_ok());
+                assert!(entry.warnings.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn warnings_are_reported_per_file() {
+        let dir = std::env::temp_dir().join(format!("dot_vox_load_dir_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut bytes = include_bytes!("resources/placeholder.vox").to_vec();
+        // Make the version newer than supported.
+        bytes[4..8].copy_from_slice(&(crate::SUPPORTED_VERSION + 1).to_le_bytes());
+        fs::write(dir.join("newer.vox"), &bytes).unwrap();
+
+        let entries = load_dir(&dir, &LoadDirOptions::default());
+        fs::remove_dir_all(&dir).unwrap();
+        let entries = entries.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].result.is_ok());
+        assert_eq!(
+            entries[0].warnings,
+            [Warning::NewerVersion(crate::SUPPORTED_VERSION + 1)]
+        );
+    }
+
+    #[test]
+    fn parse_options_apply_to_each_file() {
+        let dir =
+            std::env::temp_dir().join(format!("dot_vox_load_dir_parse_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let bytes = include_bytes!("resources/placeholder.vox");
+        fs::write(dir.join("first.vox"), bytes).unwrap();
+        fs::write(dir.join("second.vox"), bytes).unwrap();
+        let voxels = crate::load_bytes(bytes).unwrap().models[0].voxels.len();
+
+        let load = |max_total_voxels| {
+            let options = LoadDirOptions {
+                parse: ParseOptions::default().max_total_voxels(max_total_voxels),
+                ..LoadDirOptions::default()
+            };
+            load_dir(&dir, &options).unwrap()
+        };
+        let within = load(voxels);
+        let exceeded = load(voxels - 1);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(within.len(), 2);
+        assert!(within.iter().all(|entry| entry.result.is_ok()));
+        assert_eq!(exceeded.len(), 2);
+        assert!(exceeded.iter().all(|entry| matches!(
+            entry.result,
+            Err(Error::LimitExceeded(crate::Limit::TotalVoxels))
+        )));
+    }
+
+    #[test]
+    fn missing_directory_causes_error() {
+        assert!(load_dir("src/resources/not_here", &LoadDirOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use crate::{
+    model, palette,
+    parser::{self, Chunk},
+    visitor::{for_each_chunk, voxels_of},
+    ChunkKind, Color, Dict, DotVoxData, Error, Frame, Layer, Material, Model, ParseOptions,
+    RawVoxels, SceneNode, ShapeModel, Size, DEFAULT_PALETTE,
+};
+use nom::{
+    bytes::complete::take,
+    combinator::{flat_map, map_res},
+    multi::count,
+    number::complete::le_u32,
+    sequence::pair,
+    IResult,
+};
+use std::{mem::size_of, str};
+
+/// Dictionary borrowed from the input, as a list of key-value pairs in file
+/// order.
+pub type DictRef<'a> = Vec<(&'a str, &'a str)>;
+
+/// The content of a `.vox` file borrowed from its bytes, parsed by
+/// [`DotVoxDataRef::parse`].
+///
+/// Strings and voxels point into the input instead of being copied, which
+/// saves most of the allocations of [`load_bytes`](crate::load_bytes) for
+/// files with large scene graphs. Chunks that are not borrowed, such as
+/// cameras and render settings, are kept as raw bytes and decoded by
+/// [`DotVoxDataRef::to_owned`].
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let bytes = include_bytes!("resources/placeholder.vox");
+/// let data = DotVoxDataRef::parse(bytes).unwrap();
+/// assert_eq!(data.models[0].voxels.len(), 4);
+/// assert_eq!(data.to_owned(), load_bytes(bytes).unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct DotVoxDataRef<'a> {
+    /// The version number of the `.vox` file.
+    pub version: u32,
+    /// The models, see [`DotVoxData::models`].
+    pub models: Vec<ModelRef<'a>>,
+    /// The colour palette, see [`DotVoxData::palette`].
+    pub palette: Vec<Color>,
+    /// The `MATL` materials, see [`DotVoxData::materials`].
+    pub materials: Vec<MaterialRef<'a>>,
+    /// The scene graph, see [`DotVoxData::scenes`].
+    pub scenes: Vec<SceneNodeRef<'a>>,
+    /// The attributes of each layer, see [`DotVoxData::layers`].
+    pub layers: Vec<DictRef<'a>>,
+    /// Every other chunk within `MAIN`, in file order.
+    pub other_chunks: Vec<RawChunkRef<'a>>,
+    /// The kinds of the chunks read, see [`DotVoxData::chunk_order`].
+    pub chunk_order: Vec<ChunkKind>,
+}
+
+/// A model borrowed from the input, see [`Model`].
+#[derive(Clone, Debug)]
+pub struct ModelRef<'a> {
+    /// The size of the model in voxels, clamped as by
+    /// [`load_bytes`](crate::load_bytes).
+    pub size: Size,
+    /// The voxels of the model.
+    pub voxels: RawVoxels<'a>,
+}
+
+/// A material borrowed from the input, see [`Material`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MaterialRef<'a> {
+    /// The palette index this material applies to.
+    pub id: u32,
+    /// The properties of the material.
+    pub properties: DictRef<'a>,
+}
+
+/// A scene graph node borrowed from the input, see [`SceneNode`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SceneNodeRef<'a> {
+    /// Transform node.
+    Transform {
+        /// Attributes.
+        attributes: DictRef<'a>,
+        /// Attributes of each frame.
+        frames: Vec<DictRef<'a>>,
+        /// Child node of this transform node.
+        child: u32,
+        /// Layer ID.
+        layer_id: u32,
+    },
+    /// Group node.
+    Group {
+        /// Attributes.
+        attributes: DictRef<'a>,
+        /// Child nodes.
+        children: Vec<u32>,
+    },
+    /// Shape node.
+    Shape {
+        /// Attributes.
+        attributes: DictRef<'a>,
+        /// Models.
+        models: Vec<ShapeModelRef<'a>>,
+    },
+}
+
+/// A model of a shape node borrowed from the input, see [`ShapeModel`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShapeModelRef<'a> {
+    /// ID of the model.
+    pub model_id: u32,
+    /// Attributes of the model in this shape node.
+    pub attributes: DictRef<'a>,
+}
+
+/// A chunk borrowed from the input, not decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawChunkRef<'a> {
+    /// Id of the chunk.
+    pub id: &'a str,
+    /// Content of the chunk.
+    pub content: &'a [u8],
+    /// Children of the chunk.
+    pub children: &'a [u8],
+}
+
+impl<'a> DotVoxDataRef<'a> {
+    /// Parses `bytes` as a `.vox` file without copying its strings and voxels.
+    ///
+    /// Chunks that cannot be decoded are skipped, as by
+    /// [`load_bytes`](crate::load_bytes). As strings are borrowed, those that
+    /// are not valid UTF-8 make their chunk invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidMagic`] for data that is not a `.vox` file,
+    /// [`Error::UnexpectedEof`] if a chunk is cut off, or
+    /// [`Error::InvalidChunk`] if the id of a chunk is not valid UTF-8.
+    pub fn parse(bytes: &'a [u8]) -> Result<DotVoxDataRef<'a>, Error> {
+        let mut data = DotVoxDataRef {
+            version: 0,
+            models: Vec::new(),
+            palette: DEFAULT_PALETTE.to_vec(),
+            materials: Vec::new(),
+            scenes: Vec::new(),
+            layers: Vec::new(),
+            other_chunks: Vec::new(),
+            chunk_order: Vec::new(),
+        };
+        let mut size = None;
+        data.version = for_each_chunk(bytes, |id, content, children, location| {
+            let id = str::from_utf8(id).map_err(|_| location())?;
+            let decoded = match id {
+                "SIZE" => model::parse_size(content).ok().map(|(_, parsed)| {
+                    size = Some(parsed.clamped());
+                }),
+                "XYZI" => voxels_of(content).map(|voxels| {
+                    // Like the parser, skip voxels without a preceding SIZE.
+                    if let Some(size) = size {
+                        data.models.push(ModelRef { size, voxels });
+                        data.chunk_order.push(ChunkKind::Models);
+                    }
+                }),
+                "RGBA" => palette::extract_palette(content)
+                    .ok()
+                    .map(|(_, palette)| data.palette = palette),
+                "MATL" => {
+                    pair(le_u32, parse_dict_ref)(content)
+                        .ok()
+                        .map(|(_, (id, properties))| {
+                            data.materials.push(MaterialRef { id, properties })
+                        })
+                }
+                "nTRN" | "nGRP" | "nSHP" => parse_scene_node_ref(id, content)
+                    .ok()
+                    .map(|(_, node)| data.scenes.push(node)),
+                "LAYR" => parse_layer_ref(content)
+                    .ok()
+                    .map(|(_, layer)| data.layers.push(layer)),
+                _ => {
+                    data.other_chunks.push(RawChunkRef {
+                        id,
+                        content,
+                        children,
+                    });
+                    Some(())
+                }
+            };
+            // Models are recorded along with their voxels.
+            match ChunkKind::of(id) {
+                Some(kind) if kind != ChunkKind::Models && decoded.is_some() => {
+                    data.chunk_order.push(kind)
+                }
+                _ => {}
+            }
+            if decoded.is_none() {
+                let location = location();
+                event!(
+                    warn,
+                    { chunk_id = location.id, offset = location.offset },
+                    "Unable to parse chunk"
+                );
+            }
+            Ok(())
+        })?;
+        Ok(data)
+    }
+
+    /// Copies everything into a [`DotVoxData`], equal to what
+    /// [`load_bytes`](crate::load_bytes) returns for the same input.
+    pub fn to_owned(&self) -> DotVoxData {
+        let options = ParseOptions::default();
+        let others = self
+            .other_chunks
+            .iter()
+            .filter_map(|chunk| {
+                parser::decode_child(chunk.id, chunk.content, chunk.children, &options)
+            })
+            .collect();
+        let mut data = parser::map_chunk_to_data(self.version, Chunk::Main(others), &options);
+
+        data.models = self
+            .models
+            .iter()
+            .map(|model| Model {
+                size: model.size,
+                voxels: model.voxels.clone().collect(),
+            })
+            .collect();
+        data.palette = self.palette.clone();
+        // Legacy `MATT` materials are among the other chunks.
+        let legacy = std::mem::take(&mut data.materials);
+        data.materials = self
+            .materials
+            .iter()
+            .map(|material| Material {
+                id: material.id,
+                properties: to_dict(&material.properties),
+            })
+            .chain(legacy)
+            .collect();
+        data.scenes = self.scenes.iter().map(SceneNodeRef::to_owned).collect();
+        data.layers = self
+            .layers
+            .iter()
+            .map(|attributes| Layer {
+                attributes: to_dict(attributes),
+            })
+            .collect();
+        data.chunk_order = ChunkKind::shortest(self.chunk_order.clone());
+        data.synthesize_scene();
+        data
+    }
+}
+
+impl From<&DotVoxDataRef<'_>> for DotVoxData {
+    fn from(data: &DotVoxDataRef<'_>) -> Self {
+        data.to_owned()
+    }
+}
+
+impl SceneNodeRef<'_> {
+    /// Copies this node into a [`SceneNode`].
+    pub fn to_owned(&self) -> SceneNode {
+        match self {
+            SceneNodeRef::Transform {
+                attributes,
+                frames,
+                child,
+                layer_id,
+            } => SceneNode::Transform {
+                attributes: to_dict(attributes),
+                frames: frames
+                    .iter()
+                    .map(|frame| Frame::new(to_dict(frame)))
+                    .collect(),
+                child: *child,
+                layer_id: *layer_id,
+            },
+            SceneNodeRef::Group {
+                attributes,
+                children,
+            } => SceneNode::Group {
+                attributes: to_dict(attributes),
+                children: children.clone(),
+            },
+            SceneNodeRef::Shape { attributes, models } => SceneNode::Shape {
+                attributes: to_dict(attributes),
+                models: models
+                    .iter()
+                    .map(|model| ShapeModel {
+                        model_id: model.model_id,
+                        attributes: to_dict(&model.attributes),
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+fn to_dict(dict: &DictRef<'_>) -> Dict {
+    dict.iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn parse_str_ref(i: &[u8]) -> IResult<&[u8], &str> {
+    map_res(flat_map(le_u32, take), str::from_utf8)(i)
+}
+
+fn parse_dict_ref(i: &[u8]) -> IResult<&[u8], DictRef<'_>> {
+    let (i, n) = le_u32(i)?;
+    let n = parser::validate_count(i, n, size_of::<u32>() * 2)?;
+    count(pair(parse_str_ref, parse_str_ref), n)(i)
+}
+
+fn parse_u32s(i: &[u8]) -> IResult<&[u8], Vec<u32>> {
+    let (i, n) = le_u32(i)?;
+    let n = parser::validate_count(i, n, size_of::<u32>())?;
+    count(le_u32, n)(i)
+}
+
+fn parse_scene_node_ref<'a>(id: &str, i: &'a [u8]) -> IResult<&'a [u8], SceneNodeRef<'a>> {
+    let (i, (_node_id, attributes)) = pair(le_u32, parse_dict_ref)(i)?;
+    match id {
+        "nTRN" => {
+            let (i, (child, _reserved)) = pair(le_u32, le_u32)(i)?;
+            let (i, layer_id) = le_u32(i)?;
+            let (i, frame_count) = le_u32(i)?;
+            let frame_count = parser::validate_count(i, frame_count, size_of::<u32>())?;
+            let (i, frames) = count(parse_dict_ref, frame_count)(i)?;
+            let node = SceneNodeRef::Transform {
+                attributes,
+                frames,
+                child,
+                layer_id,
+            };
+            Ok((i, node))
+        }
+        "nGRP" => {
+            let (i, children) = parse_u32s(i)?;
+            Ok((
+                i,
+                SceneNodeRef::Group {
+                    attributes,
+                    children,
+                },
+            ))
+        }
+        _ => {
+            let (i, model_count) = le_u32(i)?;
+            let model_count = parser::validate_count(i, model_count, size_of::<u32>() * 2)?;
+            let (i, models) = count(
+                |i| {
+                    let (i, (model_id, attributes)) = pair(le_u32, parse_dict_ref)(i)?;
+                    Ok((
+                        i,
+                        ShapeModelRef {
+                            model_id,
+                            attributes,
+                        },
+                    ))
+                },
+                model_count,
+            )(i)?;
+            Ok((i, SceneNodeRef::Shape { attributes, models }))
+        }
+    }
+}
+
+fn parse_layer_ref(i: &[u8]) -> IResult<&[u8], DictRef<'_>> {
+    let (i, (_id, attributes)) = pair(le_u32, parse_dict_ref)(i)?;
+    let (i, _reserved) = le_u32(i)?;
+    Ok((i, attributes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_owned_matches_load_bytes() {
+        for file in [
+            &include_bytes!("resources/placeholder-with-materials.vox")[..],
+            include_bytes!("resources/transforms.vox"),
+            include_bytes!("resources/axes.vox"),
+            include_bytes!("resources/metal-material.vox"),
+        ] {
+            let data = DotVoxDataRef::parse(file).unwrap();
+            assert_eq!(data.to_owned(), crate::load_bytes(file).unwrap());
+        }
+    }
+
+    #[test]
+    fn strings_are_borrowed_from_the_input() {
+        let bytes = include_bytes!("resources/transforms.vox");
+        let data = DotVoxDataRef::parse(bytes).unwrap();
+        let range = bytes.as_ptr_range();
+        let SceneNodeRef::Transform { frames, .. } = &data.scenes[2] else {
+            panic!("Expected a transform node, got {:?}", data.scenes[2]);
+        };
+        let (key, value) = frames[0][0];
+        assert!(range.contains(&key.as_ptr()) && range.contains(&value.as_ptr()));
+        assert!(range.contains(&data.models[0].voxels.as_bytes().as_ptr()));
+    }
+
+    #[test]
+    fn parse_reports_malformed_files() {
+        assert!(matches!(
+            DotVoxDataRef::parse(include_bytes!("resources/not_a.vox")),
+            Err(Error::InvalidMagic)
+        ));
+        let bytes = include_bytes!("resources/placeholder.vox");
+        assert!(matches!(
+            DotVoxDataRef::parse(&bytes[..bytes.len() - 1]),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+}
@@ -0,0 +1,418 @@
+use crate::{
+    prefab, Color, Dict, DotVoxData, Frame, Material, Model, Position, Rotation, SceneNode,
+    ShapeModel,
+};
+use std::{error, fmt};
+
+/// Builds a [`DotVoxData`] from scratch, created by [`DotVoxData::builder`].
+///
+/// The data starts as [`DotVoxData::new`]: version 150 with the
+/// [`DEFAULT_PALETTE`](crate::DEFAULT_PALETTE) and a scene graph made of a root transform and group
+/// node. Each model added is placed under that group.
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let data = DotVoxData::builder()
+///     .add_layer("ground", None)
+///     .add_model(Model {
+///         size: Size { x: 2, y: 2, z: 1 },
+///         voxels: vec![Voxel { x: 0, y: 1, z: 0, i: 3 }],
+///     })
+///     .build();
+/// assert_eq!(data.models.len(), 1);
+/// assert_eq!(data.instances().len(), 1);
+/// assert!(data.validate().is_empty());
+/// ```
+#[derive(Clone, Debug)]
+pub struct DotVoxBuilder {
+    data: DotVoxData,
+    root_group: usize,
+}
+
+impl DotVoxData {
+    /// Returns a builder for data made from scratch, see [`DotVoxBuilder`].
+    pub fn builder() -> DotVoxBuilder {
+        let mut data = DotVoxData::new();
+        let root_group = prefab::root_group(&mut data).expect("new data has a root group");
+        DotVoxBuilder { data, root_group }
+    }
+}
+
+impl DotVoxBuilder {
+    /// Sets the version number of the file.
+    pub fn version(mut self, version: u32) -> Self {
+        self.data.version = version;
+        self
+    }
+
+    /// Adds a model with its minimum corner at the origin, as MagicaVoxel
+    /// places models of files without a scene graph.
+    pub fn add_model(self, model: Model) -> Self {
+        let translation = Position {
+            x: (model.size.x / 2) as i32,
+            y: (model.size.y / 2) as i32,
+            z: (model.size.z / 2) as i32,
+        };
+        self.add_model_at(model, translation, Rotation::IDENTITY)
+    }
+
+    /// Adds a model whose center is moved to `translation` and rotated by
+    /// `rotation`, like the frames of transform nodes.
+    pub fn add_model_at(mut self, model: Model, translation: Position, rotation: Rotation) -> Self {
+        self.data.models.push(model);
+        let model_id = (self.data.models.len() - 1) as u32;
+        prefab::place_model(
+            &mut self.data,
+            self.root_group,
+            model_id,
+            translation,
+            rotation,
+        );
+        self
+    }
+
+    /// Replaces the palette.
+    pub fn set_palette(mut self, palette: Vec<Color>) -> Self {
+        self.data.palette = palette;
+        self
+    }
+
+    /// Appends a visible layer, as by [`Layers::add`](crate::Layers::add).
+    /// Models added afterwards are placed on the first layer.
+    pub fn add_layer(mut self, name: &str, color: Option<Color>) -> Self {
+        self.data.layers_mut().add(name, color);
+        self
+    }
+
+    /// Adds a material, replacing any previous one with the same id.
+    pub fn add_material(mut self, material: Material) -> Self {
+        self.data.materials.retain(|other| other.id != material.id);
+        self.data.materials.push(material);
+        self
+    }
+
+    /// Returns the data built.
+    pub fn build(self) -> DotVoxData {
+        self.data
+    }
+}
+
+/// Error returned when nodes cannot be added to a scene graph, by
+/// [`SceneBuilder`], [`Prefab::instantiate_into`](crate::Prefab::instantiate_into)
+/// and [`scatter`](crate::scatter).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// The parent is not a node of the scene graph.
+    MissingParent {
+        /// The missing parent.
+        parent: u32,
+    },
+    /// The node is not a transform node leading to a group node, so nothing
+    /// can be added under it.
+    NotAGroup {
+        /// Index of the node.
+        node_id: u32,
+    },
+    /// The density given to [`scatter`](crate::scatter) is not a positive
+    /// number.
+    InvalidDensity,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingParent { parent } => {
+                write!(f, "parent node {} is not part of the scene graph", parent)
+            }
+            BuildError::NotAGroup { node_id } => {
+                write!(f, "node {} does not lead to a group node", node_id)
+            }
+            BuildError::InvalidDensity => write!(f, "density must be positive"),
+        }
+    }
+}
+
+impl error::Error for BuildError {}
+
+/// Builds a scene graph, taking care of node indices.
+///
+/// Every node added is a transform node, holding its translation, rotation,
+/// name and layer, with either a group or a shape node as its child. Nodes
+/// are referred to by the index of their transform node, starting with
+/// [`SceneBuilder::ROOT`].
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let mut data = load("src/resources/placeholder.vox").unwrap();
+/// let mut scene = SceneBuilder::new();
+/// let group = scene.add_group(SceneBuilder::ROOT).unwrap();
+/// scene.set_name(group, "pair");
+/// scene.set_translation(group, Position { x: 10, y: 0, z: 0 });
+/// scene.add_shape(group, 0).unwrap();
+/// let shape = scene.add_shape(group, 0).unwrap();
+/// scene.set_rotation(shape, Rotation::from_byte(0b0010001));
+///
+/// data.scenes = scene.build();
+/// data.synthetic_scene = false;
+/// assert!(data.validate().is_empty());
+/// assert_eq!(data.instances().len(), 2);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SceneBuilder {
+    scenes: Vec<SceneNode>,
+}
+
+impl Default for SceneBuilder {
+    fn default() -> Self {
+        SceneBuilder::new()
+    }
+}
+
+impl SceneBuilder {
+    /// The root node, a transform node with a group node as its child.
+    pub const ROOT: u32 = 0;
+
+    /// Creates a scene graph holding only the root node.
+    pub fn new() -> Self {
+        SceneBuilder {
+            scenes: vec![
+                prefab::transform_node(Dict::new(), 1, u32::MAX),
+                SceneNode::Group {
+                    attributes: Dict::new(),
+                    children: Vec::new(),
+                },
+            ],
+        }
+    }
+
+    /// Adds a group node under the group node `parent`, and returns its id.
+    /// Fails with [`BuildError::MissingParent`] if `parent` is not a node of
+    /// this scene graph, or [`BuildError::NotAGroup`] if it is a shape.
+    pub fn add_group(&mut self, parent: u32) -> Result<u32, BuildError> {
+        self.add(
+            parent,
+            SceneNode::Group {
+                attributes: Dict::new(),
+                children: Vec::new(),
+            },
+        )
+    }
+
+    /// Adds a shape node placing the model `model_id` under the group node
+    /// `parent`, and returns its id. Fails like
+    /// [`add_group`](SceneBuilder::add_group).
+    pub fn add_shape(&mut self, parent: u32, model_id: u32) -> Result<u32, BuildError> {
+        self.add(
+            parent,
+            SceneNode::Shape {
+                attributes: Dict::new(),
+                models: vec![ShapeModel {
+                    model_id,
+                    attributes: Dict::new(),
+                }],
+            },
+        )
+    }
+
+    /// Moves the node `node` by `translation` relative to its parent.
+    /// Returns `false` if there is no such node.
+    pub fn set_translation(&mut self, node: u32, translation: Position) -> bool {
+        self.edit_frame(node, |frame| frame.with_position(translation))
+    }
+
+    /// Rotates the node `node` by `rotation` relative to its parent. Returns
+    /// `false` if there is no such node.
+    pub fn set_rotation(&mut self, node: u32, rotation: Rotation) -> bool {
+        self.edit_frame(node, |frame| frame.with_orientation(rotation))
+    }
+
+    /// Names the node `node`, as shown by MagicaVoxel. Returns `false` if
+    /// there is no such node.
+    pub fn set_name(&mut self, node: u32, name: &str) -> bool {
+        match self.scenes.get_mut(node as usize) {
+            Some(node @ SceneNode::Transform { .. }) => {
+                node.set_name(name);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Puts the node `node` on the layer `layer_id`, an index into
+    /// [`DotVoxData::layers`]. Nodes are on no layer by default. Returns
+    /// `false` if there is no such node.
+    pub fn set_layer(&mut self, node: u32, layer_id: u32) -> bool {
+        match self.scenes.get_mut(node as usize) {
+            Some(SceneNode::Transform {
+                layer_id: layer, ..
+            }) => {
+                *layer = layer_id;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the nodes built, for [`DotVoxData::scenes`].
+    pub fn build(self) -> Vec<SceneNode> {
+        self.scenes
+    }
+
+    fn add(&mut self, parent: u32, node: SceneNode) -> Result<u32, BuildError> {
+        let group = match self.scenes.get(parent as usize) {
+            Some(SceneNode::Transform { child, .. }) => *child as usize,
+            _ => return Err(BuildError::MissingParent { parent }),
+        };
+        let id = self.scenes.len() as u32;
+        match self.scenes.get_mut(group) {
+            Some(SceneNode::Group { children, .. }) => children.push(id),
+            _ => return Err(BuildError::NotAGroup { node_id: parent }),
+        }
+        self.scenes
+            .push(prefab::transform_node(Dict::new(), id + 1, u32::MAX));
+        self.scenes.push(node);
+        Ok(id)
+    }
+
+    fn edit_frame(&mut self, node: u32, edit: impl FnOnce(Frame) -> Frame) -> bool {
+        match self.scenes.get_mut(node as usize) {
+            Some(SceneNode::Transform { frames, .. }) => {
+                if frames.is_empty() {
+                    frames.push(Frame::default());
+                }
+                frames[0] = edit(std::mem::take(&mut frames[0]));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Size, Voxel, DEFAULT_PALETTE};
+
+    fn model(x: u8) -> Model {
+        Model {
+            size: Size { x: 4, y: 4, z: 4 },
+            voxels: vec![Voxel {
+                x,
+                y: 0,
+                z: 0,
+                i: 1,
+            }],
+        }
+    }
+
+    #[test]
+    fn empty_builder_has_a_root_group() {
+        let data = DotVoxData::builder().build();
+        assert_eq!(data.version, 150);
+        assert_eq!(data.palette, DEFAULT_PALETTE.to_vec());
+        assert_eq!(data.scenes.len(), 2);
+        assert!(matches!(
+            data.scenes[0],
+            SceneNode::Transform {
+                child: 1,
+                layer_id: u32::MAX,
+                ..
+            }
+        ));
+        assert!(
+            matches!(&data.scenes[1], SceneNode::Group { children, .. } if children.is_empty())
+        );
+        assert!(data.validate().is_empty());
+    }
+
+    #[test]
+    fn built_data_round_trips() {
+        let mut properties = Dict::new();
+        properties.insert("_type".to_owned(), "_emit".to_owned());
+        let material = Material { id: 2, properties };
+        let data = DotVoxData::builder()
+            .add_layer("first", None)
+            .add_model(model(0))
+            .add_model_at(model(3), Position { x: 10, y: 0, z: 0 }, Rotation::IDENTITY)
+            .add_material(material.clone())
+            .add_material(material)
+            .build();
+        assert_eq!(data.materials.len(), 1);
+        assert!(data.validate().is_empty());
+
+        let positions: Vec<_> = data.world_voxels().map(|voxel| voxel.position).collect();
+        assert_eq!(
+            positions,
+            [
+                Position { x: 0, y: 0, z: 0 },
+                Position {
+                    x: 11,
+                    y: -2,
+                    z: -2
+                }
+            ]
+        );
+
+        let reloaded = crate::load_bytes(&data.to_bytes().unwrap()).unwrap();
+        assert_eq!(reloaded.models, data.models);
+        assert_eq!(reloaded.scenes, data.scenes);
+        assert_eq!(reloaded.layers, data.layers);
+    }
+
+    #[test]
+    fn scene_builder_indexes_nodes() {
+        let mut scene = SceneBuilder::new();
+        let group = scene.add_group(SceneBuilder::ROOT).unwrap();
+        let shape = scene.add_shape(group, 1).unwrap();
+        let other = scene.add_shape(SceneBuilder::ROOT, 0).unwrap();
+        assert_eq!((group, shape, other), (2, 4, 6));
+        assert_eq!(
+            scene.add_shape(shape, 0),
+            Err(BuildError::NotAGroup { node_id: shape })
+        );
+        assert_eq!(
+            scene.add_shape(group + 1, 0),
+            Err(BuildError::MissingParent { parent: group + 1 })
+        );
+        assert!(scene.set_layer(shape, 0));
+        assert!(!scene.set_name(group + 1, "group"));
+
+        let scenes = scene.build();
+        assert!(
+            matches!(&scenes[1], SceneNode::Group { children, .. } if children == &[group, other])
+        );
+        assert!(matches!(&scenes[3], SceneNode::Group { children, .. } if children == &[shape]));
+        assert!(matches!(
+            &scenes[5],
+            SceneNode::Shape { models, .. } if models[0].model_id == 1
+        ));
+        assert!(matches!(
+            scenes[4],
+            SceneNode::Transform { layer_id: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn scene_builder_sets_transforms() {
+        let mut scene = SceneBuilder::new();
+        let group = scene.add_group(SceneBuilder::ROOT).unwrap();
+        let shape = scene.add_shape(group, 0).unwrap();
+        let translation = Position { x: 5, y: -3, z: 8 };
+        let rotation = Rotation::from_byte(0b0010001);
+        assert!(scene.set_translation(group, translation));
+        assert!(scene.set_rotation(shape, rotation));
+        assert!(scene.set_name(group, "group"));
+
+        let mut data = DotVoxData::builder().add_model(model(0)).build();
+        data.scenes = scene.build();
+        assert!(data.validate().is_empty());
+        let instances: Vec<_> = data.instances().collect();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].translation, translation);
+        assert_eq!(instances[0].rotation, rotation);
+        assert_eq!(data.scenes[group as usize].name().as_deref(), Some("group"));
+    }
+}
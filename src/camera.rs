@@ -0,0 +1,258 @@
+use crate::{
+    parser::{parse_dict, ParseOptions},
+    Dict,
+};
+use nom::{number::complete::le_u32, sequence::pair, IResult};
+
+/// A viewport camera, from an `rCAM` chunk.
+///
+/// The typed accessors return `None` when the attribute is missing or
+/// malformed. Angles are in degrees.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    /// ID of this camera.
+    pub id: u32,
+    /// Attributes of this camera.
+    pub attributes: Dict,
+}
+
+impl Eq for Camera {}
+
+impl Camera {
+    /// The `_mode` attribute, e.g. `pers` for perspective or `orth` for
+    /// orthographic.
+    pub fn mode(&self) -> Option<&str> {
+        self.attributes.get("_mode").map(String::as_str)
+    }
+
+    /// Whether this camera uses an orthographic projection (`orth` or `iso`
+    /// modes).
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self.mode(), Some("orth" | "iso"))
+    }
+
+    /// The `_focus` attribute: the point the camera orbits around.
+    pub fn focus(&self) -> Option<[f32; 3]> {
+        self.get_f32s("_focus")
+    }
+
+    /// The `_angle` attribute: pitch, yaw and roll of the camera.
+    pub fn angle(&self) -> Option<[f32; 3]> {
+        self.get_f32s("_angle")
+    }
+
+    /// The `_radius` attribute: distance from the camera to its focus.
+    pub fn radius(&self) -> Option<f32> {
+        self.get_f32("_radius")
+    }
+
+    /// The `_frustum` attribute: the tangent of half the vertical field of
+    /// view, also used as the half height of orthographic views at unit
+    /// distance.
+    pub fn frustum(&self) -> Option<f32> {
+        self.get_f32("_frustum")
+    }
+
+    /// The `_fov` attribute: vertical field of view.
+    pub fn fov(&self) -> Option<f32> {
+        self.get_f32("_fov")
+    }
+
+    /// Position of the camera in world space. The camera looks along +Y at
+    /// zero angles; positive pitch looks down, and positive yaw turns
+    /// towards +X.
+    pub fn eye(&self) -> [f32; 3] {
+        let focus = self.focus().unwrap_or_default();
+        let forward = self.forward();
+        let radius = self.radius().unwrap_or_default();
+        [
+            focus[0] - forward[0] * radius,
+            focus[1] - forward[1] * radius,
+            focus[2] - forward[2] * radius,
+        ]
+    }
+
+    /// Right-handed, Z up world to view matrix, as columns. The camera looks
+    /// down -Z in view space. Missing attributes default to zero.
+    pub fn to_view_matrix(&self) -> [[f32; 4]; 4] {
+        let [_, _, roll] = self.angle().unwrap_or_default().map(f32::to_radians);
+        let forward = self.forward();
+        let right = normalize(cross(forward, [0.0, 0.0, 1.0]));
+        let right = if right.iter().all(|x| x.is_finite()) {
+            right
+        } else {
+            // Looking straight up or down.
+            [1.0, 0.0, 0.0]
+        };
+        let up = cross(right, forward);
+        let (sin, cos) = roll.sin_cos();
+        let right = [0, 1, 2].map(|k| right[k] * cos + up[k] * sin);
+        let up = [0, 1, 2].map(|k| up[k] * cos - right[k] * sin);
+
+        let eye = self.eye();
+        [
+            [right[0], up[0], -forward[0], 0.0],
+            [right[1], up[1], -forward[1], 0.0],
+            [right[2], up[2], -forward[2], 0.0],
+            [-dot(right, eye), -dot(up, eye), dot(forward, eye), 1.0],
+        ]
+    }
+
+    /// Right-handed projection matrix mapping depth to `[0, 1]`, as columns.
+    /// Orthographic cameras cover `radius * frustum` above and below the
+    /// focus.
+    pub fn to_projection(&self, aspect_ratio: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+        let frustum = self
+            .frustum()
+            .or_else(|| Some((self.fov()?.to_radians() / 2.0).tan()))
+            .unwrap_or(1.0);
+        let depth = 1.0 / (near - far);
+        if self.is_orthographic() {
+            let half_height = self.radius().unwrap_or(1.0) * frustum;
+            let half_width = half_height * aspect_ratio;
+            [
+                [1.0 / half_width, 0.0, 0.0, 0.0],
+                [0.0, 1.0 / half_height, 0.0, 0.0],
+                [0.0, 0.0, depth, 0.0],
+                [0.0, 0.0, depth * near, 1.0],
+            ]
+        } else {
+            let f = 1.0 / frustum;
+            [
+                [f / aspect_ratio, 0.0, 0.0, 0.0],
+                [0.0, f, 0.0, 0.0],
+                [0.0, 0.0, far * depth, -1.0],
+                [0.0, 0.0, near * far * depth, 0.0],
+            ]
+        }
+    }
+
+    fn forward(&self) -> [f32; 3] {
+        let [pitch, yaw, _] = self.angle().unwrap_or_default().map(f32::to_radians);
+        [
+            yaw.sin() * pitch.cos(),
+            yaw.cos() * pitch.cos(),
+            -pitch.sin(),
+        ]
+    }
+
+    fn get_f32(&self, prop: &str) -> Option<f32> {
+        let value = self.attributes.get(prop)?;
+        match value.parse::<f32>() {
+            Ok(x) => Some(x),
+            Err(_) => {
+                debug!("Could not parse float for property '{}': {}", prop, value);
+                None
+            }
+        }
+    }
+
+    fn get_f32s(&self, prop: &str) -> Option<[f32; 3]> {
+        let value = self.attributes.get(prop)?;
+        let values: Option<Vec<f32>> = value.split_whitespace().map(|x| x.parse().ok()).collect();
+        match values.and_then(|values| values.try_into().ok()) {
+            Some(values) => Some(values),
+            None => {
+                debug!("Could not parse floats for property '{}': {}", prop, value);
+                None
+            }
+        }
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    v.map(|x| x / length)
+}
+
+/// Parses the content of an `rCAM` chunk.
+pub fn parse_camera<'a>(i: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], Camera> {
+    let (i, (id, attributes)) = pair(le_u32, |i| parse_dict(i, options))(i)?;
+    Ok((i, Camera { id, attributes }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(attributes: &[(&str, &str)]) -> Camera {
+        Camera {
+            id: 0,
+            attributes: attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    fn transform(matrix: [[f32; 4]; 4], point: [f32; 3]) -> [f32; 4] {
+        let point = [point[0], point[1], point[2], 1.0];
+        [0, 1, 2, 3].map(|row| (0..4).map(|col| matrix[col][row] * point[col]).sum())
+    }
+
+    fn assert_close(a: [f32; 4], b: [f32; 4]) {
+        assert!(
+            a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn reads_cameras() {
+        let data = crate::load("src/resources/axes.vox").unwrap();
+        assert_eq!(data.cameras.len(), 10);
+        let camera = &data.cameras[0];
+        assert_eq!(camera.mode(), Some("pers"));
+        assert_eq!(camera.focus(), Some([0.0; 3]));
+        assert_eq!(camera.frustum(), Some(0.414214));
+        assert_eq!(camera.fov(), Some(45.0));
+
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+        assert_eq!(crate::load_bytes(&bytes).unwrap().cameras, data.cameras);
+    }
+
+    #[test]
+    fn view_matrix_looks_at_focus() {
+        let orbit = camera(&[("_focus", "1 2 3"), ("_angle", "0 0 0"), ("_radius", "10")]);
+        assert_eq!(orbit.eye(), [1.0, -8.0, 3.0]);
+        let view = orbit.to_view_matrix();
+        assert_close(transform(view, [1.0, 2.0, 3.0]), [0.0, 0.0, -10.0, 1.0]);
+        assert_close(transform(view, [2.0, 2.0, 4.0]), [1.0, 1.0, -10.0, 1.0]);
+
+        let view = camera(&[("_angle", "0 90 0"), ("_radius", "10")]).to_view_matrix();
+        assert_close(transform(view, [0.0, 0.0, 0.0]), [0.0, 0.0, -10.0, 1.0]);
+        assert_close(transform(view, [0.0, -1.0, 0.0]), [1.0, 0.0, -10.0, 1.0]);
+    }
+
+    #[test]
+    fn projections_map_depth_to_unit_range() {
+        let perspective = camera(&[("_mode", "pers"), ("_frustum", "1")]);
+        let projection = perspective.to_projection(2.0, 1.0, 100.0);
+        let near = transform(projection, [2.0, 1.0, -1.0]);
+        assert_close(near.map(|x| x / near[3]), [1.0, 1.0, 0.0, 1.0]);
+        let far = transform(projection, [0.0, 0.0, -100.0]);
+        assert_close(far.map(|x| x / far[3]), [0.0, 0.0, 1.0, 1.0]);
+
+        let orthographic = camera(&[("_mode", "orth"), ("_frustum", "0.5"), ("_radius", "4")]);
+        let projection = orthographic.to_projection(1.0, 0.0, 10.0);
+        assert_close(
+            transform(projection, [2.0, -2.0, -10.0]),
+            [1.0, -1.0, 1.0, 1.0],
+        );
+    }
+}
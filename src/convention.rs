@@ -0,0 +1,230 @@
+use crate::{Axis, DotVoxData, Rotation, SceneNode, Size};
+
+/// A coordinate system to convert data to with [`DotVoxData::convert_axes`].
+/// Each variant maps MagicaVoxel's `(x, y, z)` to the coordinates listed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AxisConvention {
+    /// Right-handed, Z up, as MagicaVoxel: `(x, y, z)`.
+    RightHandedZUp,
+    /// Right-handed, Y up, as glTF: `(x, z, -y)`.
+    RightHandedYUp,
+    /// Left-handed, Y up, as Unity: `(x, z, y)`.
+    LeftHandedYUp,
+    /// Left-handed, Z up: `(x, -y, z)`.
+    LeftHandedZUp,
+}
+
+impl AxisConvention {
+    /// The change of basis from MagicaVoxel's coordinates to this convention.
+    pub fn rotation(self) -> Rotation {
+        Rotation::from_byte(match self {
+            AxisConvention::RightHandedZUp => 0b0000100,
+            AxisConvention::RightHandedYUp => 0b1001000,
+            AxisConvention::LeftHandedYUp => 0b0001000,
+            AxisConvention::LeftHandedZUp => 0b0100100,
+        })
+    }
+}
+
+impl DotVoxData {
+    /// Rewrites data in MagicaVoxel's right-handed, Z up coordinates into
+    /// `convention`: the voxels and sizes of models, and the `_t` and `_r`
+    /// fields of every frame of the scene graph. Cameras are left as they
+    /// are.
+    ///
+    /// Models are permuted and mirrored like the world, so transform nodes
+    /// keep rotations that are not reflections. The scene graph is written
+    /// afterwards, even if it was [synthetic](DotVoxData::synthetic_scene).
+    ///
+    /// Voxel centers are mapped exactly. Along negated axes, the integer
+    /// positions given by [`world_voxels`](DotVoxData::world_voxels) of models
+    /// of odd size may differ by one from those of models of even size, as
+    /// half-voxel offsets are rounded down.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut data = load("src/resources/placeholder.vox").unwrap();
+    /// let size = data.models[0].size;
+    /// data.convert_axes(AxisConvention::RightHandedYUp);
+    /// assert_eq!(data.models[0].size, Size { x: size.x, y: size.z, z: size.y });
+    /// ```
+    pub fn convert_axes(&mut self, convention: AxisConvention) {
+        self.change_basis(convention.rotation());
+    }
+
+    /// Mirrors the data across the plane through the origin perpendicular to
+    /// `axis`, as [`convert_axes`](DotVoxData::convert_axes) does: voxels
+    /// within each model are flipped along `axis`, and the `_t` and `_r`
+    /// fields of the scene graph follow.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let original = load("src/resources/placeholder.vox").unwrap();
+    /// let mut data = original.clone();
+    /// data.mirror(Axis::X);
+    /// data.mirror(Axis::X);
+    /// assert_eq!(data.models, original.models);
+    /// ```
+    pub fn mirror(&mut self, axis: Axis) {
+        self.change_basis(Rotation::from_byte(match axis {
+            Axis::X => 0b0010100,
+            Axis::Y => 0b0100100,
+            Axis::Z => 0b1000100,
+        }));
+    }
+
+    /// Moves everything into the coordinates given by `basis`, as a matrix
+    /// applied to MagicaVoxel's coordinates.
+    fn change_basis(&mut self, basis: Rotation) {
+        if basis == Rotation::IDENTITY {
+            return;
+        }
+        let inverse = basis.inverse();
+
+        for model in &mut self.models {
+            let size = [model.size.x, model.size.y, model.size.z].map(|s| s as i32);
+            let converted = basis.transform_point(size).map(i32::abs);
+            for voxel in &mut model.voxels {
+                // Doubled offsets from the center of the model, as by
+                // `Transform::voxel_position`, are mapped exactly.
+                let voxel_position = [voxel.x, voxel.y, voxel.z];
+                let doubled_offset = basis
+                    .transform_point([0, 1, 2].map(|k| 2 * voxel_position[k] as i32 + 1 - size[k]));
+                let [x, y, z] =
+                    [0, 1, 2].map(|k| ((doubled_offset[k] + converted[k] - 1) / 2) as u8);
+                (voxel.x, voxel.y, voxel.z) = (x, y, z);
+            }
+            let [x, y, z] = converted.map(|s| s as u32);
+            model.size = Size { x, y, z };
+        }
+
+        for node in &mut self.scenes {
+            let SceneNode::Transform { frames, .. } = node else {
+                continue;
+            };
+            for frame in frames {
+                if let Some(position) = frame.position() {
+                    *frame = std::mem::take(frame).with_position(basis * position);
+                }
+                if let Some(rotation) = frame.orientation() {
+                    *frame = std::mem::take(frame).with_orientation(basis * rotation * inverse);
+                }
+            }
+        }
+        self.synthetic_scene = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    const CONVENTIONS: [AxisConvention; 4] = [
+        AxisConvention::RightHandedZUp,
+        AxisConvention::RightHandedYUp,
+        AxisConvention::LeftHandedYUp,
+        AxisConvention::LeftHandedZUp,
+    ];
+
+    /// Doubled world space centers of every voxel, in placement order.
+    fn doubled_centers(data: &DotVoxData) -> Vec<[i32; 3]> {
+        let mut centers = Vec::new();
+        for instance in data.instances() {
+            let model = &data.models[instance.model_id as usize];
+            for voxel in &model.voxels {
+                let offset = instance.rotation.transform_point([
+                    2 * voxel.x as i32 + 1 - model.size.x as i32,
+                    2 * voxel.y as i32 + 1 - model.size.y as i32,
+                    2 * voxel.z as i32 + 1 - model.size.z as i32,
+                ]);
+                let Position { x, y, z } = instance.translation;
+                centers.push([2 * x + offset[0], 2 * y + offset[1], 2 * z + offset[2]]);
+            }
+        }
+        centers
+    }
+
+    #[test]
+    fn voxel_centers_change_basis() {
+        for path in ["src/resources/transforms.vox", "src/resources/axes.vox"] {
+            let original = crate::load(path).unwrap();
+            let expected = doubled_centers(&original);
+            for convention in CONVENTIONS {
+                let mut data = original.clone();
+                data.convert_axes(convention);
+                assert!(data.validate().is_empty(), "{path} {convention:?}");
+                let basis = convention.rotation();
+                let actual = doubled_centers(&data);
+                assert_eq!(actual.len(), expected.len());
+                for (actual, expected) in actual.iter().zip(&expected) {
+                    assert_eq!(*actual, basis.transform_point(*expected));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conversions_are_proper_or_not() {
+        for convention in CONVENTIONS {
+            let right_handed = matches!(
+                convention,
+                AxisConvention::RightHandedZUp | AxisConvention::RightHandedYUp
+            );
+            assert_eq!(convention.rotation().is_reflection(), !right_handed);
+        }
+        // Y up: MagicaVoxel's up becomes +y.
+        assert_eq!(
+            AxisConvention::RightHandedYUp
+                .rotation()
+                .transform_point([0, 0, 1]),
+            [0, 1, 0]
+        );
+        assert_eq!(
+            AxisConvention::LeftHandedYUp
+                .rotation()
+                .transform_point([0, 0, 1]),
+            [0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn identity_conversion_leaves_data_unchanged() {
+        let original = crate::load("src/resources/placeholder.vox").unwrap();
+        let mut data = original.clone();
+        data.convert_axes(AxisConvention::RightHandedZUp);
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn mirroring_negates_one_axis() {
+        for path in ["src/resources/transforms.vox", "src/resources/axes.vox"] {
+            let original = crate::load(path).unwrap();
+            let expected = doubled_centers(&original);
+            for axis in [Axis::X, Axis::Y, Axis::Z] {
+                let mut data = original.clone();
+                data.mirror(axis);
+                assert!(data.validate().is_empty(), "{path} {axis:?}");
+                for (instance, original) in data.instances().zip(original.instances()) {
+                    assert_eq!(
+                        instance.rotation.is_reflection(),
+                        original.rotation.is_reflection()
+                    );
+                }
+                let actual = doubled_centers(&data);
+                for (actual, expected) in actual.iter().zip(&expected) {
+                    for k in 0..3 {
+                        let sign = if k == axis as usize { -1 } else { 1 };
+                        assert_eq!(actual[k], sign * expected[k]);
+                    }
+                }
+
+                data.mirror(axis);
+                assert_eq!(data.models, original.models);
+                assert_eq!(doubled_centers(&data), expected);
+            }
+        }
+    }
+}
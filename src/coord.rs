@@ -0,0 +1,226 @@
+use crate::{Position, Size, Voxel};
+use std::{
+    num::TryFromIntError,
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+};
+
+/// Coordinates of a voxel within its model, as stored in the file.
+///
+/// Model space is unsigned and bounded by the model [`Size`]. Convert to
+/// [`WorldCoord`] before combining with translations from the scene graph.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VoxelCoord {
+    /// The X coordinate.
+    pub x: u8,
+    /// The Y coordinate.
+    pub y: u8,
+    /// The Z coordinate.
+    pub z: u8,
+}
+
+impl VoxelCoord {
+    /// Creates new model space coordinates.
+    pub const fn new(x: u8, y: u8, z: u8) -> Self {
+        VoxelCoord { x, y, z }
+    }
+
+    /// Whether these coordinates lie within a model of the given size.
+    pub fn is_within(&self, size: Size) -> bool {
+        u32::from(self.x) < size.x && u32::from(self.y) < size.y && u32::from(self.z) < size.z
+    }
+}
+
+impl From<[u8; 3]> for VoxelCoord {
+    fn from([x, y, z]: [u8; 3]) -> Self {
+        VoxelCoord { x, y, z }
+    }
+}
+
+impl From<VoxelCoord> for [u8; 3] {
+    fn from(coord: VoxelCoord) -> Self {
+        [coord.x, coord.y, coord.z]
+    }
+}
+
+impl From<&Voxel> for VoxelCoord {
+    fn from(voxel: &Voxel) -> Self {
+        voxel.coord()
+    }
+}
+
+impl TryFrom<WorldCoord> for VoxelCoord {
+    type Error = TryFromIntError;
+
+    fn try_from(coord: WorldCoord) -> Result<Self, Self::Error> {
+        Ok(VoxelCoord {
+            x: coord.x.try_into()?,
+            y: coord.y.try_into()?,
+            z: coord.z.try_into()?,
+        })
+    }
+}
+
+impl Voxel {
+    /// The model space coordinates of this voxel.
+    pub fn coord(&self) -> VoxelCoord {
+        VoxelCoord::new(self.x, self.y, self.z)
+    }
+}
+
+/// Signed coordinates in world space, wide enough to hold any translation of
+/// the scene graph plus any model space offset without overflowing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WorldCoord {
+    /// The X coordinate.
+    pub x: i64,
+    /// The Y coordinate.
+    pub y: i64,
+    /// The Z coordinate.
+    pub z: i64,
+}
+
+impl WorldCoord {
+    /// The origin of world space.
+    pub const ZERO: WorldCoord = WorldCoord::new(0, 0, 0);
+
+    /// Creates new world space coordinates.
+    pub const fn new(x: i64, y: i64, z: i64) -> Self {
+        WorldCoord { x, y, z }
+    }
+}
+
+impl From<[i64; 3]> for WorldCoord {
+    fn from([x, y, z]: [i64; 3]) -> Self {
+        WorldCoord { x, y, z }
+    }
+}
+
+impl From<WorldCoord> for [i64; 3] {
+    fn from(coord: WorldCoord) -> Self {
+        [coord.x, coord.y, coord.z]
+    }
+}
+
+impl From<VoxelCoord> for WorldCoord {
+    fn from(coord: VoxelCoord) -> Self {
+        WorldCoord::new(coord.x.into(), coord.y.into(), coord.z.into())
+    }
+}
+
+impl From<Position> for WorldCoord {
+    fn from(position: Position) -> Self {
+        WorldCoord::new(position.x.into(), position.y.into(), position.z.into())
+    }
+}
+
+impl From<Size> for WorldCoord {
+    fn from(size: Size) -> Self {
+        WorldCoord::new(size.x.into(), size.y.into(), size.z.into())
+    }
+}
+
+impl TryFrom<WorldCoord> for Position {
+    type Error = TryFromIntError;
+
+    fn try_from(coord: WorldCoord) -> Result<Self, Self::Error> {
+        Ok(Position {
+            x: coord.x.try_into()?,
+            y: coord.y.try_into()?,
+            z: coord.z.try_into()?,
+        })
+    }
+}
+
+impl Add for WorldCoord {
+    type Output = WorldCoord;
+
+    fn add(self, rhs: WorldCoord) -> WorldCoord {
+        WorldCoord::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Add<VoxelCoord> for WorldCoord {
+    type Output = WorldCoord;
+
+    fn add(self, rhs: VoxelCoord) -> WorldCoord {
+        self + WorldCoord::from(rhs)
+    }
+}
+
+impl Sub for WorldCoord {
+    type Output = WorldCoord;
+
+    fn sub(self, rhs: WorldCoord) -> WorldCoord {
+        WorldCoord::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Sub<VoxelCoord> for WorldCoord {
+    type Output = WorldCoord;
+
+    fn sub(self, rhs: VoxelCoord) -> WorldCoord {
+        self - WorldCoord::from(rhs)
+    }
+}
+
+impl Neg for WorldCoord {
+    type Output = WorldCoord;
+
+    fn neg(self) -> WorldCoord {
+        WorldCoord::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl AddAssign for WorldCoord {
+    fn add_assign(&mut self, rhs: WorldCoord) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for WorldCoord {
+    fn sub_assign(&mut self, rhs: WorldCoord) {
+        *self = *self - rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voxel_coords_widen_to_world_coords() {
+        let voxel = Voxel {
+            x: 255,
+            y: 1,
+            z: 2,
+            i: 0,
+        };
+        let origin = WorldCoord::from(Position {
+            x: i32::MAX,
+            y: -3,
+            z: 0,
+        });
+        let world = origin + voxel.coord();
+        assert_eq!(world, WorldCoord::new(i32::MAX as i64 + 255, -2, 2));
+        assert!(Position::try_from(world).is_err());
+        assert_eq!(world - origin, WorldCoord::from(voxel.coord()));
+    }
+
+    #[test]
+    fn world_coords_narrow_to_voxel_coords() {
+        assert_eq!(
+            VoxelCoord::try_from(WorldCoord::new(1, 2, 255)),
+            Ok(VoxelCoord::new(1, 2, 255))
+        );
+        assert!(VoxelCoord::try_from(WorldCoord::new(-1, 0, 0)).is_err());
+        assert!(VoxelCoord::try_from(WorldCoord::new(0, 256, 0)).is_err());
+    }
+
+    #[test]
+    fn is_within_checks_model_bounds() {
+        let size = Size { x: 2, y: 3, z: 1 };
+        assert!(VoxelCoord::new(1, 2, 0).is_within(size));
+        assert!(!VoxelCoord::new(2, 0, 0).is_within(size));
+        assert!(!VoxelCoord::new(0, 0, 1).is_within(size));
+    }
+}
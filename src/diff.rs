@@ -0,0 +1,186 @@
+use crate::{DotVoxData, Palette, PaletteChange, Size, Voxel};
+
+#[cfg(feature = "ahash")]
+use ahash::AHashMap as HashMap;
+
+#[cfg(not(feature = "ahash"))]
+use std::collections::HashMap;
+
+/// Structural differences between two versions of a file, computed by
+/// [`DotVoxData::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DotVoxDiff {
+    /// Changes to the models, in order of model id.
+    pub models: Vec<ModelChange>,
+    /// Palette slots whose color changed.
+    pub palette: Vec<PaletteChange>,
+    /// Whether any material changed.
+    pub materials: bool,
+    /// Whether the scene graph changed.
+    pub scenes: bool,
+    /// Whether any layer changed.
+    pub layers: bool,
+}
+
+impl DotVoxDiff {
+    /// Whether the two versions have the same models, palette, materials,
+    /// scene graph and layers.
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+            && self.palette.is_empty()
+            && !self.materials
+            && !self.scenes
+            && !self.layers
+    }
+}
+
+/// A change to one model, reported by [`DotVoxData::diff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModelChange {
+    /// A model was added at the end of [`DotVoxData::models`].
+    Added(u32),
+    /// A model was removed from the end of [`DotVoxData::models`].
+    Removed(u32),
+    /// The size of a model changed. Its voxels may have changed as well.
+    Resized {
+        /// Index of the model.
+        model_id: u32,
+        /// Previous size.
+        old: Size,
+        /// New size.
+        new: Size,
+    },
+    /// Voxels of a model changed. A voxel whose color changed is listed in
+    /// both `removed`, with its previous color, and `added`.
+    Voxels {
+        /// Index of the model.
+        model_id: u32,
+        /// Voxels present only in the new version, sorted by position.
+        added: Vec<Voxel>,
+        /// Voxels present only in the previous version, sorted by position.
+        removed: Vec<Voxel>,
+    },
+}
+
+impl DotVoxData {
+    /// Computes what changed from `self` to `other`, e.g. between two
+    /// versions of a file being edited.
+    ///
+    /// Models are matched by index. The order of voxels within a model does
+    /// not matter.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let old = load("src/resources/placeholder.vox").unwrap();
+    /// let mut new = load("src/resources/placeholder.vox").unwrap();
+    /// new.models[0].voxels[0].i += 1;
+    ///
+    /// let diff = old.diff(&new);
+    /// match &diff.models[..] {
+    ///     [ModelChange::Voxels { added, removed, .. }] => {
+    ///         assert_eq!(added.len(), 1);
+    ///         assert_eq!(removed.len(), 1);
+    ///     }
+    ///     changes => panic!("Unexpected changes {:?}", changes),
+    /// }
+    /// assert!(diff.palette.is_empty() && !diff.scenes);
+    /// ```
+    pub fn diff(&self, other: &DotVoxData) -> DotVoxDiff {
+        let mut models = Vec::new();
+        for (model_id, (old, new)) in self.models.iter().zip(&other.models).enumerate() {
+            let model_id = model_id as u32;
+            if old.size != new.size {
+                models.push(ModelChange::Resized {
+                    model_id,
+                    old: old.size,
+                    new: new.size,
+                });
+            }
+            let (added, removed) = voxel_changes(&old.voxels, &new.voxels);
+            if !added.is_empty() || !removed.is_empty() {
+                models.push(ModelChange::Voxels {
+                    model_id,
+                    added,
+                    removed,
+                });
+            }
+        }
+        let common = self.models.len().min(other.models.len()) as u32;
+        models.extend((common..other.models.len() as u32).map(ModelChange::Added));
+        models.extend((common..self.models.len() as u32).map(ModelChange::Removed));
+
+        DotVoxDiff {
+            models,
+            palette: self.palette.diff(&other.palette),
+            materials: self.materials != other.materials,
+            scenes: self.scenes != other.scenes,
+            layers: self.layers != other.layers,
+        }
+    }
+}
+
+/// Voxels only in `new`, and voxels only in `old`.
+fn voxel_changes(old: &[Voxel], new: &[Voxel]) -> (Vec<Voxel>, Vec<Voxel>) {
+    let index = |voxels: &[Voxel]| -> HashMap<[u8; 3], u8> {
+        voxels
+            .iter()
+            .map(|voxel| ([voxel.x, voxel.y, voxel.z], voxel.i))
+            .collect()
+    };
+    let only_in = |voxels: &[Voxel], other: &HashMap<[u8; 3], u8>| {
+        let mut only: Vec<Voxel> = voxels
+            .iter()
+            .filter(|voxel| other.get(&[voxel.x, voxel.y, voxel.z]) != Some(&voxel.i))
+            .copied()
+            .collect();
+        only.sort_by_key(|voxel| [voxel.z, voxel.y, voxel.x]);
+        only
+    };
+    (only_in(new, &index(old)), only_in(old, &index(new)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_files_have_empty_diff() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        let mut shuffled = crate::load("src/resources/transforms.vox").unwrap();
+        shuffled.models[0].voxels.reverse();
+        assert!(data.diff(&shuffled).is_empty());
+    }
+
+    #[test]
+    fn diff_reports_model_changes() {
+        let old = crate::load("src/resources/placeholder.vox").unwrap();
+        let mut new = crate::load("src/resources/placeholder.vox").unwrap();
+        let removed = new.models[0].voxels.pop().unwrap();
+        new.models[0].size.z += 1;
+        new.models.push(new.models[0].clone());
+        new.palette[3].r ^= 1;
+        new.scenes.clear();
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.models,
+            [
+                ModelChange::Resized {
+                    model_id: 0,
+                    old: old.models[0].size,
+                    new: new.models[0].size,
+                },
+                ModelChange::Voxels {
+                    model_id: 0,
+                    added: vec![],
+                    removed: vec![removed],
+                },
+                ModelChange::Added(1),
+            ]
+        );
+        assert_eq!(diff.palette.len(), 1);
+        assert!(diff.scenes && !diff.materials && !diff.layers);
+        assert_eq!(new.diff(&old).models.last(), Some(&ModelChange::Removed(1)));
+    }
+}
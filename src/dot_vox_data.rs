@@ -1,8 +1,25 @@
-use crate::{Color, Dict, Layer, Material, Model, SceneNode};
-use std::io::{self, Write};
+use crate::{
+    prefab, Camera, Color, Dict, Error, Layer, Material, Model, Palette, PaletteExtension,
+    RawChunk, SceneNode, Voxel, DEFAULT_PALETTE,
+};
+use std::{
+    collections::BTreeMap,
+    error, fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
 /// Container for `.vox` file data.
-#[derive(Debug, PartialEq, Eq)]
+///
+/// `==` compares the data structurally, down to the order of voxels and
+/// materials. Use [`DotVoxData::equivalent`] to check whether two files have
+/// the same content regardless of how it is laid out.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct DotVoxData {
     /// The version number of the `.vox` file.
     pub version: u32,
@@ -14,27 +31,530 @@ pub struct DotVoxData {
     pub materials: Vec<Material>,
     /// Scene. The first node in this list is always the root node.
     pub scenes: Vec<SceneNode>,
+    /// Whether [`DotVoxData::scenes`] was synthesized rather than read,
+    /// see [`DotVoxData::synthesize_scene`]. A synthetic scene graph is not
+    /// written by [`DotVoxData::write_vox`]; clear this flag to write it.
+    pub synthetic_scene: bool,
     /// Layers. Used by scene transform nodes.
     pub layers: Vec<Layer>,
+    /// Non-standard extension allowing more than 255 colors, if used.
+    pub palette_extension: Option<PaletteExtension>,
+    /// Order in which the palette is displayed by MagicaVoxel, from the `IMAP`
+    /// chunk written when the palette has been re-ordered in the editor. Entry
+    /// `n` is the index into [`DotVoxData::palette`] shown at position `n`.
+    pub index_map: Option<Vec<u8>>,
+    /// Render settings from the `rOBJ` chunks, see
+    /// [`DotVoxData::render_settings`] for typed access.
+    pub render_objects: Vec<Dict>,
+    /// Viewport cameras from the `rCAM` chunks.
+    pub cameras: Vec<Camera>,
+    /// Application-defined chunks, as pairs of id and content, for the ids
+    /// registered with [`ParseOptions::extension_chunk`](crate::ParseOptions::extension_chunk).
+    /// Written after every standard chunk by default, in order.
+    pub extension_chunks: Vec<(String, Vec<u8>)>,
+    /// Chunks not recognized by this crate, written back unchanged after
+    /// every other chunk by default.
+    pub unknown_chunks: Vec<RawChunk>,
+    /// Kinds of the first chunks [`write_vox`](DotVoxData::write_vox) writes,
+    /// one entry per chunk (per model for [`ChunkKind::Models`]), each
+    /// standing for the next chunk of that kind not written yet. The chunks
+    /// left are written after them in the order of [`ChunkKind`], and entries
+    /// with no chunk left are skipped.
+    ///
+    /// Set when reading a file whose chunks are not in that order, to the
+    /// shortest list writing them back in the same order, and empty otherwise.
+    pub chunk_order: Vec<ChunkKind>,
+}
+
+/// A kind of chunk, see [`DotVoxData::chunk_order`]. Kinds are listed, and
+/// ordered, as recent versions of MagicaVoxel write them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChunkKind {
+    /// The `SIZE` and `XYZI` chunks of a model.
+    Models,
+    /// The `nTRN`, `nGRP` and `nSHP` chunks of the scene graph.
+    SceneGraph,
+    /// The `LAYR` chunks.
+    Layers,
+    /// The `RGBA` chunk.
+    Palette,
+    /// The `XPAL` chunk.
+    PaletteExtension,
+    /// The `IMAP` chunk.
+    IndexMap,
+    /// The `MATL` chunks, and the legacy `MATT` chunks.
+    Materials,
+    /// The `rOBJ` chunks.
+    RenderObjects,
+    /// The `rCAM` chunks.
+    Cameras,
+    /// The [extension chunks](DotVoxData::extension_chunks).
+    Extensions,
+    /// The [unknown chunks](DotVoxData::unknown_chunks).
+    Unknown,
+}
+
+impl ChunkKind {
+    /// Every kind, in order.
+    const ALL: [ChunkKind; 11] = [
+        ChunkKind::Models,
+        ChunkKind::SceneGraph,
+        ChunkKind::Layers,
+        ChunkKind::Palette,
+        ChunkKind::PaletteExtension,
+        ChunkKind::IndexMap,
+        ChunkKind::Materials,
+        ChunkKind::RenderObjects,
+        ChunkKind::Cameras,
+        ChunkKind::Extensions,
+        ChunkKind::Unknown,
+    ];
+
+    /// The kind of the chunk with the given id when read without extension
+    /// chunks, or `None` for chunks that are not written on their own.
+    pub(crate) fn of(id: &str) -> Option<ChunkKind> {
+        match id {
+            "XYZI" => Some(ChunkKind::Models),
+            "MAIN" | "PACK" | "SIZE" => None,
+            "nTRN" | "nGRP" | "nSHP" => Some(ChunkKind::SceneGraph),
+            "LAYR" => Some(ChunkKind::Layers),
+            "RGBA" => Some(ChunkKind::Palette),
+            "XPAL" => Some(ChunkKind::PaletteExtension),
+            "IMAP" => Some(ChunkKind::IndexMap),
+            "MATL" | "MATT" => Some(ChunkKind::Materials),
+            "rOBJ" => Some(ChunkKind::RenderObjects),
+            "rCAM" => Some(ChunkKind::Cameras),
+            _ => Some(ChunkKind::Unknown),
+        }
+    }
+
+    /// The shortest start of `order`, the kinds of every chunk of a file, with
+    /// which the chunks are written in that order, e.g. nothing if they are in
+    /// the order of [`ChunkKind`] already.
+    pub(crate) fn shortest(mut order: Vec<ChunkKind>) -> Vec<ChunkKind> {
+        let mut len = order.len();
+        while len > 0 && order.get(len).map_or(true, |&next| order[len - 1] <= next) {
+            len -= 1;
+        }
+        order.truncate(len);
+        order
+    }
+}
+
+/// Selects which chunks [`DotVoxData::write_vox_with`] writes. Everything is
+/// written by default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Write the palette, its extension and index map. Readers fall back to
+    /// [`DEFAULT_PALETTE`] for files without one.
+    pub palette: bool,
+    /// Write the materials.
+    pub materials: bool,
+    /// Write the scene graph and layers.
+    pub scene_graph: bool,
+    /// Indices into [`DotVoxData::layers`] of the only layers to write, or
+    /// `None` to write them all. Transform nodes on other layers are left
+    /// out along with their subtree, and so are the models only they place.
+    pub layers: Option<Vec<u32>>,
+    /// Write the voxels of each model sorted by z, then y, then x (and
+    /// palette index for duplicates), so that the output does not depend on
+    /// the order of [`Model::voxels`].
+    pub sort_voxels: bool,
+    /// Split models too large for MagicaVoxel to open, as by
+    /// [`DotVoxData::split_oversized_models`].
+    pub split_oversized_models: bool,
+    /// Refuse to write data for which [`DotVoxData::validate`] reports
+    /// problems, failing with an [`io::ErrorKind::InvalidData`] error that
+    /// wraps the first [`ValidationError`](crate::ValidationError).
+    pub validate: bool,
+    /// Write a `PACK` chunk holding the number of models before them, as
+    /// MagicaVoxel did before version 0.99. Only legacy importers need it.
+    pub pack: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            palette: true,
+            materials: true,
+            scene_graph: true,
+            layers: None,
+            sort_voxels: false,
+            split_oversized_models: false,
+            validate: false,
+            pack: false,
+        }
+    }
+}
+
+impl Default for DotVoxData {
+    fn default() -> Self {
+        DotVoxData::new()
+    }
 }
 
 impl DotVoxData {
+    /// An empty file that MagicaVoxel opens: version 150, the
+    /// [`DEFAULT_PALETTE`], no materials, which leaves every palette entry
+    /// diffuse, and a scene graph made of a root transform and group node.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = DotVoxData::new();
+    /// assert!(data.models.is_empty());
+    /// assert_eq!(data.scenes.len(), 2);
+    /// assert!(data.validate().is_empty());
+    /// assert_eq!(load_bytes(&data.to_bytes().unwrap()).unwrap(), data);
+    /// ```
+    pub fn new() -> Self {
+        let mut data = DotVoxData {
+            version: 150,
+            models: Vec::new(),
+            palette: DEFAULT_PALETTE.to_vec(),
+            materials: Vec::new(),
+            scenes: Vec::new(),
+            synthetic_scene: false,
+            layers: Vec::new(),
+            palette_extension: None,
+            index_map: None,
+            render_objects: Vec::new(),
+            cameras: Vec::new(),
+            extension_chunks: Vec::new(),
+            unknown_chunks: Vec::new(),
+            chunk_order: Vec::new(),
+        };
+        prefab::root_group(&mut data).expect("empty scene graphs have a root group");
+        data
+    }
+
+    /// Recolors every voxel to the closest entry of `target` (see
+    /// [`Palette::nearest`]), then replaces the palette with `target`.
+    ///
+    /// Materials are left untouched, and keep applying to the same palette
+    /// slots in `target`.
+    pub fn remap_to_palette(&mut self, target: &[Color]) {
+        let remap: Vec<u8> = self
+            .palette
+            .iter()
+            .map(|color| target.nearest(color).unwrap_or(0))
+            .collect();
+
+        for voxel in self.models.iter_mut().flat_map(|model| &mut model.voxels) {
+            voxel.i = remap.get(voxel.i as usize).copied().unwrap_or(0);
+        }
+        self.palette = target.to_vec();
+    }
+
+    /// Whether `self` and `other` describe the same content, even if not
+    /// structurally equal.
+    ///
+    /// Compared to `==`, this ignores:
+    /// - the file version,
+    /// - the order of voxels within each model,
+    /// - the order of materials, and materials that are plain diffuse (no
+    ///   `_type`, or `_type` set to `_diffuse`), as MagicaVoxel writes those
+    ///   for every palette slot,
+    /// - an empty palette versus an explicit [`DEFAULT_PALETTE`],
+    /// - no scene graph versus a [synthetic](DotVoxData::synthetic_scene) one.
+    ///
+    /// Dictionaries are compared regardless of order, like with `==`.
+    pub fn equivalent(&self, other: &DotVoxData) -> bool {
+        self.models.len() == other.models.len()
+            && self
+                .models
+                .iter()
+                .zip(&other.models)
+                .all(|(a, b)| a.size == b.size && sorted_voxels(a) == sorted_voxels(b))
+            && effective_palette(&self.palette) == effective_palette(&other.palette)
+            && effective_materials(&self.materials) == effective_materials(&other.materials)
+            && self.read_scenes() == other.read_scenes()
+            && self.layers == other.layers
+            && self.palette_extension == other.palette_extension
+            && self.index_map == other.index_map
+            && self.render_objects == other.render_objects
+            && self.cameras == other.cameras
+            && self.extension_chunks == other.extension_chunks
+            && self.unknown_chunks == other.unknown_chunks
+    }
+
+    /// The scene graph, unless synthetic.
+    fn read_scenes(&self) -> &[SceneNode] {
+        if self.synthetic_scene {
+            &[]
+        } else {
+            &self.scenes
+        }
+    }
+
     /// Serializes `self` in the `.vox` format.
+    ///
+    /// The output only depends on the content of `self`:
+    /// - models, scene nodes and layers are written in the order of their
+    ///   `Vec`s, and their ids in the file are their indices, so that reading
+    ///   the file back yields the same indices,
+    /// - materials are written sorted by id,
+    /// - dictionary entries are written sorted by key.
+    ///
+    /// Chunks are streamed to `writer` as they are serialized, so that the
+    /// memory used does not grow with the size of the file. Writes are small
+    /// and frequent: wrap unbuffered writers such as a [`File`] in a
+    /// [`BufWriter`](std::io::BufWriter).
     pub fn write_vox<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
-        self.write_header(writer)?;
+        self.write_vox_with(writer, WriteOptions::default())
+    }
+
+    /// Serializes `self` in memory with [`write_vox`](Self::write_vox).
+    ///
+    /// # Errors
+    ///
+    /// Fails only if `self` cannot be represented in the `.vox` format, i.e.
+    /// on a [`ChunkSizeOverflow`] or a chunk id that is not 4 bytes long.
+    ///
+    /// ```
+    /// let data = dot_vox::load("src/resources/placeholder.vox").unwrap();
+    /// let bytes = data.to_bytes().unwrap();
+    /// assert_eq!(dot_vox::load_bytes(&bytes).unwrap(), data);
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+        self.write_vox(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Writes `self` to the file at `path` with [`write_vox`](Self::write_vox),
+    /// creating the file or replacing its content.
+    ///
+    /// With the `flate2` feature, the file is compressed with gzip if `path`
+    /// has a `.gz` extension.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the file cannot be created or written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        self.write_file(File::create(path)?, path)?;
+        Ok(())
+    }
+
+    /// Writes `self` to the file at `path` like [`save`](Self::save), but
+    /// through a temporary file in the same directory that is then renamed
+    /// over `path`. The file at `path` is thus either left untouched or
+    /// entirely replaced, even if the process crashes while writing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] if the temporary file cannot be created, written
+    /// or renamed. The temporary file is removed on failure.
+    pub fn save_atomic<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file_name = path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Path does not name a file")
+        })?;
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(file_name);
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp_path = path.with_file_name(temp_name);
+
+        let write = || -> Result<(), io::Error> {
+            self.write_file(File::create(&temp_path)?, path)?
+                .sync_all()?;
+            std::fs::rename(&temp_path, path)
+        };
+        write().map_err(|error| {
+            let _ = std::fs::remove_file(&temp_path);
+            Error::Io(error)
+        })
+    }
+
+    /// Writes `self` to `file`, saved at `path`, and returns the file.
+    #[cfg_attr(not(feature = "flate2"), allow(unused_variables))]
+    fn write_file(&self, file: File, path: &Path) -> Result<File, io::Error> {
+        let mut writer = BufWriter::new(file);
+        #[cfg(feature = "flate2")]
+        if crate::gzip::is_compressed_path(path) {
+            let mut encoder = crate::gzip::encoder(writer);
+            self.write_vox(&mut encoder)?;
+            writer = encoder.finish()?;
+            return writer.into_inner().map_err(io::IntoInnerError::into_error);
+        }
+        self.write_vox(&mut writer)?;
+        writer.into_inner().map_err(io::IntoInnerError::into_error)
+    }
+
+    /// Serializes `self` in the `.vox` format, leaving out the chunks not
+    /// selected by `options`, e.g. to produce minimal files for use at
+    /// runtime. See [`write_vox`](Self::write_vox).
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = load("src/resources/placeholder.vox").unwrap();
+    /// let options = WriteOptions {
+    ///     palette: false,
+    ///     materials: false,
+    ///     ..WriteOptions::default()
+    /// };
+    /// let mut bytes = Vec::new();
+    /// data.write_vox_with(&mut bytes, options).unwrap();
+    ///
+    /// let reloaded = load_bytes(&bytes).unwrap();
+    /// assert_eq!(reloaded.models, data.models);
+    /// assert_eq!(reloaded.palette, DEFAULT_PALETTE.to_vec());
+    /// assert!(reloaded.materials.is_empty());
+    /// ```
+    pub fn write_vox_with<W: Write>(
+        &self,
+        writer: &mut W,
+        options: WriteOptions,
+    ) -> Result<(), io::Error> {
+        if options.split_oversized_models
+            && !self
+                .models
+                .iter()
+                .all(|model| model.size.is_within_limits())
+        {
+            let mut data = self.clone();
+            data.split_oversized_models();
+            let options = WriteOptions {
+                split_oversized_models: false,
+                ..options
+            };
+            return data.write_vox_with(writer, options);
+        }
+        if options.validate {
+            if let Some(error) = self.validate().into_iter().next() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+        }
+        if let Some(layers) = &options.layers {
+            let mut data = self.clone();
+            data.retain_layers(layers);
+            let options = WriteOptions {
+                layers: None,
+                ..options
+            };
+            return data.write_vox_with(writer, options);
+        }
 
-        // Write out all of the children of MAIN first to get the number of bytes.
-        let mut children_buffer = Vec::new();
-        self.write_models(&mut children_buffer)?;
-        self.write_scene_graph(&mut children_buffer)?;
-        self.write_palette_chunk(&mut children_buffer)?;
-        self.write_materials(&mut children_buffer)?;
-        self.write_layers(&mut children_buffer)?;
-        let num_main_children_bytes = children_buffer.len() as u32;
+        // Serialize the other children of MAIN once without keeping them, only
+        // to count their bytes, so that they can then be streamed to `writer`
+        // instead of being buffered as a whole. The size of models is known
+        // upfront.
+        let pack_len = if options.pack { 12 + 4 } else { 0 };
+        let mut counter = ByteCounter(self.models_len()? + pack_len);
+        self.write_main_children(&mut counter, &options, false)?;
+        let num_main_children_bytes = checked_len("MAIN", counter.0)?;
 
+        self.write_header(writer)?;
         self.write_main_chunk(writer, num_main_children_bytes)?;
+        if options.pack {
+            let count = checked_len("PACK", self.models.len())?;
+            Self::write_leaf_chunk(writer, "PACK", &count.to_le_bytes())?;
+        }
+        self.write_main_children(writer, &options, true)
+    }
+
+    /// Number of bytes taken by the `SIZE` and `XYZI` chunks of every model,
+    /// failing like [`write_models`](Self::write_models) would.
+    fn models_len(&self) -> Result<usize, io::Error> {
+        let mut len = 0usize;
+        for model in self.models.iter() {
+            let count = model.voxels.len();
+            checked_len("XYZI", count)?;
+            let content = count.saturating_mul(4).saturating_add(4);
+            checked_len("XYZI", content)?;
+            len = len.saturating_add(12 + 12 + 12 + content);
+        }
+        Ok(len)
+    }
+
+    /// Writes the children of MAIN in the [`chunk_order`](Self::chunk_order),
+    /// leaving the models out unless `models` is set.
+    fn write_main_children<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &WriteOptions,
+        models: bool,
+    ) -> Result<(), io::Error> {
+        // Material ids are written as-is, i.e. 1-based like the palette indices
+        // stored in `XYZI`, in ascending order as MagicaVoxel itself does.
+        let mut materials: Vec<&Material> = self.materials.iter().collect();
+        materials.sort_by_key(|material| material.id);
+
+        for (kind, range) in self.chunk_runs(options) {
+            match kind {
+                ChunkKind::Models if models => {
+                    Self::write_models(writer, &self.models[range], options.sort_voxels)?
+                }
+                ChunkKind::Models => {}
+                ChunkKind::SceneGraph => self.write_scene_graph(writer, range)?,
+                ChunkKind::Layers => self.write_layers(writer, range)?,
+                ChunkKind::Palette => self.write_palette_chunk(writer)?,
+                ChunkKind::PaletteExtension => self.write_palette_extension(writer)?,
+                ChunkKind::IndexMap => self.write_index_map(writer)?,
+                ChunkKind::Materials => Self::write_materials(writer, &materials[range])?,
+                ChunkKind::RenderObjects => {
+                    Self::write_render_objects(writer, &self.render_objects[range])?
+                }
+                ChunkKind::Cameras => Self::write_cameras(writer, &self.cameras[range])?,
+                ChunkKind::Extensions => {
+                    Self::write_extension_chunks(writer, &self.extension_chunks[range])?
+                }
+                ChunkKind::Unknown => {
+                    Self::write_unknown_chunks(writer, &self.unknown_chunks[range])?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the chunks written with `options` into runs of consecutive
+    /// chunks of the same kind, following the [`chunk_order`](Self::chunk_order).
+    /// Each run is given as a range of indices among the chunks of its kind.
+    fn chunk_runs(&self, options: &WriteOptions) -> Vec<(ChunkKind, Range<usize>)> {
+        let mut next = [0usize; ChunkKind::ALL.len()];
+        let mut runs: Vec<(ChunkKind, Range<usize>)> = Vec::new();
+        let listed = self.chunk_order.iter().map(|&kind| (kind, 1));
+        let rest = ChunkKind::ALL.iter().map(|&kind| (kind, usize::MAX));
+        for (kind, count) in listed.chain(rest) {
+            let start = next[kind as usize];
+            let end = start
+                .saturating_add(count)
+                .min(self.chunk_count(kind, options));
+            if start >= end {
+                continue;
+            }
+            next[kind as usize] = end;
+            match runs.last_mut() {
+                Some((last, range)) if *last == kind => range.end = end,
+                _ => runs.push((kind, start..end)),
+            }
+        }
+        runs
+    }
 
-        writer.write_all(&children_buffer)
+    /// Number of chunks of `kind` written with `options`, counting each model
+    /// once.
+    fn chunk_count(&self, kind: ChunkKind, options: &WriteOptions) -> usize {
+        match kind {
+            ChunkKind::Models => self.models.len(),
+            ChunkKind::SceneGraph if options.scene_graph && !self.synthetic_scene => {
+                self.scenes.len()
+            }
+            ChunkKind::Layers if options.scene_graph => self.layers.len(),
+            ChunkKind::Palette if options.palette => 1,
+            ChunkKind::PaletteExtension if options.palette => {
+                usize::from(self.palette_extension.is_some())
+            }
+            ChunkKind::IndexMap if options.palette => usize::from(self.index_map.is_some()),
+            ChunkKind::Materials if options.materials => self.materials.len(),
+            ChunkKind::RenderObjects => self.render_objects.len(),
+            ChunkKind::Cameras => self.cameras.len(),
+            ChunkKind::Extensions => self.extension_chunks.len(),
+            ChunkKind::Unknown => self.unknown_chunks.len(),
+            _ => 0,
+        }
     }
 
     fn write_header<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
@@ -50,15 +570,53 @@ impl DotVoxData {
         Self::write_chunk(writer, "MAIN", &[], num_children_bytes)
     }
 
-    fn write_models<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
-        for model in self.models.iter() {
-            Self::write_model(writer, model)?;
+    #[cfg(not(feature = "rayon"))]
+    fn write_models<W: Write>(
+        writer: &mut W,
+        models: &[Model],
+        sort_voxels: bool,
+    ) -> Result<(), io::Error> {
+        for (model_id, model) in models.iter().enumerate() {
+            Self::write_model(writer, model_id, model, sort_voxels)?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    fn write_models<W: Write>(
+        writer: &mut W,
+        models: &[Model],
+        sort_voxels: bool,
+    ) -> Result<(), io::Error> {
+        // Encode a few models per thread at a time, to bound the memory used.
+        let batch_size = 4 * rayon::current_num_threads();
+        let batch_size = batch_size.max(1);
+        for (batch_index, batch) in models.chunks(batch_size).enumerate() {
+            let encoded: Vec<Vec<u8>> = batch
+                .par_iter()
+                .enumerate()
+                .map(|(index, model)| {
+                    let mut buffer = Vec::new();
+                    let model_id = batch_index * batch_size + index;
+                    Self::write_model(&mut buffer, model_id, model, sort_voxels)?;
+                    Ok(buffer)
+                })
+                .collect::<Result<_, io::Error>>()?;
+            for buffer in encoded {
+                writer.write_all(&buffer)?;
+            }
         }
 
         Ok(())
     }
 
-    fn write_model<W: Write>(writer: &mut W, model: &Model) -> Result<(), io::Error> {
+    fn write_model<W: Write>(
+        writer: &mut W,
+        model_id: usize,
+        model: &Model,
+        sort_voxels: bool,
+    ) -> Result<(), io::Error> {
         let mut size_chunk = Vec::new();
         size_chunk.extend_from_slice(&model.size.x.to_le_bytes());
         size_chunk.extend_from_slice(&model.size.y.to_le_bytes());
@@ -66,33 +624,65 @@ impl DotVoxData {
         Self::write_leaf_chunk(writer, "SIZE", &size_chunk)?;
 
         let mut xyzi_chunk = Vec::new();
-        xyzi_chunk.extend_from_slice(&(model.voxels.len() as u32).to_le_bytes());
-        for voxel in model.voxels.iter() {
-            xyzi_chunk.push(voxel.x);
-            xyzi_chunk.push(voxel.y);
-            xyzi_chunk.push(voxel.z);
-            // `Voxel::i` uses 0-based palette indices, while VOX uses 1-based.
-            xyzi_chunk.push(voxel.i + 1);
+        xyzi_chunk.extend_from_slice(&checked_len("XYZI", model.voxels.len())?.to_le_bytes());
+        if sort_voxels {
+            let mut voxels: Vec<&Voxel> = model.voxels.iter().collect();
+            voxels.sort_unstable_by_key(|voxel| (voxel.z, voxel.y, voxel.x, voxel.i));
+            Self::write_voxels(&mut xyzi_chunk, model_id, voxels)?;
+        } else {
+            Self::write_voxels(&mut xyzi_chunk, model_id, &model.voxels)?;
         }
         Self::write_leaf_chunk(writer, "XYZI", &xyzi_chunk)
     }
 
-    fn write_string(buffer: &mut Vec<u8>, str: &String) {
-        buffer.extend_from_slice(&((str.len() as u32).to_le_bytes()));
-        buffer.extend_from_slice(&str.as_bytes());
+    fn write_voxels<'a>(
+        buffer: &mut Vec<u8>,
+        model_id: usize,
+        voxels: impl IntoIterator<Item = &'a Voxel>,
+    ) -> Result<(), io::Error> {
+        for voxel in voxels {
+            buffer.push(voxel.x);
+            buffer.push(voxel.y);
+            buffer.push(voxel.z);
+            // `Voxel::i` uses 0-based palette indices, while VOX uses 1-based.
+            let index = voxel.i.checked_add(1).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    crate::ValidationError::InvalidPaletteIndex {
+                        model_id,
+                        voxel: *voxel,
+                    },
+                )
+            })?;
+            buffer.push(index);
+        }
+        Ok(())
     }
 
-    fn write_dict(buffer: &mut Vec<u8>, dict: &Dict) {
-        buffer.extend_from_slice(&((dict.len() as u32).to_le_bytes()));
-        for (key, value) in dict.iter() {
-            Self::write_string(buffer, key);
-            Self::write_string(buffer, value);
+    fn write_string(buffer: &mut Vec<u8>, str: &String) -> Result<(), io::Error> {
+        buffer.extend_from_slice(&checked_len("STRING", str.len())?.to_le_bytes());
+        buffer.extend_from_slice(str.as_bytes());
+        Ok(())
+    }
+
+    fn write_dict(buffer: &mut Vec<u8>, dict: &Dict) -> Result<(), io::Error> {
+        buffer.extend_from_slice(&checked_len("DICT", dict.len())?.to_le_bytes());
+        let mut entries: Vec<(&String, &String)> = dict.iter().collect();
+        entries.sort_unstable();
+        for (key, value) in entries {
+            Self::write_string(buffer, key)?;
+            Self::write_string(buffer, value)?;
         }
+        Ok(())
     }
 
-    fn write_scene_graph<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
-        for (i, node) in self.scenes.iter().enumerate() {
-            Self::write_scene_node(writer, node, i as u32)?;
+    fn write_scene_graph<W: Write>(
+        &self,
+        writer: &mut W,
+        range: Range<usize>,
+    ) -> Result<(), io::Error> {
+        for (i, node) in self.scenes[range.clone()].iter().enumerate() {
+            Self::write_scene_node(writer, node, checked_len("nTRN", range.start + i)?)?;
         }
 
         Ok(())
@@ -111,9 +701,9 @@ impl DotVoxData {
                 children,
             } => {
                 id = "nGRP";
-                node_chunk.extend_from_slice(&(i as u32).to_le_bytes());
-                Self::write_dict(&mut node_chunk, &attributes);
-                node_chunk.extend_from_slice(&((children.len() as u32).to_le_bytes()));
+                node_chunk.extend_from_slice(&i.to_le_bytes());
+                Self::write_dict(&mut node_chunk, attributes)?;
+                node_chunk.extend_from_slice(&checked_len(id, children.len())?.to_le_bytes());
                 for child in children {
                     node_chunk.extend_from_slice(&child.to_le_bytes());
                 }
@@ -125,24 +715,24 @@ impl DotVoxData {
                 attributes,
             } => {
                 id = "nTRN";
-                node_chunk.extend_from_slice(&(i as u32).to_le_bytes());
-                Self::write_dict(&mut node_chunk, &attributes);
+                node_chunk.extend_from_slice(&i.to_le_bytes());
+                Self::write_dict(&mut node_chunk, attributes)?;
                 node_chunk.extend_from_slice(&child.to_le_bytes());
                 node_chunk.extend_from_slice(&u32::MAX.to_le_bytes());
                 node_chunk.extend_from_slice(&layer_id.to_le_bytes());
-                node_chunk.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+                node_chunk.extend_from_slice(&checked_len(id, frames.len())?.to_le_bytes());
                 for frame in frames {
-                    Self::write_dict(&mut node_chunk, &frame.attributes);
+                    Self::write_dict(&mut node_chunk, &frame.attributes)?;
                 }
             }
             SceneNode::Shape { attributes, models } => {
                 id = "nSHP";
-                node_chunk.extend_from_slice(&(i as u32).to_le_bytes());
-                Self::write_dict(&mut node_chunk, &attributes);
-                node_chunk.extend_from_slice(&(models.len() as u32).to_le_bytes());
+                node_chunk.extend_from_slice(&i.to_le_bytes());
+                Self::write_dict(&mut node_chunk, attributes)?;
+                node_chunk.extend_from_slice(&checked_len(id, models.len())?.to_le_bytes());
                 for model in models {
                     node_chunk.extend_from_slice(&model.model_id.to_le_bytes());
-                    Self::write_dict(&mut node_chunk, &model.attributes);
+                    Self::write_dict(&mut node_chunk, &model.attributes)?;
                 }
             }
         }
@@ -160,28 +750,95 @@ impl DotVoxData {
         Self::write_leaf_chunk(writer, "RGBA", &chunk)
     }
 
-    fn write_materials<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
-        for material in self.materials.iter() {
+    fn write_palette_extension<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match &self.palette_extension {
+            Some(extension) => Self::write_leaf_chunk(writer, "XPAL", &extension.to_bytes()),
+            None => Ok(()),
+        }
+    }
+
+    fn write_index_map<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        match &self.index_map {
+            Some(index_map) => Self::write_leaf_chunk(writer, "IMAP", index_map),
+            None => Ok(()),
+        }
+    }
+
+    fn write_materials<W: Write>(writer: &mut W, materials: &[&Material]) -> Result<(), io::Error> {
+        for material in materials {
             let mut chunk = Vec::new();
             chunk.extend_from_slice(&material.id.to_le_bytes());
-            Self::write_dict(&mut chunk, &material.properties);
+            Self::write_dict(&mut chunk, &material.properties)?;
             Self::write_leaf_chunk(writer, "MATL", &chunk)?;
         }
         Ok(())
     }
 
-    fn write_layers<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
-        for (i, layer) in self.layers.iter().enumerate() {
-            let id = i as u32;
+    fn write_layers<W: Write>(&self, writer: &mut W, range: Range<usize>) -> Result<(), io::Error> {
+        for (i, layer) in self.layers[range.clone()].iter().enumerate() {
+            let id = checked_len("LAYR", range.start + i)?;
             let mut chunk = Vec::new();
             chunk.extend_from_slice(&id.to_le_bytes());
-            Self::write_dict(&mut chunk, &layer.attributes);
+            Self::write_dict(&mut chunk, &layer.attributes)?;
             chunk.extend_from_slice(&u32::MAX.to_le_bytes());
             Self::write_leaf_chunk(writer, "LAYR", &chunk)?;
         }
         Ok(())
     }
 
+    fn write_render_objects<W: Write>(writer: &mut W, objects: &[Dict]) -> Result<(), io::Error> {
+        for object in objects {
+            let mut chunk = Vec::new();
+            Self::write_dict(&mut chunk, object)?;
+            Self::write_leaf_chunk(writer, "rOBJ", &chunk)?;
+        }
+        Ok(())
+    }
+
+    fn write_cameras<W: Write>(writer: &mut W, cameras: &[Camera]) -> Result<(), io::Error> {
+        for camera in cameras {
+            let mut chunk = Vec::new();
+            chunk.extend_from_slice(&camera.id.to_le_bytes());
+            Self::write_dict(&mut chunk, &camera.attributes)?;
+            Self::write_leaf_chunk(writer, "rCAM", &chunk)?;
+        }
+        Ok(())
+    }
+
+    fn write_extension_chunks<W: Write>(
+        writer: &mut W,
+        chunks: &[(String, Vec<u8>)],
+    ) -> Result<(), io::Error> {
+        for (id, content) in chunks {
+            if id.len() != 4 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Chunk ids must be 4 bytes long",
+                ));
+            }
+            Self::write_leaf_chunk(writer, id, content)?;
+        }
+        Ok(())
+    }
+
+    fn write_unknown_chunks<W: Write>(
+        writer: &mut W,
+        chunks: &[RawChunk],
+    ) -> Result<(), io::Error> {
+        for chunk in chunks {
+            if chunk.id.len() != 4 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Chunk ids must be 4 bytes long",
+                ));
+            }
+            let num_children_bytes = checked_len(&chunk.id, chunk.children.len())?;
+            Self::write_chunk(writer, &chunk.id, &chunk.content, num_children_bytes)?;
+            writer.write_all(&chunk.children)?;
+        }
+        Ok(())
+    }
+
     fn write_leaf_chunk<W: Write>(writer: &mut W, id: &str, chunk: &[u8]) -> Result<(), io::Error> {
         let num_children_bytes: u32 = 0;
 
@@ -196,8 +853,539 @@ impl DotVoxData {
     ) -> Result<(), io::Error> {
         assert!(id.len() == 4);
         writer.write_all(id.as_bytes())?;
-        writer.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        writer.write_all(&checked_len(id, chunk.len())?.to_le_bytes())?;
         writer.write_all(&num_children_bytes.to_le_bytes())?;
         writer.write_all(chunk)
     }
 }
+
+/// Error wrapped in the [`io::Error`] returned by
+/// [`DotVoxData::write_vox`] when a chunk, or a count within it, does not fit
+/// the 32-bit sizes of the `.vox` format. Nothing is written in that case.
+///
+/// ```
+/// use dot_vox::ChunkSizeOverflow;
+/// # fn handle(error: std::io::Error) {
+/// if let Some(overflow) = error
+///     .get_ref()
+///     .and_then(|inner| inner.downcast_ref::<ChunkSizeOverflow>())
+/// {
+///     eprintln!("{} chunk too large: {}", overflow.id, overflow.len);
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkSizeOverflow {
+    /// Id of the offending chunk, or `DICT` / `STRING` for the length of a
+    /// dictionary or string.
+    pub id: String,
+    /// The length that does not fit in a `u32`.
+    pub len: usize,
+}
+
+impl fmt::Display for ChunkSizeOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} length {} exceeds the maximum of {} allowed by the .vox format",
+            self.id,
+            self.len,
+            u32::MAX
+        )
+    }
+}
+
+impl error::Error for ChunkSizeOverflow {}
+
+fn sorted_voxels(model: &Model) -> Vec<[u8; 4]> {
+    let mut voxels: Vec<[u8; 4]> = model
+        .voxels
+        .iter()
+        .map(|voxel| [voxel.x, voxel.y, voxel.z, voxel.i])
+        .collect();
+    voxels.sort_unstable();
+    voxels
+}
+
+fn effective_palette(palette: &[Color]) -> &[Color] {
+    if palette.is_empty() {
+        &DEFAULT_PALETTE
+    } else {
+        palette
+    }
+}
+
+fn effective_materials(materials: &[Material]) -> BTreeMap<u32, &Dict> {
+    materials
+        .iter()
+        .filter(|material| !matches!(material.material_type(), None | Some("_diffuse")))
+        .map(|material| (material.id, &material.properties))
+        .collect()
+}
+
+/// A writer discarding its input, counting the bytes written.
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn checked_len(id: &str, len: usize) -> Result<u32, io::Error> {
+    u32::try_from(len).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            ChunkSizeOverflow {
+                id: id.to_owned(),
+                len,
+            },
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn oversized_lengths_are_reported() {
+        assert_eq!(checked_len("XYZI", u32::MAX as usize).unwrap(), u32::MAX);
+
+        let error = checked_len("XYZI", u32::MAX as usize + 1).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let overflow = error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<ChunkSizeOverflow>())
+            .unwrap();
+        assert_eq!(overflow.id, "XYZI");
+        assert_eq!(overflow.len, u32::MAX as usize + 1);
+    }
+
+    fn load(bytes: &[u8]) -> DotVoxData {
+        crate::load_bytes(bytes).unwrap()
+    }
+
+    #[test]
+    fn round_trip_is_equivalent() {
+        let data = load(include_bytes!("resources/placeholder-with-materials.vox"));
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+        assert!(load(&bytes).equivalent(&data));
+    }
+
+    #[test]
+    fn voxel_order_is_ignored() {
+        let data = load(include_bytes!("resources/placeholder.vox"));
+        let mut other = load(include_bytes!("resources/placeholder.vox"));
+        other.models[0].voxels.reverse();
+        other.version += 1;
+        assert_ne!(data, other);
+        assert!(data.equivalent(&other));
+
+        other.models[0].voxels[0].i += 1;
+        assert!(!data.equivalent(&other));
+    }
+
+    #[test]
+    fn default_palette_and_materials_are_ignored() {
+        let data = load(include_bytes!("resources/placeholder.vox"));
+        let mut other = load(include_bytes!("resources/placeholder.vox"));
+        other.palette = Vec::new();
+        other.materials = (1..=256)
+            .map(|id| Material {
+                id,
+                properties: [("_type".to_owned(), "_diffuse".to_owned())]
+                    .into_iter()
+                    .collect(),
+            })
+            .collect();
+        assert!(data.equivalent(&other));
+
+        other.materials[3]
+            .properties
+            .insert("_type".to_owned(), "_metal".to_owned());
+        assert!(!data.equivalent(&other));
+    }
+
+    #[test]
+    fn write_preserves_indices() {
+        let mut data = load(include_bytes!("resources/transforms.vox"));
+        // Swap the models, and the shape nodes referring to them, so that the
+        // order differs from the one MagicaVoxel wrote.
+        data.models.swap(0, 1);
+        for node in data.scenes.iter_mut() {
+            if let SceneNode::Shape { models, .. } = node {
+                for model in models {
+                    model.model_id = 1 - model.model_id;
+                }
+            }
+        }
+        data.layers.reverse();
+
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+        let reloaded = load(&bytes);
+        assert_eq!(reloaded.models, data.models);
+        assert_eq!(reloaded.scenes, data.scenes);
+        assert_eq!(reloaded.layers, data.layers);
+    }
+
+    #[test]
+    fn render_settings_and_cameras_are_written_like_magicavoxel() {
+        let source = include_bytes!("resources/axes.vox");
+        let data = load(source);
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+
+        // MagicaVoxel writes the render settings and cameras after materials.
+        let ids = |bytes: &[u8]| -> Vec<[u8; 4]> {
+            let chunks = crate::raw::read_chunks(bytes).unwrap();
+            let ids = chunks[0].children.iter().map(|chunk| chunk.id);
+            ids.filter(|id| matches!(id, b"MATL" | b"rOBJ" | b"rCAM"))
+                .collect()
+        };
+        assert_eq!(ids(&bytes), ids(source));
+
+        let reloaded = load(&bytes);
+        assert_eq!(reloaded.render_objects, data.render_objects);
+        assert_eq!(reloaded.cameras, data.cameras);
+    }
+
+    #[test]
+    fn write_options_leave_out_chunks() {
+        let data = load(include_bytes!("resources/placeholder.vox"));
+        let write = |options: WriteOptions| {
+            let mut bytes = Vec::new();
+            data.write_vox_with(&mut bytes, options).unwrap();
+            load(&bytes)
+        };
+
+        assert_eq!(write(WriteOptions::default()), data);
+        let reloaded = write(WriteOptions {
+            scene_graph: false,
+            ..WriteOptions::default()
+        });
+        assert_eq!(reloaded.models, data.models);
+        assert!(reloaded.layers.is_empty());
+        assert!(reloaded.synthetic_scene);
+    }
+
+    #[test]
+    fn write_options_select_layers() {
+        let mut data = load(include_bytes!("resources/transforms.vox"));
+        let SceneNode::Group { children, .. } = &data.scenes[1] else {
+            panic!("expected the root group");
+        };
+        let moved = children[0] as usize;
+        if let SceneNode::Transform { layer_id, .. } = &mut data.scenes[moved] {
+            *layer_id = 1;
+        }
+        let write = |layers: Vec<u32>| {
+            let options = WriteOptions {
+                layers: Some(layers),
+                ..WriteOptions::default()
+            };
+            let mut bytes = Vec::new();
+            data.write_vox_with(&mut bytes, options).unwrap();
+            load(&bytes)
+        };
+
+        let first = write(vec![0]);
+        let second = write(vec![1]);
+        assert_eq!(first.layers, data.layers[..1]);
+        assert_eq!(second.layers, data.layers[1..2]);
+        assert_eq!(
+            first.instances().count() + second.instances().count(),
+            data.instances().count()
+        );
+        assert!(second
+            .scenes
+            .iter()
+            .any(|node| matches!(node, SceneNode::Transform { layer_id: 0, .. })));
+        assert!(second.models.len() < data.models.len());
+    }
+
+    #[test]
+    fn save_writes_a_file_that_loads_back() {
+        let data = load(include_bytes!("resources/placeholder.vox"));
+        let path = std::env::temp_dir().join("dot_vox_save_writes_a_file_that_loads_back.vox");
+        data.save(&path).unwrap();
+        let reloaded = crate::load(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(reloaded, data);
+
+        let missing = std::env::temp_dir()
+            .join("dot_vox_missing_dir")
+            .join("out.vox");
+        assert!(matches!(data.save(missing), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn save_atomic_replaces_the_file() {
+        let dir = std::env::temp_dir().join("dot_vox_save_atomic_replaces_the_file");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.vox");
+        std::fs::write(&path, b"previous").unwrap();
+
+        let data = load(include_bytes!("resources/placeholder.vox"));
+        data.save_atomic(&path).unwrap();
+        assert_eq!(crate::load(path.to_str().unwrap()).unwrap(), data);
+        // Only the target is left behind.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(data.save_atomic("/"), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn identical_data_serializes_to_identical_bytes() {
+        for bytes in [
+            &include_bytes!("resources/axes.vox")[..],
+            include_bytes!("resources/placeholder-with-materials.vox"),
+            include_bytes!("resources/transforms.vox"),
+        ] {
+            // The dictionaries of separate loads may iterate in different orders.
+            assert_eq!(
+                load(bytes).to_bytes().unwrap(),
+                load(bytes).to_bytes().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn sorted_voxels_do_not_depend_on_order() {
+        let data = load(include_bytes!("resources/axes.vox"));
+        let mut shuffled = data.clone();
+        for model in shuffled.models.iter_mut() {
+            model.voxels.reverse();
+        }
+        let options = WriteOptions {
+            sort_voxels: true,
+            ..WriteOptions::default()
+        };
+        let write = |data: &DotVoxData| {
+            let mut bytes = Vec::new();
+            data.write_vox_with(&mut bytes, options.clone()).unwrap();
+            bytes
+        };
+        assert_ne!(data.to_bytes().unwrap(), shuffled.to_bytes().unwrap());
+        assert_eq!(write(&data), write(&shuffled));
+
+        let voxels = &load(&write(&data)).models[0].voxels;
+        assert!(voxels
+            .windows(2)
+            .all(|pair| (pair[0].z, pair[0].y, pair[0].x) <= (pair[1].z, pair[1].y, pair[1].x)));
+    }
+
+    #[test]
+    fn invalid_data_is_refused_when_validating() {
+        let mut data = load(include_bytes!("resources/placeholder.vox"));
+        data.models.clear();
+        // Written as is by default.
+        assert!(data.to_bytes().is_ok());
+
+        let options = WriteOptions {
+            validate: true,
+            ..WriteOptions::default()
+        };
+        let error = data.write_vox_with(&mut Vec::new(), options).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let inner = error
+            .get_ref()
+            .unwrap()
+            .downcast_ref::<crate::ValidationError>();
+        assert!(matches!(
+            inner,
+            Some(crate::ValidationError::MissingModel { .. })
+        ));
+    }
+
+    #[test]
+    fn palette_index_255_is_refused() {
+        let mut data = load(include_bytes!("resources/placeholder.vox"));
+        data.models[0].voxels[1].i = 255;
+        let voxel = data.models[0].voxels[1];
+
+        let error = data.to_bytes().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let inner = error
+            .get_ref()
+            .unwrap()
+            .downcast_ref::<crate::ValidationError>();
+        assert_eq!(
+            inner,
+            Some(&crate::ValidationError::InvalidPaletteIndex { model_id: 0, voxel })
+        );
+    }
+
+    #[test]
+    fn chunks_are_streamed_to_the_writer() {
+        /// Keeps what is written, and the size of the largest write.
+        #[derive(Default)]
+        struct Recorder {
+            bytes: Vec<u8>,
+            largest_write: usize,
+        }
+
+        impl Write for Recorder {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.largest_write = self.largest_write.max(buf.len());
+                self.bytes.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let data = load(include_bytes!("resources/axes.vox"));
+        let mut recorder = Recorder::default();
+        data.write_vox(&mut recorder).unwrap();
+        assert_eq!(load(&recorder.bytes), data);
+        // Chunks are written one by one, rather than all at once.
+        assert!(recorder.largest_write < recorder.bytes.len() / 4);
+    }
+
+    /// The children of MAIN, as their id and bytes.
+    fn chunks(bytes: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut chunks = Vec::new();
+        crate::visitor::for_each_chunk(bytes, |id, content, children, _| {
+            let mut chunk = id.to_vec();
+            chunk.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            chunk.extend_from_slice(&(children.len() as u32).to_le_bytes());
+            chunk.extend_from_slice(content);
+            chunk.extend_from_slice(children);
+            chunks.push((String::from_utf8_lossy(id).into_owned(), chunk));
+            Ok(())
+        })
+        .unwrap();
+        chunks
+    }
+
+    fn ids(chunks: &[(String, Vec<u8>)]) -> Vec<&str> {
+        chunks.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    fn file_of(chunks: &[(String, Vec<u8>)]) -> Vec<u8> {
+        let children: Vec<u8> = chunks.iter().flat_map(|(_, chunk)| chunk.clone()).collect();
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&150u32.to_le_bytes());
+        bytes.extend_from_slice(b"MAIN");
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        bytes.extend(children);
+        bytes
+    }
+
+    #[test]
+    fn chunk_order_is_preserved() {
+        for bytes in [
+            &include_bytes!("resources/axes.vox")[..],
+            include_bytes!("resources/placeholder-with-materials.vox"),
+            include_bytes!("resources/metal-material.vox"),
+        ] {
+            let data = load(bytes);
+            assert!(data.chunk_order.is_empty());
+            let written = data.to_bytes().unwrap();
+            assert_eq!(ids(&chunks(&written)), ids(&chunks(bytes)));
+        }
+
+        // Interleave chunks of different kinds.
+        let mut shuffled = chunks(include_bytes!("resources/axes.vox"));
+        let camera = shuffled.iter().rposition(|(id, _)| id == "rCAM").unwrap();
+        let camera = shuffled.remove(camera);
+        shuffled.insert(0, camera);
+        let material = shuffled.iter().position(|(id, _)| id == "MATL").unwrap();
+        let material = shuffled.remove(material);
+        shuffled.insert(3, material);
+        let note = shuffled.pop().unwrap();
+        let layer = shuffled.iter().position(|(id, _)| id == "LAYR").unwrap();
+        shuffled.insert(layer + 1, note);
+
+        let bytes = file_of(&shuffled);
+        let data = load(&bytes);
+        assert!(!data.chunk_order.is_empty());
+        let written = data.to_bytes().unwrap();
+        assert_eq!(ids(&chunks(&written)), ids(&shuffled));
+        assert_eq!(load(&written), data);
+        assert_eq!(
+            crate::DotVoxDataRef::parse(&bytes).unwrap().to_owned(),
+            data
+        );
+    }
+
+    #[test]
+    fn chunk_order_sets_the_order_of_chunks() {
+        let mut data = load(include_bytes!("resources/axes.vox"));
+        data.chunk_order = vec![
+            ChunkKind::Palette,
+            ChunkKind::Unknown,
+            ChunkKind::Models,
+            ChunkKind::Cameras,
+            ChunkKind::Models,
+            ChunkKind::IndexMap,
+        ];
+        let bytes = data.to_bytes().unwrap();
+        let written = chunks(&bytes);
+        let ids = ids(&written);
+        assert_eq!(
+            ids[..7],
+            ["RGBA", "NOTE", "SIZE", "XYZI", "rCAM", "SIZE", "XYZI"]
+        );
+        assert_eq!(ids.last(), Some(&"rCAM"));
+
+        // Entries that the rest of the chunks follow anyway are left out.
+        let reloaded = load(&bytes);
+        assert_eq!(reloaded.chunk_order, data.chunk_order[..4]);
+        assert_eq!(reloaded.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn models_are_written_in_order() {
+        let mut data = load(include_bytes!("resources/placeholder.vox"));
+        data.models = (0..100u8)
+            .map(|i| Model {
+                size: crate::Size { x: 1, y: 1, z: 1 },
+                voxels: vec![Voxel {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                    i,
+                }],
+            })
+            .collect();
+        data.scenes.clear();
+        let reloaded = load(&data.to_bytes().unwrap());
+        assert_eq!(reloaded.models, data.models);
+    }
+
+    #[test]
+    fn pack_is_written_only_on_request() {
+        let data = load(include_bytes!("resources/axes.vox"));
+        let bytes = data.to_bytes().unwrap();
+        assert!(!bytes.windows(4).any(|id| id == b"PACK"));
+
+        let options = WriteOptions {
+            pack: true,
+            ..WriteOptions::default()
+        };
+        let mut packed = Vec::new();
+        data.write_vox_with(&mut packed, options).unwrap();
+        let count = (data.models.len() as u32).to_le_bytes();
+        assert_eq!(&packed[20..28], b"PACK\x04\0\0\0");
+        assert_eq!(&packed[32..36], &count);
+        assert_eq!(packed.len(), bytes.len() + 16);
+
+        let reloaded = load(&packed);
+        assert_eq!(reloaded.unknown_chunks, data.unknown_chunks);
+        assert!(reloaded.equivalent(&data));
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{parser::ChunkLocation, IntegrityIssue};
+use std::{error, fmt, io};
+
+/// Error returned when loading a `.vox` file fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The data does not start with the `VOX ` magic number.
+    InvalidMagic,
+    /// The data ends in the middle of the header or of a chunk.
+    UnexpectedEof,
+    /// A chunk could not be parsed.
+    InvalidChunk {
+        /// Id of the chunk, decoded lossily.
+        id: String,
+        /// Offset of the chunk header in the data.
+        offset: usize,
+        /// Ids of the chunks enclosing this one, outermost first, e.g.
+        /// `["MAIN"]` for the scene graph chunks.
+        path: Vec<String>,
+    },
+    /// The integrity check requested by
+    /// [`ParseOptions::integrity_check`](crate::ParseOptions::integrity_check)
+    /// found issues.
+    Integrity(Vec<IntegrityIssue>),
+    /// The file exceeds a limit set on [`ParseOptions`](crate::ParseOptions).
+    LimitExceeded(Limit),
+    /// Parsing was aborted through
+    /// [`ParseOptions::cancel_flag`](crate::ParseOptions::cancel_flag).
+    Cancelled,
+    /// The file version is newer than [`SUPPORTED_VERSION`](crate::SUPPORTED_VERSION),
+    /// and [`ParseOptions::reject_newer_versions`](crate::ParseOptions::reject_newer_versions)
+    /// is set.
+    UnsupportedVersion(u32),
+}
+
+impl Error {
+    /// Converts a parsing error on `bytes` into an [`Error`].
+    pub(crate) fn from_nom(bytes: &[u8], error: nom::Err<nom::error::Error<&[u8]>>) -> Error {
+        if !bytes.starts_with(crate::parser::MAGIC_NUMBER.as_bytes()) {
+            return Error::InvalidMagic;
+        }
+        match error {
+            nom::Err::Incomplete(_) => Error::UnexpectedEof,
+            nom::Err::Error(error) | nom::Err::Failure(error) => {
+                if error.code == nom::error::ErrorKind::Eof {
+                    return Error::UnexpectedEof;
+                }
+                let offset = bytes.len() - error.input.len();
+                let id = bytes.get(offset..offset + 4).unwrap_or_default();
+                Error::InvalidChunk {
+                    id: String::from_utf8_lossy(id).into_owned(),
+                    offset,
+                    path: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "Unable to load file: {}", error),
+            Error::InvalidMagic => write!(f, "Not a valid MagicaVoxel .vox file"),
+            Error::UnexpectedEof => write!(f, "Unexpected end of file"),
+            Error::InvalidChunk { id, offset, path } => {
+                write!(f, "Invalid {:?} chunk at offset {} (", id, offset)?;
+                for parent in path {
+                    write!(f, "{} > ", parent)?;
+                }
+                write!(f, "{})", id)
+            }
+            Error::Integrity(issues) => {
+                write!(f, "File failed integrity check")?;
+                for issue in issues {
+                    write!(f, "; {}", issue)?;
+                }
+                Ok(())
+            }
+            Error::LimitExceeded(limit) => write!(f, "File exceeds the limit on {}", limit),
+            Error::Cancelled => write!(f, "Parsing was cancelled"),
+            Error::UnsupportedVersion(version) => write!(
+                f,
+                "File version {} is newer than the supported version {}",
+                version,
+                crate::SUPPORTED_VERSION
+            ),
+        }
+    }
+}
+
+/// A limit on the content of a file, see [`Error::LimitExceeded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Limit {
+    /// [`ParseOptions::max_total_voxels`](crate::ParseOptions::max_total_voxels).
+    TotalVoxels,
+    /// [`ParseOptions::max_nodes`](crate::ParseOptions::max_nodes).
+    Nodes,
+    /// [`ParseOptions::max_dict_entries`](crate::ParseOptions::max_dict_entries).
+    DictEntries,
+    /// [`ParseOptions::max_depth`](crate::ParseOptions::max_depth).
+    Depth,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::TotalVoxels => write!(f, "the total number of voxels"),
+            Limit::Nodes => write!(f, "the number of scene graph nodes"),
+            Limit::DictEntries => write!(f, "the total number of dictionary entries"),
+            Limit::Depth => write!(f, "the nesting depth of chunks"),
+        }
+    }
+}
+
+/// A problem skipped over by [`load_bytes_partial`](crate::load_bytes_partial).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The data ends before the end of a chunk. The part of the chunk that is
+    /// present is kept if it has children, and skipped otherwise.
+    Truncated {
+        /// Id of the chunk, decoded lossily.
+        id: String,
+        /// Offset of the chunk header in the data.
+        offset: usize,
+        /// Number of bytes declared by the chunk, including its header, or
+        /// `None` if the header itself is cut off.
+        declared: Option<usize>,
+        /// Number of bytes actually available.
+        available: usize,
+    },
+    /// A chunk could not be parsed and was skipped.
+    InvalidChunk {
+        /// Id of the chunk, decoded lossily.
+        id: String,
+        /// Offset of the chunk header in the data.
+        offset: usize,
+        /// Ids of the chunks enclosing this one, outermost first.
+        path: Vec<String>,
+    },
+    /// The file version is newer than [`SUPPORTED_VERSION`](crate::SUPPORTED_VERSION),
+    /// so the file may contain content that was not understood and dropped.
+    NewerVersion(u32),
+    /// A transform node uses the non-standard `_s` scale attribute, which is
+    /// ignored when placing models. See [`Frame::scale`](crate::Frame::scale).
+    NonStandardScale {
+        /// Index of the transform node in [`DotVoxData::scenes`](crate::DotVoxData::scenes).
+        node_id: u32,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::Truncated {
+                id,
+                offset,
+                declared: Some(declared),
+                available,
+            } => write!(
+                f,
+                "{:?} chunk at offset {} declares {} bytes, but only {} are available",
+                id, offset, declared, available
+            ),
+            Warning::Truncated {
+                id,
+                offset,
+                declared: None,
+                available,
+            } => write!(
+                f,
+                "{:?} chunk at offset {} is cut off after {} bytes of its header",
+                id, offset, available
+            ),
+            Warning::InvalidChunk { id, offset, path } => {
+                write!(f, "Skipped invalid {:?} chunk at offset {} (", id, offset)?;
+                for parent in path {
+                    write!(f, "{} > ", parent)?;
+                }
+                write!(f, "{})", id)
+            }
+            Warning::NewerVersion(version) => write!(
+                f,
+                "File version {} is newer than the supported version {}, some content may be lost",
+                version,
+                crate::SUPPORTED_VERSION
+            ),
+            Warning::NonStandardScale { node_id } => write!(
+                f,
+                "Transform node {} uses the non-standard '_s' scale attribute, which is ignored",
+                node_id
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ChunkLocation> for Error {
+    fn from(location: ChunkLocation) -> Self {
+        Error::InvalidChunk {
+            id: location.id,
+            offset: location.offset,
+            path: location.path,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_file_is_unexpected_eof() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        for len in [6, 20, bytes.len() / 2] {
+            assert!(matches!(
+                crate::load_bytes(&bytes[..len]),
+                Err(Error::UnexpectedEof)
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_chunk_id_is_reported() {
+        let mut bytes = include_bytes!("resources/placeholder.vox").to_vec();
+        bytes[8] = 0xff;
+        match crate::load_bytes(&bytes) {
+            Err(error @ Error::InvalidChunk { .. }) => {
+                assert_eq!(
+                    error.to_string(),
+                    "Invalid \"\u{fffd}AIN\" chunk at offset 8 (\u{fffd}AIN)"
+                );
+            }
+            result => panic!("Expected an invalid chunk, got {:?}", result),
+        }
+    }
+}
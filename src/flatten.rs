@@ -0,0 +1,163 @@
+use crate::{DotVoxData, Model, Size, Voxel};
+use std::collections::BTreeMap;
+
+/// Which voxel [`DotVoxData::flatten`] keeps where models overlap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overlap {
+    /// The voxel of the model placed first in depth-first order.
+    First,
+    /// The voxel of the model placed last in depth-first order, as if models
+    /// were painted over each other.
+    #[default]
+    Last,
+}
+
+impl DotVoxData {
+    /// Bakes every model placed by the scene graph into a single model, with
+    /// each voxel at its world position relative to the minimum corner of the
+    /// scene. Models that are not [visible](DotVoxData::is_visible) are left
+    /// out, and `overlap` decides which voxel is kept where models overlap.
+    /// Voxels are sorted by z, then y, then x.
+    ///
+    /// Returns `None` if the scene does not fit in the 256 voxels per axis
+    /// allowed by the format. A scene without voxels gives an empty model of
+    /// size zero.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = load("src/resources/placeholder.vox").unwrap();
+    /// let model = data.flatten(Overlap::Last).unwrap();
+    /// assert_eq!(model.size, Size { x: 2, y: 2, z: 2 });
+    /// assert_eq!(model.voxels.len(), data.world_voxels().len());
+    /// ```
+    pub fn flatten(&self, overlap: Overlap) -> Option<Model> {
+        let mut voxels = BTreeMap::new();
+        for voxel in self.world_voxels() {
+            let position = voxel.position;
+            let key = (position.z, position.y, position.x);
+            match overlap {
+                Overlap::First => {
+                    voxels.entry(key).or_insert(voxel.i);
+                }
+                Overlap::Last => {
+                    voxels.insert(key, voxel.i);
+                }
+            }
+        }
+
+        let (mut min, mut max) = ([i32::MAX; 3], [i32::MIN; 3]);
+        for &(z, y, x) in voxels.keys() {
+            for (axis, value) in [x, y, z].into_iter().enumerate() {
+                min[axis] = min[axis].min(value);
+                max[axis] = max[axis].max(value);
+            }
+        }
+        if voxels.is_empty() {
+            return Some(Model {
+                size: Size { x: 0, y: 0, z: 0 },
+                voxels: Vec::new(),
+            });
+        }
+        let extent = |axis: usize| (max[axis] as i64 - min[axis] as i64 + 1) as u32;
+        let size = Size {
+            x: extent(0),
+            y: extent(1),
+            z: extent(2),
+        };
+        if !size.is_within_limits() {
+            return None;
+        }
+
+        let voxels = voxels
+            .into_iter()
+            .map(|((z, y, x), i)| Voxel {
+                x: (x - min[0]) as u8,
+                y: (y - min[1]) as u8,
+                z: (z - min[2]) as u8,
+                i,
+            })
+            .collect();
+        Some(Model { size, voxels })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Position, Rotation, SceneBuilder};
+
+    fn overlapping() -> DotVoxData {
+        let model = |i: u8| Model {
+            size: Size { x: 2, y: 1, z: 1 },
+            voxels: vec![
+                Voxel {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                    i,
+                },
+                Voxel {
+                    x: 1,
+                    y: 0,
+                    z: 0,
+                    i,
+                },
+            ],
+        };
+        DotVoxData::builder()
+            .add_model_at(model(1), Position { x: 1, y: 0, z: 0 }, Rotation::IDENTITY)
+            .add_model_at(model(2), Position { x: 2, y: 0, z: 0 }, Rotation::IDENTITY)
+            .build()
+    }
+
+    #[test]
+    fn overlapping_voxels_are_resolved() {
+        let data = overlapping();
+        let colors = |overlap| -> Vec<u8> {
+            let model = data.flatten(overlap).unwrap();
+            assert_eq!(model.size, Size { x: 3, y: 1, z: 1 });
+            model.voxels.iter().map(|voxel| voxel.i).collect()
+        };
+        assert_eq!(colors(Overlap::First), [1, 1, 2]);
+        assert_eq!(colors(Overlap::Last), [1, 2, 2]);
+    }
+
+    #[test]
+    fn flattened_voxels_keep_their_relative_positions() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        let model = data.flatten(Overlap::Last).unwrap();
+        let mut world: Vec<_> = data.world_voxels().collect();
+        let min = |axis: fn(&Position) -> i32| {
+            world
+                .iter()
+                .map(|voxel| axis(&voxel.position))
+                .min()
+                .unwrap()
+        };
+        let (x, y, z) = (min(|p| p.x), min(|p| p.y), min(|p| p.z));
+        world.sort_by_key(|voxel| (voxel.position.z, voxel.position.y, voxel.position.x));
+        world.dedup_by_key(|voxel| voxel.position);
+        assert_eq!(model.voxels.len(), world.len());
+        for (voxel, world) in model.voxels.iter().zip(world) {
+            assert_eq!(voxel.x as i32 + x, world.position.x);
+            assert_eq!(voxel.y as i32 + y, world.position.y);
+            assert_eq!(voxel.z as i32 + z, world.position.z);
+        }
+    }
+
+    #[test]
+    fn empty_and_oversized_scenes() {
+        let empty = DotVoxData::new().flatten(Overlap::Last).unwrap();
+        assert_eq!(empty.size, Size { x: 0, y: 0, z: 0 });
+
+        let mut data = overlapping();
+        let mut scene = SceneBuilder::new();
+        for x in [0, 300] {
+            let shape = scene.add_shape(SceneBuilder::ROOT, 0).unwrap();
+            scene.set_translation(shape, Position { x, y: 0, z: 0 });
+        }
+        data.scenes = scene.build();
+        assert_eq!(data.flatten(Overlap::Last), None);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{DotVoxData, SceneNode};
+
+/// Selects what [`DotVoxData::gc_with`] removes. Everything is removed by
+/// default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GcOptions {
+    /// Remove scene nodes that cannot be reached from the root node.
+    pub scenes: bool,
+    /// Remove models not referenced by any shape node. Models are never
+    /// removed from files without a scene graph.
+    pub models: bool,
+    /// Remove layers not referenced by any transform node.
+    pub layers: bool,
+    /// Remove materials for palette indices not used by any voxel.
+    pub materials: bool,
+}
+
+impl Default for GcOptions {
+    fn default() -> Self {
+        GcOptions {
+            scenes: true,
+            models: true,
+            layers: true,
+            materials: true,
+        }
+    }
+}
+
+/// Number of items removed by [`DotVoxData::gc`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Number of scene nodes removed.
+    pub scenes: usize,
+    /// Number of models removed.
+    pub models: usize,
+    /// Number of layers removed.
+    pub layers: usize,
+    /// Number of materials removed.
+    pub materials: usize,
+}
+
+impl DotVoxData {
+    /// Removes unreachable scene nodes, unreferenced models, unused layers and
+    /// orphan materials, updating every index referring to the items that
+    /// remain. Equivalent to [`gc_with(GcOptions::default())`](Self::gc_with).
+    pub fn gc(&mut self) -> GcReport {
+        self.gc_with(GcOptions::default())
+    }
+
+    /// Removes the garbage selected by `options`. See [`gc`](Self::gc).
+    pub fn gc_with(&mut self, options: GcOptions) -> GcReport {
+        let mut report = GcReport::default();
+        if options.scenes {
+            report.scenes = self.gc_scenes();
+        }
+        if options.models {
+            report.models = self.gc_models();
+        }
+        if options.layers {
+            report.layers = self.gc_layers();
+        }
+        if options.materials {
+            report.materials = self.gc_materials();
+        }
+        report
+    }
+
+    fn gc_scenes(&mut self) -> usize {
+        if self.scenes.is_empty() {
+            return 0;
+        }
+
+        let mut reachable = vec![false; self.scenes.len()];
+        let mut stack = vec![0u32];
+        while let Some(node) = stack.pop() {
+            match reachable.get_mut(node as usize) {
+                Some(seen) if !*seen => *seen = true,
+                _ => continue,
+            }
+            match &self.scenes[node as usize] {
+                SceneNode::Transform { child, .. } => stack.push(*child),
+                SceneNode::Group { children, .. } => stack.extend(children.iter().copied()),
+                SceneNode::Shape { .. } => {}
+            }
+        }
+
+        let remap = compact(&reachable);
+        let mut index = 0;
+        self.scenes.retain(|_| {
+            index += 1;
+            reachable[index - 1]
+        });
+        for node in self.scenes.iter_mut() {
+            match node {
+                SceneNode::Transform { child, .. } => *child = remap_index(&remap, *child),
+                SceneNode::Group { children, .. } => {
+                    for child in children.iter_mut() {
+                        *child = remap_index(&remap, *child);
+                    }
+                }
+                SceneNode::Shape { .. } => {}
+            }
+        }
+
+        reachable.len() - self.scenes.len()
+    }
+
+    fn gc_models(&mut self) -> usize {
+        if self.scenes.is_empty() {
+            return 0;
+        }
+
+        let mut used = vec![false; self.models.len()];
+        for node in self.scenes.iter() {
+            if let SceneNode::Shape { models, .. } = node {
+                for model in models {
+                    if let Some(used) = used.get_mut(model.model_id as usize) {
+                        *used = true;
+                    }
+                }
+            }
+        }
+
+        let remap = compact(&used);
+        let mut index = 0;
+        self.models.retain(|_| {
+            index += 1;
+            used[index - 1]
+        });
+        for node in self.scenes.iter_mut() {
+            if let SceneNode::Shape { models, .. } = node {
+                for model in models.iter_mut() {
+                    model.model_id = remap_index(&remap, model.model_id);
+                }
+            }
+        }
+
+        used.len() - self.models.len()
+    }
+
+    fn gc_layers(&mut self) -> usize {
+        let mut used = vec![false; self.layers.len()];
+        for node in self.scenes.iter() {
+            if let SceneNode::Transform { layer_id, .. } = node {
+                if let Some(used) = used.get_mut(*layer_id as usize) {
+                    *used = true;
+                }
+            }
+        }
+
+        let remap = compact(&used);
+        let mut index = 0;
+        self.layers.retain(|_| {
+            index += 1;
+            used[index - 1]
+        });
+        for node in self.scenes.iter_mut() {
+            if let SceneNode::Transform { layer_id, .. } = node {
+                *layer_id = remap_index(&remap, *layer_id);
+            }
+        }
+
+        used.len() - self.layers.len()
+    }
+
+    fn gc_materials(&mut self) -> usize {
+        let mut used = [false; 257];
+        for voxel in self.models.iter().flat_map(|model| &model.voxels) {
+            used[voxel.i as usize + 1] = true;
+        }
+
+        let count = self.materials.len();
+        self.materials
+            .retain(|material| used.get(material.id as usize) == Some(&true));
+        count - self.materials.len()
+    }
+}
+
+impl DotVoxData {
+    /// Keeps only the layers at the given indices, removing the subtrees of
+    /// transform nodes on other layers and the models only they place.
+    pub(crate) fn retain_layers(&mut self, layers: &[u32]) {
+        let excluded = |node: u32| match self.scenes.get(node as usize) {
+            Some(SceneNode::Transform { layer_id, .. }) => {
+                (*layer_id as usize) < self.layers.len() && !layers.contains(layer_id)
+            }
+            _ => false,
+        };
+        let children: Vec<Option<Vec<u32>>> = self
+            .scenes
+            .iter()
+            .map(|node| match node {
+                SceneNode::Group { children, .. } => Some(
+                    children
+                        .iter()
+                        .copied()
+                        .filter(|&child| !excluded(child))
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect();
+        for (node, kept) in self.scenes.iter_mut().zip(children) {
+            if let (SceneNode::Group { children, .. }, Some(kept)) = (node, kept) {
+                *children = kept;
+            }
+        }
+        self.gc_with(GcOptions {
+            scenes: true,
+            models: true,
+            layers: false,
+            materials: false,
+        });
+
+        let keep: Vec<bool> = (0..self.layers.len() as u32)
+            .map(|layer| layers.contains(&layer))
+            .collect();
+        let remap = compact(&keep);
+        let mut index = 0;
+        self.layers.retain(|_| {
+            index += 1;
+            keep[index - 1]
+        });
+        for node in self.scenes.iter_mut() {
+            if let SceneNode::Transform { layer_id, .. } = node {
+                *layer_id = remap_index(&remap, *layer_id);
+            }
+        }
+    }
+}
+
+/// Maps the indices of kept items to their index once removed items are
+/// dropped.
+fn compact(keep: &[bool]) -> Vec<Option<u32>> {
+    let mut next = 0;
+    keep.iter()
+        .map(|keep| {
+            keep.then(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect()
+}
+
+/// Indices pointing to removed or missing items are left untouched, so that
+/// they remain dangling.
+fn remap_index(remap: &[Option<u32>], index: u32) -> u32 {
+    remap
+        .get(index as usize)
+        .copied()
+        .flatten()
+        .unwrap_or(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dict, Layer};
+
+    #[test]
+    fn placeholder_has_only_unused_layers_and_materials() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        let report = data.gc();
+        assert_eq!(
+            report,
+            GcReport {
+                scenes: 0,
+                models: 0,
+                layers: 7,
+                materials: 252,
+            }
+        );
+        assert_eq!(data.layers[0].name(), Some("0".to_owned()));
+        let ids: Vec<u32> = data.materials.iter().map(|m| m.id).collect();
+        assert_eq!(ids, [6, 216, 226, 236]);
+    }
+
+    #[test]
+    fn removes_unreachable_nodes_and_their_models() {
+        let mut data = crate::load("src/resources/transforms.vox").unwrap();
+        // Detach the second child of the root group, the only user of model 1.
+        if let SceneNode::Group { children, .. } = &mut data.scenes[1] {
+            children.retain(|child| *child != 4);
+        }
+        data.layers.push(Layer {
+            attributes: Dict::new(),
+        });
+        let options = GcOptions {
+            materials: false,
+            ..GcOptions::default()
+        };
+
+        let report = data.gc_with(options);
+        assert_eq!(report.scenes, 4);
+        assert_eq!(report.models, 1);
+        assert_eq!(report.materials, 0);
+        assert_eq!(data.scenes.len(), 6);
+        assert_eq!(data.models.len(), 1);
+        match &data.scenes[1] {
+            SceneNode::Group { children, .. } => assert_eq!(children, &[2, 4]),
+            node => panic!("Expected a group node, got {:?}", node),
+        }
+        match &data.scenes[4] {
+            SceneNode::Transform { child, .. } => assert_eq!(*child, 5),
+            node => panic!("Expected a transform node, got {:?}", node),
+        }
+    }
+}
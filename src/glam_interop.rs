@@ -0,0 +1,70 @@
+//! Conversions to [`glam`] types, behind the `glam` feature.
+
+use crate::{Position, Rotation, Size};
+
+impl From<Rotation> for glam::Mat3 {
+    /// The signed permutation matrix of the rotation, which may include a
+    /// reflection.
+    fn from(rotation: Rotation) -> Self {
+        glam::Mat3::from_cols_array_2d(&rotation.to_cols_array_2d())
+    }
+}
+
+impl From<Rotation> for glam::Quat {
+    /// The rotation part of [`Rotation::to_quat_scale`]. Reflections cannot
+    /// be represented by a quaternion: convert to a [`glam::Mat3`], or apply
+    /// the scale of `to_quat_scale` as well, to keep them.
+    fn from(rotation: Rotation) -> Self {
+        glam::Quat::from_array(rotation.to_quat_scale().0)
+    }
+}
+
+impl From<Position> for glam::IVec3 {
+    fn from(position: Position) -> Self {
+        glam::IVec3::new(position.x, position.y, position.z)
+    }
+}
+
+impl From<glam::IVec3> for Position {
+    fn from(vector: glam::IVec3) -> Self {
+        Position {
+            x: vector.x,
+            y: vector.y,
+            z: vector.z,
+        }
+    }
+}
+
+impl From<Size> for glam::UVec3 {
+    fn from(size: Size) -> Self {
+        glam::UVec3::new(size.x, size.y, size.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_match_integer_math() {
+        let point = Position { x: 4, y: -1, z: 9 };
+        for rotation in Rotation::all() {
+            let expected = glam::IVec3::from(rotation * point).as_vec3();
+
+            let matrix = glam::Mat3::from(rotation);
+            assert_eq!(matrix * glam::IVec3::from(point).as_vec3(), expected);
+
+            let (_, scale) = rotation.to_quat_scale();
+            let quat = glam::Quat::from(rotation);
+            let rotated =
+                quat * (glam::Vec3::from_array(scale) * glam::IVec3::from(point).as_vec3());
+            assert!(rotated.abs_diff_eq(expected, 1e-5), "{:?}", rotation);
+        }
+
+        assert_eq!(Position::from(glam::IVec3::from(point)), point);
+        assert_eq!(
+            glam::UVec3::from(Size { x: 1, y: 2, z: 3 }),
+            glam::UVec3::new(1, 2, 3)
+        );
+    }
+}
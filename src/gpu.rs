@@ -0,0 +1,243 @@
+//! Plain data types laid out for upload to GPU buffers.
+//!
+//! Every type here is `#[repr(C)]` without implicit padding, as are
+//! [`Voxel`] and [`Color`]. With the `bytemuck` feature enabled they all
+//! implement [`bytemuck::Pod`], so slices of them can be viewed as bytes with
+//! `bytemuck::cast_slice` and uploaded without copies.
+
+use crate::{DotVoxData, Model};
+
+/// Side of a [`Brick`] in voxels.
+pub const BRICK_SIZE: u32 = 8;
+
+/// A model instance, for instanced rendering.
+///
+/// Laid out as 80 bytes: the 64 byte column-major matrix, followed by the
+/// model id and 12 bytes of padding, keeping arrays of instances 16 byte
+/// aligned as uniform and storage buffers require.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+pub struct GpuInstance {
+    /// Column-major matrix from model space, where the voxel `(x, y, z)`
+    /// spans `x..x + 1`, `y..y + 1` and `z..z + 1`, to world space.
+    pub transform: [[f32; 4]; 4],
+    /// Index of the model in [`DotVoxData::models`].
+    pub model_id: u32,
+    padding: [u32; 3],
+}
+
+impl GpuInstance {
+    /// Creates an instance of a model with the given transform.
+    pub fn new(transform: [[f32; 4]; 4], model_id: u32) -> Self {
+        GpuInstance {
+            transform,
+            model_id,
+            padding: [0; 3],
+        }
+    }
+}
+
+/// An 8×8×8 block of a model, for sparse voxel storage on the GPU.
+///
+/// Laid out as 592 bytes: the origin and voxel count as four `u32`, the
+/// occupancy bitmask as sixteen `u32`, then one palette index per voxel.
+/// Voxels are numbered `x + 8 * y + 64 * z` relative to the origin, both in
+/// the bitmask, least significant bit first, and in the palette indices.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
+pub struct Brick {
+    /// Model space coordinates of the voxel at the minimum corner of the
+    /// brick, a multiple of [`BRICK_SIZE`].
+    pub origin: [u32; 3],
+    /// Number of occupied voxels.
+    pub voxel_count: u32,
+    /// One bit per voxel, set for occupied voxels.
+    pub occupancy: [u32; 16],
+    /// Palette index of each voxel, `0` for empty voxels.
+    pub indices: [u8; 512],
+}
+
+impl Brick {
+    fn empty(origin: [u32; 3]) -> Self {
+        Brick {
+            origin,
+            voxel_count: 0,
+            occupancy: [0; 16],
+            indices: [0; 512],
+        }
+    }
+
+    /// Palette index of the voxel at the given position relative to the
+    /// origin, if occupied.
+    pub fn get(&self, x: u32, y: u32, z: u32) -> Option<u8> {
+        if x >= BRICK_SIZE || y >= BRICK_SIZE || z >= BRICK_SIZE {
+            return None;
+        }
+        let bit = (x + BRICK_SIZE * (y + BRICK_SIZE * z)) as usize;
+        (self.occupancy[bit / 32] & (1 << (bit % 32)) != 0).then_some(self.indices[bit])
+    }
+}
+
+impl Model {
+    /// Splits the model into [`Brick`]s, skipping empty ones. Bricks are
+    /// ordered by origin, along X first, then Y, then Z.
+    pub fn bricks(&self) -> Vec<Brick> {
+        let mut bricks: Vec<Brick> = Vec::new();
+        let mut voxels: Vec<_> = self.voxels.iter().collect();
+        let brick_of =
+            |x: u8, y: u8, z: u8| [z, y, x].map(|coordinate| coordinate as u32 / BRICK_SIZE);
+        voxels.sort_by_key(|voxel| brick_of(voxel.x, voxel.y, voxel.z));
+        for voxel in voxels {
+            let [z, y, x] = brick_of(voxel.x, voxel.y, voxel.z);
+            let origin = [x, y, z].map(|coordinate| coordinate * BRICK_SIZE);
+            if bricks.last().map(|brick| brick.origin) != Some(origin) {
+                bricks.push(Brick::empty(origin));
+            }
+            let brick = bricks.last_mut().unwrap();
+            let [x, y, z] = [voxel.x, voxel.y, voxel.z].map(|c| c as u32 % BRICK_SIZE);
+            let bit = (x + BRICK_SIZE * (y + BRICK_SIZE * z)) as usize;
+            if brick.occupancy[bit / 32] & (1 << (bit % 32)) == 0 {
+                brick.voxel_count += 1;
+            }
+            brick.occupancy[bit / 32] |= 1 << (bit % 32);
+            brick.indices[bit] = voxel.i;
+        }
+        bricks
+    }
+}
+
+impl DotVoxData {
+    /// Returns a [`GpuInstance`] for every model placed by the scene graph,
+    /// in the order of [`DotVoxData::instances`].
+    ///
+    /// The transforms place voxels exactly where
+    /// [`DotVoxData::world_voxels`] does.
+    pub fn gpu_instances(&self) -> Vec<GpuInstance> {
+        self.placed_models()
+            .iter()
+            .filter_map(|placed| {
+                let size = self.models.get(placed.model_id as usize)?.size;
+                let size = [size.x as i32, size.y as i32, size.z as i32];
+                let rotation = placed.rotation.to_cols_array_2d();
+                let rotated_size = placed.rotation.transform_point(size);
+
+                // The center of the model lands on the translation, shifted
+                // by half a voxel along axes of odd size to stay on the grid.
+                let mut translation = [0, 1, 2].map(|k| {
+                    placed.translation[k] as f32 + (rotated_size[k] % 2).abs() as f32 / 2.0
+                });
+                for (column, size) in rotation.iter().zip(size) {
+                    for (t, r) in translation.iter_mut().zip(column) {
+                        *t -= r * size as f32 / 2.0;
+                    }
+                }
+
+                let [c0, c1, c2] = rotation.map(|[x, y, z]| [x, y, z, 0.0]);
+                let [tx, ty, tz] = translation;
+                Some(GpuInstance::new(
+                    [c0, c1, c2, [tx, ty, tz, 1.0]],
+                    placed.model_id,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, Voxel};
+    use std::mem::{align_of, size_of};
+
+    #[test]
+    fn layouts_have_no_padding() {
+        assert_eq!(size_of::<Voxel>(), 4);
+        assert_eq!(size_of::<Color>(), 4);
+        assert_eq!(size_of::<GpuInstance>(), 80);
+        assert_eq!(size_of::<Brick>(), 592);
+        assert_eq!(align_of::<Brick>(), 4);
+    }
+
+    #[test]
+    fn gpu_instances_match_world_voxels() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        let instances = data.gpu_instances();
+        assert_eq!(instances.len(), data.instances().count());
+        let mut world = data.world_voxels();
+        for instance in instances.iter() {
+            let model = &data.models[instance.model_id as usize];
+            for voxel in model.voxels.iter() {
+                // Transform the center of the voxel.
+                let center = [voxel.x, voxel.y, voxel.z].map(|c| c as f32 + 0.5);
+                let m = &instance.transform;
+                let position =
+                    [0, 1, 2].map(|r| (0..3).map(|c| m[c][r] * center[c]).sum::<f32>() + m[3][r]);
+                let expected = world.next().unwrap().position;
+                assert_eq!(
+                    position.map(|p| p.floor() as i32),
+                    [expected.x, expected.y, expected.z]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bricks_hold_every_voxel() {
+        let model = Model {
+            size: crate::Size { x: 20, y: 3, z: 9 },
+            voxels: vec![
+                Voxel {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                    i: 7,
+                },
+                Voxel {
+                    x: 19,
+                    y: 2,
+                    z: 8,
+                    i: 9,
+                },
+                Voxel {
+                    x: 9,
+                    y: 1,
+                    z: 0,
+                    i: 0,
+                },
+            ],
+        };
+        let bricks = model.bricks();
+        let origins: Vec<_> = bricks.iter().map(|brick| brick.origin).collect();
+        assert_eq!(origins, [[0, 0, 0], [8, 0, 0], [16, 0, 8]]);
+        assert!(bricks.iter().all(|brick| brick.voxel_count == 1));
+        assert_eq!(bricks[0].get(0, 0, 0), Some(7));
+        assert_eq!(bricks[0].get(1, 0, 0), None);
+        assert_eq!(bricks[1].get(1, 1, 0), Some(0));
+        assert_eq!(bricks[2].get(3, 2, 0), Some(9));
+        assert_eq!(bricks[2].get(8, 0, 0), None);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn casts_to_bytes() {
+        let data = crate::load("src/resources/placeholder.vox").unwrap();
+        let bytes: &[u8] = bytemuck::cast_slice(&data.palette);
+        assert_eq!(bytes.len(), data.palette.len() * 4);
+        assert_eq!(
+            bytes[..4],
+            [
+                data.palette[0].r,
+                data.palette[0].g,
+                data.palette[0].b,
+                data.palette[0].a
+            ]
+        );
+        let voxels: &[u8] = bytemuck::cast_slice(&data.models[0].voxels);
+        assert_eq!(voxels.len(), data.models[0].voxels.len() * 4);
+        let instances = data.gpu_instances();
+        let instance_bytes: &[u8] = bytemuck::cast_slice(&instances);
+        assert_eq!(instance_bytes.len(), 80);
+    }
+}
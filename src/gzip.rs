@@ -0,0 +1,68 @@
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// The first bytes of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether `file` starts with a gzip header. The file is rewound after.
+pub(crate) fn is_compressed(file: &mut File) -> Result<bool, io::Error> {
+    let mut magic = Vec::with_capacity(GZIP_MAGIC.len());
+    (&mut *file)
+        .take(GZIP_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(magic == GZIP_MAGIC)
+}
+
+/// Whether a file saved at `path` should be compressed, i.e. it has a `.gz`
+/// extension.
+pub(crate) fn is_compressed_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+/// Decompresses `file`, which may hold several concatenated gzip members.
+pub(crate) fn decoder(file: File) -> impl Read {
+    MultiGzDecoder::new(BufReader::new(file))
+}
+
+pub(crate) fn encoder<W: Write>(writer: W) -> GzEncoder<W> {
+    GzEncoder::new(writer, Compression::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gz_files_are_compressed_and_loaded() {
+        let data = crate::load("src/resources/placeholder.vox").unwrap();
+        let dir = std::env::temp_dir().join("dot_vox_gz_files_are_compressed_and_loaded");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("placeholder.vox.gz");
+        data.save(&path).unwrap();
+        assert!(std::fs::read(&path).unwrap().starts_with(&GZIP_MAGIC));
+        assert_eq!(crate::load(path.to_str().unwrap()).unwrap(), data);
+
+        let atomic = dir.join("atomic.VOX.GZ");
+        data.save_atomic(&atomic).unwrap();
+        assert!(std::fs::read(&atomic).unwrap().starts_with(&GZIP_MAGIC));
+
+        // Detection relies on the content, not the extension.
+        let renamed = dir.join("renamed.vox");
+        std::fs::rename(&atomic, &renamed).unwrap();
+        assert_eq!(crate::load(renamed.to_str().unwrap()).unwrap(), data);
+
+        let plain = dir.join("plain.vox");
+        data.save(&plain).unwrap();
+        assert!(!std::fs::read(&plain).unwrap().starts_with(&GZIP_MAGIC));
+        assert_eq!(crate::load(plain.to_str().unwrap()).unwrap(), data);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    model,
+    visitor::{for_each_chunk, voxels_of},
+    Error, Model, RawVoxels, Size,
+};
+
+/// Where the models of a `.vox` file are, found by [`VoxIndex::scan`]
+/// without decoding their voxels.
+///
+/// Scanning only reads chunk headers and `SIZE` chunks, so it is cheap even
+/// for large files. Models are then decoded one at a time with
+/// [`VoxIndex::load_model`], e.g. to stream in only the parts of a scene
+/// that are visible.
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let index = VoxIndex::scan(include_bytes!("resources/placeholder.vox")).unwrap();
+/// assert_eq!(index.len(), 1);
+/// assert_eq!(index.entry(0).unwrap().voxel_count, 4);
+///
+/// let model = index.load_model(0).unwrap();
+/// assert_eq!(model.voxels.len(), 4);
+/// ```
+#[derive(Clone, Debug)]
+pub struct VoxIndex<'a> {
+    version: u32,
+    entries: Vec<ModelEntry>,
+    voxels: Vec<&'a [u8]>,
+}
+
+/// The location and size of a model within a file, as recorded by
+/// [`VoxIndex::scan`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModelEntry {
+    /// The size of the model, clamped as by [`load_bytes`](crate::load_bytes).
+    pub size: Size,
+    /// Offset of the model's `XYZI` chunk from the start of the file.
+    pub offset: usize,
+    /// Number of voxels in the model.
+    pub voxel_count: usize,
+}
+
+impl<'a> VoxIndex<'a> {
+    /// Records the location of every model in `bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidMagic`] for data that is not a `.vox` file,
+    /// [`Error::UnexpectedEof`] if a chunk is cut off, or
+    /// [`Error::InvalidChunk`] if a `SIZE` chunk cannot be decoded or an
+    /// `XYZI` chunk holds fewer voxels than it declares.
+    pub fn scan(bytes: &'a [u8]) -> Result<VoxIndex<'a>, Error> {
+        let mut entries = Vec::new();
+        let mut voxels = Vec::new();
+        let mut size = None;
+        let version = for_each_chunk(bytes, |id, content, _, location| {
+            match id {
+                b"SIZE" => {
+                    let (_, parsed) = model::parse_size(content).map_err(|_| location())?;
+                    size = Some(parsed.clamped());
+                }
+                b"XYZI" => {
+                    let raw = voxels_of(content).ok_or_else(location)?;
+                    // Like the parser, skip voxels without a preceding SIZE.
+                    if let Some(size) = size {
+                        entries.push(ModelEntry {
+                            size,
+                            offset: location().offset,
+                            voxel_count: raw.len(),
+                        });
+                        voxels.push(raw.as_bytes());
+                    }
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(VoxIndex {
+            version,
+            entries,
+            voxels,
+        })
+    }
+
+    /// The version of the file.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Number of models in the file.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the file has no models.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Where model `n` is, in the order of [`DotVoxData::models`](crate::DotVoxData::models).
+    pub fn entry(&self, n: usize) -> Option<&ModelEntry> {
+        self.entries.get(n)
+    }
+
+    /// All the models, in the order of [`DotVoxData::models`](crate::DotVoxData::models).
+    pub fn entries(&self) -> &[ModelEntry] {
+        &self.entries
+    }
+
+    /// The voxels of model `n`, borrowed from the input, without collecting
+    /// them.
+    pub fn voxels(&self, n: usize) -> Option<RawVoxels<'a>> {
+        self.voxels.get(n).map(|&bytes| RawVoxels::new(bytes))
+    }
+
+    /// Decodes model `n`, equal to `load_bytes(bytes)?.models[n]`. Returns
+    /// `None` if there is no such model.
+    pub fn load_model(&self, n: usize) -> Option<Model> {
+        Some(Model {
+            size: self.entries.get(n)?.size,
+            voxels: self.voxels(n)?.collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_load_as_with_load_bytes() {
+        let bytes = include_bytes!("resources/placeholder-with-materials.vox");
+        let data = crate::load_bytes(bytes).unwrap();
+        let index = VoxIndex::scan(bytes).unwrap();
+        assert_eq!(index.version(), data.version);
+        assert_eq!(index.len(), data.models.len());
+        for (n, model) in data.models.iter().enumerate() {
+            let entry = index.entry(n).unwrap();
+            assert_eq!(&bytes[entry.offset..entry.offset + 4], b"XYZI");
+            assert_eq!(entry.voxel_count, model.voxels.len());
+            assert_eq!(index.load_model(n).as_ref(), Some(model));
+        }
+        assert_eq!(index.load_model(data.models.len()), None);
+    }
+
+    #[test]
+    fn scan_reports_malformed_files() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        assert!(matches!(
+            VoxIndex::scan(&bytes[..bytes.len() - 1]),
+            Err(Error::UnexpectedEof)
+        ));
+
+        let mut bytes = bytes.to_vec();
+        let xyzi = bytes.windows(4).position(|id| id == b"XYZI").unwrap();
+        bytes[xyzi + 12] = 200;
+        assert!(matches!(
+            VoxIndex::scan(&bytes),
+            Err(Error::InvalidChunk { offset, .. }) if offset == xyzi
+        ));
+    }
+}
@@ -0,0 +1,508 @@
+use crate::{
+    scene::{PlacedModel, SceneWalk},
+    DotVoxData, Model, Position, Rotation, SceneNode, Transform,
+};
+
+/// A model placed in the world by the scene graph.
+///
+/// Models placed several times give one instance each, with the same
+/// `model_id`, so that renderers can share their data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instance {
+    /// Index of the model in [`DotVoxData::models`].
+    pub model_id: u32,
+    /// Rotation of the model, accumulated from all its ancestors.
+    pub rotation: Rotation,
+    /// World space position of the center of the model, as positioned by
+    /// MagicaVoxel.
+    pub translation: Position,
+    /// Layer of the transform node placing the model, an index into
+    /// [`DotVoxData::layers`], or `u32::MAX` for none.
+    pub layer_id: u32,
+    /// Name of the transform node placing the model, as shown by
+    /// MagicaVoxel, if it has one.
+    pub name: Option<String>,
+}
+
+impl Instance {
+    /// The [`layer_id`](Instance::layer_id) of the model, or `None` if it is
+    /// on no layer.
+    pub fn layer(&self) -> Option<u32> {
+        Some(self.layer_id).filter(|&layer_id| layer_id != u32::MAX)
+    }
+
+    /// The transform placing the center of the model in world space.
+    pub fn transform(&self) -> Transform {
+        Transform {
+            rotation: self.rotation,
+            translation: self.translation,
+        }
+    }
+}
+
+/// A voxel of a model, in world space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WorldVoxel {
+    /// World space coordinates of the voxel.
+    pub position: Position,
+    /// Index in the color palette, see [`Voxel::i`](crate::Voxel::i).
+    pub i: u8,
+}
+
+impl DotVoxData {
+    /// Iterates over every model placed by the scene graph, in depth-first
+    /// order, skipping those that are not [visible](DotVoxData::is_visible).
+    /// Files without a scene graph place every model with its minimum corner
+    /// at the origin.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = load("src/resources/placeholder.vox").unwrap();
+    /// let instances: Vec<Instance> = data.instances().collect();
+    /// assert_eq!(instances.len(), 1);
+    /// assert_eq!(instances[0].model_id, 0);
+    /// assert_eq!(instances[0].translation, Position { x: 0, y: 0, z: 1 });
+    /// assert_eq!(instances[0].layer_id, 0);
+    /// ```
+    pub fn instances(&self) -> Instances<'_> {
+        self.scene_at_frame(0)
+    }
+
+    /// Like [`instances`](DotVoxData::instances), but with the scene evaluated
+    /// at animation frame `frame`: transform nodes use the keyframe in effect
+    /// at that frame, and shape nodes the model, as described for
+    /// [`visit_scene_at`](DotVoxData::visit_scene_at). Playing `frame` from 0
+    /// on animates the scene like MagicaVoxel.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut data = load("src/resources/placeholder.vox").unwrap();
+    /// data.models.push(data.models[0].clone());
+    /// if let SceneNode::Shape { models, .. } = &mut data.scenes[3] {
+    ///     let mut second = models[0].clone();
+    ///     second.model_id = 1;
+    ///     second.attributes.insert("_f".to_owned(), "4".to_owned());
+    ///     models.push(second);
+    /// }
+    /// let model_at = |frame| data.scene_at_frame(frame).next().unwrap().model_id;
+    /// assert_eq!(model_at(3), 0);
+    /// assert_eq!(model_at(4), 1);
+    /// ```
+    pub fn scene_at_frame(&self, frame: u32) -> Instances<'_> {
+        Instances {
+            data: self,
+            front: self.walk_scene(frame, false),
+            back: self.walk_scene(frame, true),
+            remaining: None,
+        }
+    }
+
+    /// Iterates over the voxels of every [`Instance`], in world space. Voxels
+    /// are produced one at a time, without collecting them first.
+    pub fn world_voxels(&self) -> WorldVoxels<'_> {
+        WorldVoxels {
+            front: PlacedVoxels::new(self.walk_scene(0, false)),
+            back: PlacedVoxels::new(self.walk_scene(0, true)),
+            remaining: None,
+        }
+    }
+
+    fn instance(&self, placed: PlacedModel) -> Instance {
+        let [x, y, z] = placed.translation;
+        Instance {
+            model_id: placed.model_id,
+            rotation: placed.rotation,
+            translation: Position { x, y, z },
+            layer_id: placed.layer_id,
+            name: self
+                .scenes
+                .get(placed.transform_id as usize)
+                .and_then(SceneNode::name),
+        }
+    }
+}
+
+/// Iterator over [`Instance`]s, created by [`DotVoxData::instances`].
+///
+/// The scene graph is walked as instances are requested, from both ends,
+/// so that memory use depends on the shape of the scene graph only. The
+/// instances left are only counted, with another walk, once both ends are
+/// used or the length is asked for.
+#[derive(Clone, Debug)]
+pub struct Instances<'a> {
+    data: &'a DotVoxData,
+    front: SceneWalk<'a>,
+    back: SceneWalk<'a>,
+    /// Instances left, counted when [`next_back`](Self::next_back) is first
+    /// called. Until then, only `front` has moved.
+    remaining: Option<usize>,
+}
+
+impl<'a> Instances<'a> {
+    fn remaining(&self) -> usize {
+        self.remaining.unwrap_or_else(|| self.front.clone().count())
+    }
+}
+
+impl<'a> Iterator for Instances<'a> {
+    type Item = Instance;
+
+    fn next(&mut self) -> Option<Instance> {
+        match &mut self.remaining {
+            Some(0) => return None,
+            Some(remaining) => *remaining -= 1,
+            None => {}
+        }
+        self.front.next().map(|placed| self.data.instance(placed))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Instances<'a> {
+    fn next_back(&mut self) -> Option<Instance> {
+        let remaining = self.remaining();
+        if remaining == 0 {
+            self.remaining = Some(0);
+            return None;
+        }
+        self.remaining = Some(remaining - 1);
+        self.back.next().map(|placed| self.data.instance(placed))
+    }
+}
+
+impl<'a> ExactSizeIterator for Instances<'a> {}
+
+/// The voxels of the models placed by a [`SceneWalk`], one at a time.
+#[derive(Clone, Debug)]
+struct PlacedVoxels<'a> {
+    walk: SceneWalk<'a>,
+    /// The model being placed, and the number of its voxels produced so far.
+    current: Option<(PlacedModel, &'a Model, usize)>,
+}
+
+impl<'a> PlacedVoxels<'a> {
+    fn new(walk: SceneWalk<'a>) -> Self {
+        PlacedVoxels {
+            walk,
+            current: None,
+        }
+    }
+
+    /// Number of voxels left to produce.
+    fn count(&self) -> usize {
+        let current = self
+            .current
+            .as_ref()
+            .map_or(0, |(_, model, produced)| model.voxels.len() - produced);
+        current
+            + self
+                .walk
+                .clone()
+                .filter_map(|placed| self.walk.data.models.get(placed.model_id as usize))
+                .map(|model| model.voxels.len())
+                .sum::<usize>()
+    }
+
+    /// The next voxel, taken from the back of each model when the walk is
+    /// reversed.
+    fn next(&mut self) -> Option<WorldVoxel> {
+        loop {
+            if let Some((placed, model, produced)) = &mut self.current {
+                if *produced < model.voxels.len() {
+                    let index = if self.walk.reversed {
+                        model.voxels.len() - 1 - *produced
+                    } else {
+                        *produced
+                    };
+                    *produced += 1;
+                    let voxel = &model.voxels[index];
+                    return Some(WorldVoxel {
+                        position: placed.transform().voxel_position(model.size, voxel),
+                        i: voxel.i,
+                    });
+                }
+            }
+            let placed = self.walk.next()?;
+            self.current = self
+                .walk
+                .data
+                .models
+                .get(placed.model_id as usize)
+                .map(|model| (placed, model, 0));
+        }
+    }
+}
+
+/// Iterator over [`WorldVoxel`]s, created by [`DotVoxData::world_voxels`].
+///
+/// Like [`Instances`], the scene graph is walked lazily from both ends, and
+/// the voxels left are only counted when needed.
+#[derive(Clone, Debug)]
+pub struct WorldVoxels<'a> {
+    front: PlacedVoxels<'a>,
+    back: PlacedVoxels<'a>,
+    /// Voxels left, counted when [`next_back`](Self::next_back) is first
+    /// called.
+    remaining: Option<usize>,
+}
+
+impl<'a> WorldVoxels<'a> {
+    fn remaining(&self) -> usize {
+        self.remaining.unwrap_or_else(|| self.front.count())
+    }
+}
+
+impl<'a> Iterator for WorldVoxels<'a> {
+    type Item = WorldVoxel;
+
+    fn next(&mut self) -> Option<WorldVoxel> {
+        match &mut self.remaining {
+            Some(0) => return None,
+            Some(remaining) => *remaining -= 1,
+            None => {}
+        }
+        self.front.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> DoubleEndedIterator for WorldVoxels<'a> {
+    fn next_back(&mut self) -> Option<WorldVoxel> {
+        let remaining = self.remaining();
+        if remaining == 0 {
+            self.remaining = Some(0);
+            return None;
+        }
+        self.remaining = Some(remaining - 1);
+        self.back.next()
+    }
+}
+
+impl<'a> ExactSizeIterator for WorldVoxels<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_voxels_match_instances() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        let instances: Vec<Instance> = data.instances().collect();
+        assert_eq!(instances.len(), 3);
+        assert_eq!(instances[1].model_id, 1);
+        assert_eq!(instances[1].translation, Position { x: 0, y: 25, z: 0 });
+
+        let voxels = data.world_voxels();
+        assert_eq!(voxels.len(), 6 + 32 + 6);
+        let forward: Vec<WorldVoxel> = voxels.clone().collect();
+        let mut backward: Vec<WorldVoxel> = voxels.rev().collect();
+        backward.reverse();
+        assert_eq!(forward, backward);
+        assert_eq!(forward[0].position, Position { x: 9, y: -1, z: 0 });
+        assert_eq!(forward[6].position, Position { x: 1, y: 23, z: -1 });
+    }
+
+    #[test]
+    fn world_voxels_meet_in_the_middle() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        let mut voxels = data.world_voxels();
+        let mut count = 0;
+        while voxels.next().is_some() {
+            count += 1;
+            if voxels.next_back().is_some() {
+                count += 1;
+            }
+        }
+        assert_eq!(count, 44);
+        assert_eq!(voxels.len(), 0);
+    }
+
+    #[test]
+    fn reversed_iteration_mirrors_forward_iteration() {
+        for path in ["src/resources/transforms.vox", "src/resources/axes.vox"] {
+            let mut data = crate::load(path).unwrap();
+            for _ in 0..2 {
+                let mut instances: Vec<Instance> = data.instances().collect();
+                instances.reverse();
+                assert_eq!(data.instances().rev().collect::<Vec<_>>(), instances);
+
+                let mut voxels: Vec<WorldVoxel> = data.world_voxels().collect();
+                voxels.reverse();
+                assert_eq!(data.world_voxels().rev().collect::<Vec<_>>(), voxels);
+
+                // Files without a scene graph.
+                data.scenes.clear();
+            }
+        }
+    }
+
+    #[test]
+    fn models_placed_out_of_range_are_skipped() {
+        use crate::{Dict, Frame, ShapeModel};
+
+        let far = |x: &str| {
+            vec![Frame::new(
+                [("_t".to_owned(), format!("{} 0 0", x))]
+                    .into_iter()
+                    .collect(),
+            )]
+        };
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        // Nested translations adding up past `i32::MAX`, and a sibling
+        // bringing its model back to the origin.
+        for node in [0, 2] {
+            if let SceneNode::Transform { frames, .. } = &mut data.scenes[node] {
+                *frames = far("2000000000");
+            }
+        }
+        if let SceneNode::Group { children, .. } = &mut data.scenes[1] {
+            children.push(4);
+        }
+        data.scenes.push(SceneNode::Transform {
+            attributes: Dict::new(),
+            frames: far("-2000000000"),
+            child: 5,
+            layer_id: 0,
+        });
+        data.scenes.push(SceneNode::Shape {
+            attributes: Dict::new(),
+            models: vec![ShapeModel {
+                model_id: 0,
+                attributes: Dict::new(),
+            }],
+        });
+        let data = crate::load_bytes(&data.to_bytes().unwrap()).unwrap();
+
+        let instances: Vec<Instance> = data.instances().collect();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].translation, Position { x: 0, y: 0, z: 0 });
+        assert_eq!(data.instances().rev().count(), 1);
+        assert_eq!(data.world_voxels().count(), data.models[0].voxels.len());
+        assert_eq!(data.gpu_instances().len(), 1);
+        assert!(data.flatten(crate::Overlap::Last).is_some());
+        assert!(data.bounding_box().is_some());
+        let mut visited = 0;
+        data.visit_scene(|_, _, _, _| visited += 1);
+        assert_eq!(visited, 1);
+
+        // Near the edge of the range, models are skipped only if some of
+        // their voxels would not fit.
+        let edge = Transform {
+            rotation: Rotation::IDENTITY,
+            translation: Position {
+                x: i32::MAX - 2,
+                y: i32::MIN + 2,
+                z: 0,
+            },
+        };
+        assert!(edge.fits(crate::Size { x: 2, y: 2, z: 2 }));
+        assert!(!edge.fits(crate::Size { x: 4, y: 1, z: 1 }));
+        assert_eq!(edge.checked_mul(edge), None);
+        assert_eq!((edge * edge).translation, Position { x: -6, y: 4, z: 0 });
+    }
+
+    #[test]
+    fn transforms_compose_with_their_inverse() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        for instance in data.instances() {
+            let transform = instance.transform();
+            let point = Position { x: 7, y: -3, z: 12 };
+            let back = transform
+                .inverse()
+                .transform_point(transform.transform_point(point));
+            assert_eq!(back, point);
+            assert_eq!(transform * transform.inverse(), Transform::IDENTITY);
+            assert_eq!(Transform::IDENTITY * transform, transform);
+        }
+    }
+
+    #[test]
+    fn world_voxels_are_reproducible() {
+        // FNV-1a over every world voxel, so that any change to placement,
+        // e.g. from floating point creeping in, is caught.
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        for voxel in data.world_voxels() {
+            let Position { x, y, z } = voxel.position;
+            let bytes = [x, y, z].map(i32::to_le_bytes);
+            for byte in bytes.iter().flatten().chain([&voxel.i]) {
+                hash = (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        assert_eq!(hash, 0x2c23_5727_6ac5_e3db);
+    }
+
+    #[test]
+    fn instances_report_layer_and_name() {
+        let mut scene = crate::SceneBuilder::new();
+        let group = scene.add_group(crate::SceneBuilder::ROOT).unwrap();
+        let named = scene.add_shape(group, 0).unwrap();
+        scene.add_shape(group, 0).unwrap();
+        scene.set_name(group, "group");
+        scene.set_name(named, "named");
+        scene.set_layer(named, 1);
+
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.scenes = scene.build();
+        let instances: Vec<Instance> = data.instances().collect();
+        assert_eq!(instances.len(), 2);
+        assert!(instances.iter().all(|instance| instance.model_id == 0));
+        assert_eq!(instances[0].name.as_deref(), Some("named"));
+        assert_eq!(instances[0].layer_id, 1);
+        assert_eq!(instances[1].name, None);
+        assert_eq!(instances[1].layer_id, u32::MAX);
+        assert_eq!(instances[0].layer(), Some(1));
+        assert_eq!(instances[1].layer(), None);
+    }
+
+    #[test]
+    fn scene_at_frame_selects_keyframes() {
+        use crate::Frame;
+
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.models.push(data.models[0].clone());
+        if let SceneNode::Transform { frames, .. } = &mut data.scenes[2] {
+            *frames = (0..3)
+                .map(|step| {
+                    Frame::default()
+                        .with_position(Position {
+                            x: step * 10,
+                            y: 0,
+                            z: 0,
+                        })
+                        .with_frame_index(step as u32 * 5)
+                })
+                .collect();
+        }
+        if let SceneNode::Shape { models, .. } = &mut data.scenes[3] {
+            models.push(crate::ShapeModel {
+                model_id: 1,
+                attributes: [("_f".to_owned(), "7".to_owned())].into_iter().collect(),
+            });
+        }
+
+        let at = |frame| {
+            let instance = data.scene_at_frame(frame).next().unwrap();
+            (instance.translation.x, instance.model_id)
+        };
+        assert_eq!(at(0), (0, 0));
+        assert_eq!(at(4), (0, 0));
+        assert_eq!(at(5), (10, 0));
+        assert_eq!(at(7), (10, 1));
+        assert_eq!(at(100), (20, 1));
+        assert_eq!(
+            data.instances().collect::<Vec<_>>(),
+            data.scene_at_frame(0).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{
+    camera, model, palette,
+    parser::{parse_dict, parse_legacy_material, parse_material},
+    scene, ParseOptions,
+};
+use std::fmt;
+
+/// A discrepancy between the sizes declared in a `.vox` file and its actual
+/// content, found by [`check_integrity`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    /// The file does not start with the `VOX ` magic number and a version.
+    InvalidHeader,
+    /// The file does not contain a `MAIN` chunk after the header.
+    MissingMain,
+    /// A chunk header, or the content and children it declares, extends past
+    /// the end of the file or of its parent chunk.
+    Truncated {
+        /// Id of the chunk.
+        id: String,
+        /// Offset of the chunk header in the file.
+        offset: usize,
+        /// Number of bytes declared by the chunk, including its header.
+        declared: usize,
+        /// Number of bytes actually available.
+        available: usize,
+    },
+    /// Parsing the content of a chunk consumed a different number of bytes
+    /// than the content size declared in the header.
+    ContentSizeMismatch {
+        /// Id of the chunk.
+        id: String,
+        /// Offset of the chunk header in the file.
+        offset: usize,
+        /// Content size declared in the chunk header.
+        declared: usize,
+        /// Number of bytes consumed when parsing the content.
+        consumed: usize,
+    },
+    /// Bytes follow the last complete chunk, either at the end of the file or
+    /// within the children of a chunk.
+    TrailingData {
+        /// Offset of the first trailing byte in the file.
+        offset: usize,
+        /// Number of trailing bytes.
+        len: usize,
+    },
+    /// A chunk is nested deeper than
+    /// [`ParseOptions::max_depth`](crate::ParseOptions::max_depth) allows. Its
+    /// children are not checked.
+    TooDeep {
+        /// Id of the chunk.
+        id: String,
+        /// Offset of the chunk header in the file.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::InvalidHeader => write!(f, "invalid header"),
+            IntegrityIssue::MissingMain => write!(f, "missing MAIN chunk"),
+            IntegrityIssue::Truncated {
+                id,
+                offset,
+                declared,
+                available,
+            } => write!(
+                f,
+                "{} chunk at offset {} declares {} bytes, but only {} are available",
+                id, offset, declared, available
+            ),
+            IntegrityIssue::ContentSizeMismatch {
+                id,
+                offset,
+                declared,
+                consumed,
+            } => write!(
+                f,
+                "{} chunk at offset {} declares {} content bytes, but {} were parsed",
+                id, offset, declared, consumed
+            ),
+            IntegrityIssue::TrailingData { offset, len } => {
+                write!(f, "{} trailing bytes at offset {}", len, offset)
+            }
+            IntegrityIssue::TooDeep { id, offset } => {
+                write!(f, "{} chunk at offset {} is nested too deeply", id, offset)
+            }
+        }
+    }
+}
+
+const HEADER_SIZE: usize = 8;
+const CHUNK_HEADER_SIZE: usize = 12;
+
+/// Checks that the sizes declared by every chunk of a `.vox` file match its
+/// content, returning every discrepancy found.
+///
+/// The parser silently tolerates many of these discrepancies, which some
+/// third-party exporters produce. This reports them, for example to reject
+/// such files in an asset pipeline. See also
+/// [`ParseOptions::integrity_check`].
+///
+/// Chunks are checked up to the default
+/// [`max_depth`](ParseOptions::max_depth) of the parser.
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let mut bytes = include_bytes!("resources/placeholder.vox").to_vec();
+/// assert!(check_integrity(&bytes).is_empty());
+///
+/// bytes.extend_from_slice(&[0; 3]);
+/// assert_eq!(
+///     check_integrity(&bytes),
+///     vec![IntegrityIssue::TrailingData {
+///         offset: bytes.len() - 3,
+///         len: 3
+///     }]
+/// );
+/// ```
+pub fn check_integrity(bytes: &[u8]) -> Vec<IntegrityIssue> {
+    check_integrity_with(bytes, &ParseOptions::default())
+}
+
+/// Like [`check_integrity`], with the nesting depth and string decoding of
+/// `options`.
+pub(crate) fn check_integrity_with(bytes: &[u8], options: &ParseOptions) -> Vec<IntegrityIssue> {
+    let mut issues = Vec::new();
+    if bytes.len() < HEADER_SIZE || &bytes[..4] != b"VOX " {
+        issues.push(IntegrityIssue::InvalidHeader);
+        return issues;
+    }
+    if bytes.get(HEADER_SIZE..HEADER_SIZE + 4) != Some(b"MAIN") {
+        issues.push(IntegrityIssue::MissingMain);
+        return issues;
+    }
+
+    check_chunks(bytes, HEADER_SIZE, bytes.len(), 0, options, &mut issues);
+    issues
+}
+
+/// Checks the sequence of chunks between `start` and `end`. `depth` counts
+/// the parents of these chunks.
+fn check_chunks(
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+    depth: usize,
+    options: &ParseOptions,
+    issues: &mut Vec<IntegrityIssue>,
+) {
+    let mut offset = start;
+    while offset < end {
+        let available = end - offset;
+        if available < CHUNK_HEADER_SIZE {
+            issues.push(IntegrityIssue::TrailingData {
+                offset,
+                len: available,
+            });
+            return;
+        }
+
+        let id = String::from_utf8_lossy(&bytes[offset..offset + 4]).into_owned();
+        let read_u32 = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]) as usize
+        };
+        let content_size = read_u32(offset + 4);
+        let children_size = read_u32(offset + 8);
+        let declared = CHUNK_HEADER_SIZE
+            .saturating_add(content_size)
+            .saturating_add(children_size);
+        if declared > available {
+            issues.push(IntegrityIssue::Truncated {
+                id,
+                offset,
+                declared,
+                available,
+            });
+            return;
+        }
+
+        let content_start = offset + CHUNK_HEADER_SIZE;
+        let content = &bytes[content_start..content_start + content_size];
+        let remaining = match id.as_str() {
+            "SIZE" => model::parse_size(content).map(|(i, _)| i.len()).ok(),
+            "XYZI" => model::parse_voxels(content).map(|(i, _)| i.len()).ok(),
+            // MagicaVoxel always writes 256 colors and 256 indices.
+            "RGBA" => content.len().checked_sub(256 * 4),
+            "IMAP" => content.len().checked_sub(256),
+            "XPAL" => palette::parse_palette_extension(content)
+                .map(|(i, _)| i.len())
+                .ok(),
+            "MATL" => parse_material(content, options).map(|(i, _)| i.len()).ok(),
+            "MATT" => parse_legacy_material(content).map(|(i, _)| i.len()).ok(),
+            "nTRN" => scene::parse_scene_transform(content, options)
+                .map(|(i, _)| i.len())
+                .ok(),
+            "nGRP" => scene::parse_scene_group(content, options)
+                .map(|(i, _)| i.len())
+                .ok(),
+            "nSHP" => scene::parse_scene_shape(content, options)
+                .map(|(i, _)| i.len())
+                .ok(),
+            "LAYR" => scene::parse_layer(content, options)
+                .map(|(i, _)| i.len())
+                .ok(),
+            "rOBJ" => parse_dict(content, options).map(|(i, _)| i.len()).ok(),
+            "rCAM" => camera::parse_camera(content, options)
+                .map(|(i, _)| i.len())
+                .ok(),
+            _ => Some(0),
+        };
+        match remaining {
+            Some(0) => {}
+            Some(remaining) => issues.push(IntegrityIssue::ContentSizeMismatch {
+                id: id.clone(),
+                offset,
+                declared: content_size,
+                consumed: content_size - remaining,
+            }),
+            // Content too short to be parsed at all.
+            None => issues.push(IntegrityIssue::ContentSizeMismatch {
+                id: id.clone(),
+                offset,
+                declared: content_size,
+                consumed: 0,
+            }),
+        }
+
+        // `MAIN` is at depth 1 for the parser.
+        if children_size > 0 && depth + 2 > options.depth_limit() {
+            issues.push(IntegrityIssue::TooDeep { id, offset });
+        } else if children_size > 0 {
+            let children_start = content_start + content_size;
+            check_chunks(
+                bytes,
+                children_start,
+                children_start + children_size,
+                depth + 1,
+                options,
+                issues,
+            );
+        }
+
+        offset += declared;
+        // The MAIN chunk must cover the rest of the file.
+        if depth == 0 && offset < end {
+            issues.push(IntegrityIssue::TrailingData {
+                offset,
+                len: end - offset,
+            });
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resources_pass_integrity_check() {
+        for bytes in [
+            &include_bytes!("resources/placeholder.vox")[..],
+            &include_bytes!("resources/placeholder-with-materials.vox")[..],
+            &include_bytes!("resources/axes.vox")[..],
+            &include_bytes!("resources/transforms.vox")[..],
+        ] {
+            assert_eq!(check_integrity(bytes), vec![]);
+        }
+    }
+
+    #[test]
+    fn detects_truncated_main() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        let truncated = &bytes[..bytes.len() - 10];
+        match &check_integrity(truncated)[..] {
+            [IntegrityIssue::Truncated {
+                id,
+                offset,
+                declared,
+                available,
+            }] => {
+                assert_eq!(id, "MAIN");
+                assert_eq!(*offset, 8);
+                assert_eq!(declared - available, 10);
+            }
+            issues => panic!("Unexpected issues {:?}", issues),
+        }
+    }
+
+    #[test]
+    fn detects_oversized_content() {
+        let mut bytes = include_bytes!("resources/placeholder.vox").to_vec();
+        // Grow the SIZE chunk, the first child of MAIN, by 4 bytes of padding.
+        let size_chunk = HEADER_SIZE + CHUNK_HEADER_SIZE;
+        bytes[size_chunk + 4] += 4;
+        bytes.splice(size_chunk + 24..size_chunk + 24, [0; 4]);
+        bytes[HEADER_SIZE + 8] += 4;
+
+        assert_eq!(
+            check_integrity(&bytes),
+            vec![IntegrityIssue::ContentSizeMismatch {
+                id: "SIZE".to_owned(),
+                offset: size_chunk,
+                declared: 16,
+                consumed: 12,
+            }]
+        );
+    }
+
+    #[test]
+    fn detects_short_palette() {
+        let mut bytes = include_bytes!("resources/placeholder.vox").to_vec();
+        let rgba = bytes.windows(4).position(|id| id == b"RGBA").unwrap();
+        bytes[rgba + 4..rgba + 8].copy_from_slice(&1020u32.to_le_bytes());
+        bytes.drain(rgba + 12..rgba + 16);
+        let main_children = u32::from_le_bytes(bytes[16..20].try_into().unwrap());
+        bytes[16..20].copy_from_slice(&(main_children - 4).to_le_bytes());
+
+        assert_eq!(
+            check_integrity(&bytes),
+            vec![IntegrityIssue::ContentSizeMismatch {
+                id: "RGBA".to_owned(),
+                offset: rgba,
+                declared: 1020,
+                consumed: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn nesting_is_bounded() {
+        // 1000 chunks nested in each other, within MAIN.
+        let mut chunk = Vec::new();
+        for _ in 0..1000 {
+            let mut parent = b"NEST".to_vec();
+            parent.extend_from_slice(&0u32.to_le_bytes());
+            parent.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            parent.extend_from_slice(&chunk);
+            chunk = parent;
+        }
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&150u32.to_le_bytes());
+        bytes.extend_from_slice(b"MAIN");
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&chunk);
+
+        match &check_integrity(&bytes)[..] {
+            [IntegrityIssue::TooDeep { id, .. }] => assert_eq!(id, "NEST"),
+            issues => panic!("Unexpected issues {:?}", issues),
+        }
+        let options = ParseOptions::default().max_depth(2);
+        match &check_integrity_with(&bytes, &options)[..] {
+            [IntegrityIssue::TooDeep { offset, .. }] => assert_eq!(*offset, HEADER_SIZE + 12),
+            issues => panic!("Unexpected issues {:?}", issues),
+        }
+    }
+
+    #[test]
+    fn detects_invalid_header() {
+        assert_eq!(
+            check_integrity(include_bytes!("resources/not_a.vox")),
+            vec![IntegrityIssue::InvalidHeader]
+        );
+    }
+
+    #[test]
+    fn integrity_check_option_rejects_inconsistent_files() {
+        let mut bytes = include_bytes!("resources/placeholder.vox").to_vec();
+        bytes.extend_from_slice(&[0; 3]);
+        let strict = ParseOptions::default().integrity_check(true);
+        assert!(crate::load_bytes(&bytes).is_ok());
+        match crate::load_bytes_with(&bytes, strict) {
+            Err(crate::Error::Integrity(issues)) => {
+                assert_eq!(issues, check_integrity(&bytes));
+            }
+            result => panic!("Expected an integrity error, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{Color, DotVoxData, Layer, SceneNode};
+use std::{error, fmt};
+
+/// Editing handle over the layers of a [`DotVoxData`], created by
+/// [`DotVoxData::layers_mut`].
+///
+/// Layer ids are the positions of the layers in [`DotVoxData::layers`], and
+/// are kept in sync with the `layer_id` of every transform node.
+#[derive(Debug)]
+pub struct Layers<'a> {
+    data: &'a mut DotVoxData,
+}
+
+/// Error returned by [`Layers::reorder`] for an order that does not contain
+/// every layer id exactly once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LayerOrderError {
+    /// The order does not contain the layer.
+    MissingLayer {
+        /// The missing layer id.
+        layer_id: u32,
+    },
+    /// The order contains the layer more than once.
+    DuplicateLayer {
+        /// The repeated layer id.
+        layer_id: u32,
+    },
+    /// The order contains an id past the end of [`DotVoxData::layers`].
+    UnknownLayer {
+        /// The unknown layer id.
+        layer_id: u32,
+    },
+}
+
+impl fmt::Display for LayerOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayerOrderError::MissingLayer { layer_id } => {
+                write!(f, "layer order does not contain layer {}", layer_id)
+            }
+            LayerOrderError::DuplicateLayer { layer_id } => {
+                write!(f, "layer order contains layer {} more than once", layer_id)
+            }
+            LayerOrderError::UnknownLayer { layer_id } => {
+                write!(f, "layer order contains unknown layer {}", layer_id)
+            }
+        }
+    }
+}
+
+impl error::Error for LayerOrderError {}
+
+impl DotVoxData {
+    /// Returns a handle for adding, editing and reordering layers.
+    pub fn layers_mut(&mut self) -> Layers<'_> {
+        Layers { data: self }
+    }
+}
+
+impl<'a> Layers<'a> {
+    /// Appends a new visible layer, returning its id.
+    pub fn add(&mut self, name: &str, color: Option<Color>) -> u32 {
+        let mut layer = Layer::new(name);
+        if let Some(color) = color {
+            layer.set_color(color);
+        }
+        self.data.layers.push(layer);
+        (self.data.layers.len() - 1) as u32
+    }
+
+    /// Renames the layer `id`. Returns `false` if there is no such layer.
+    pub fn rename(&mut self, id: u32, name: &str) -> bool {
+        self.edit(id, |layer| layer.set_name(name))
+    }
+
+    /// Hides or shows the layer `id`. Returns `false` if there is no such
+    /// layer.
+    pub fn set_hidden(&mut self, id: u32, hidden: bool) -> bool {
+        self.edit(id, |layer| layer.set_hidden(hidden))
+    }
+
+    /// Reorders the layers so that the layer at position `n` is the one that
+    /// had id `order[n]`, updating the `layer_id` of every transform node.
+    ///
+    /// `order` must contain every current layer id exactly once, otherwise
+    /// the layers are left as they are and the first offending id is
+    /// reported.
+    pub fn reorder(&mut self, order: &[u32]) -> Result<(), LayerOrderError> {
+        let mut remap = vec![None; self.data.layers.len()];
+        for (new_id, &old_id) in order.iter().enumerate() {
+            match remap.get_mut(old_id as usize) {
+                Some(slot @ None) => *slot = Some(new_id as u32),
+                Some(Some(_)) => return Err(LayerOrderError::DuplicateLayer { layer_id: old_id }),
+                None => return Err(LayerOrderError::UnknownLayer { layer_id: old_id }),
+            }
+        }
+        if let Some(layer_id) = remap.iter().position(Option::is_none) {
+            return Err(LayerOrderError::MissingLayer {
+                layer_id: layer_id as u32,
+            });
+        }
+
+        let mut layers: Vec<Option<Layer>> = self.data.layers.drain(..).map(Some).collect();
+        self.data.layers = order
+            .iter()
+            .filter_map(|&old_id| layers[old_id as usize].take())
+            .collect();
+        for node in self.data.scenes.iter_mut() {
+            if let SceneNode::Transform { layer_id, .. } = node {
+                if let Some(Some(new_id)) = remap.get(*layer_id as usize) {
+                    *layer_id = *new_id;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn edit(&mut self, id: u32, f: impl FnOnce(&mut Layer)) -> bool {
+        match self.data.layers.get_mut(id as usize) {
+            Some(layer) => {
+                f(layer);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer_ids(data: &DotVoxData) -> Vec<u32> {
+        data.scenes
+            .iter()
+            .filter_map(|node| match node {
+                SceneNode::Transform { layer_id, .. } => Some(*layer_id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn add_rename_and_hide_layers() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        let count = data.layers.len() as u32;
+        let mut layers = data.layers_mut();
+        let color = Color {
+            r: 1,
+            g: 2,
+            b: 3,
+            a: 0,
+        };
+        let id = layers.add("new", Some(color));
+        assert_eq!(id, count);
+        assert!(layers.rename(id, "renamed"));
+        assert!(layers.set_hidden(id, true));
+        assert!(!layers.set_hidden(id + 1, true));
+
+        let layer = &data.layers[id as usize];
+        assert_eq!(layer.name(), Some("renamed".to_owned()));
+        assert_eq!(layer.color(), Some(color));
+        assert!(layer.hidden());
+
+        data.layers_mut().set_hidden(id, false);
+        assert!(!data.layers[id as usize].hidden());
+    }
+
+    #[test]
+    fn reorder_updates_transform_nodes() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        let names: Vec<_> = data.layers.iter().map(Layer::name).collect();
+        let before = layer_ids(&data);
+        let order: Vec<u32> = (0..data.layers.len() as u32).rev().collect();
+        data.layers_mut().reorder(&order).unwrap();
+
+        let reversed: Vec<_> = names.into_iter().rev().collect();
+        let after: Vec<_> = data.layers.iter().map(Layer::name).collect();
+        assert_eq!(after, reversed);
+        let last = data.layers.len() as u32 - 1;
+        let expected: Vec<u32> = before
+            .iter()
+            .map(|&id| if id <= last { last - id } else { id })
+            .collect();
+        assert_eq!(layer_ids(&data), expected);
+    }
+
+    #[test]
+    fn reorder_rejects_invalid_orders() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        let len = data.layers.len() as u32;
+        let mut layers = data.layers_mut();
+        assert_eq!(
+            layers.reorder(&[0]),
+            Err(LayerOrderError::MissingLayer { layer_id: 1 })
+        );
+        let mut order: Vec<u32> = (0..len).collect();
+        order[1] = 0;
+        assert_eq!(
+            layers.reorder(&order),
+            Err(LayerOrderError::DuplicateLayer { layer_id: 0 })
+        );
+        order[1] = len;
+        assert_eq!(
+            layers.reorder(&order),
+            Err(LayerOrderError::UnknownLayer { layer_id: len })
+        );
+    }
+
+    #[test]
+    fn layer_attributes_are_written() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        let mut layer = Layer::new("new");
+        layer.set_color(Color {
+            r: 10,
+            g: 20,
+            b: 30,
+            a: 0,
+        });
+        layer.set_hidden(true);
+        data.layers.push(layer.clone());
+
+        let reloaded = crate::load_bytes(&data.to_bytes().unwrap()).unwrap();
+        assert_eq!(reloaded.layers.last(), Some(&layer));
+        assert!(reloaded.layers.last().unwrap().hidden());
+    }
+}
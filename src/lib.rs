@@ -1,37 +1,142 @@
 //! Load [MagicaVoxel](https://ephtracy.github.io/) `.vox` files from Rust.
+//!
+//! # API stability
+//!
+//! Items at the crate root, along with [`gpu`], [`render`] and
+//! [`placeholder`], follow semantic versioning: [`DotVoxData`] and the types it
+//! is built from, the loaders, and scene evaluation such as
+//! [`DotVoxData::instances`].
+//!
+//! The [`raw`] module exposes the chunk level representation and the `nom`
+//! parsers behind it. It tracks the file format and the parser implementation
+//! closely, and may change in minor releases.
 
-use parser::parse_vox_file;
-use std::{fs::File, io::Read};
+use std::{fs::File, io::Read, path::Path};
 
 #[macro_use]
 extern crate lazy_static;
+#[cfg(not(feature = "tracing"))]
 #[macro_use]
 extern crate log;
+#[cfg(feature = "tracing")]
+#[macro_use]
+extern crate tracing;
+
+#[macro_use]
+mod trace;
 
 #[cfg(test)]
 extern crate avow;
 
+#[cfg(feature = "async")]
+mod asynchronous;
+mod batch;
+mod borrowed;
+mod builder;
+mod camera;
+mod convention;
+mod coord;
+mod diff;
 mod dot_vox_data;
+mod error;
+mod flatten;
+mod gc;
+#[cfg(feature = "glam")]
+mod glam_interop;
+pub mod gpu;
+#[cfg(feature = "flate2")]
+mod gzip;
+mod index;
+mod instance;
+mod integrity;
+mod layers;
 mod model;
 mod palette;
 mod parser;
+mod prefab;
+pub mod raw;
+pub mod render;
+mod render_settings;
 mod scene;
+mod split;
+mod surface;
 mod types;
+mod validate;
+mod visitor;
+#[cfg(feature = "watch")]
+mod watch;
+
+#[cfg(feature = "async")]
+pub use asynchronous::{load_async, load_reader_async, load_reader_async_with};
+
+pub use batch::{load_dir, LoadDirEntry, LoadDirOptions};
+
+pub use borrowed::{
+    DictRef, DotVoxDataRef, MaterialRef, ModelRef, RawChunkRef, SceneNodeRef, ShapeModelRef,
+};
+
+pub use builder::{BuildError, DotVoxBuilder, SceneBuilder};
+
+pub use types::{Axis, EulerOrder, InvalidRotation, Rotation, Transform};
+
+pub use validate::ValidationError;
+
+pub use visitor::{parse_chunks, ChunkVisitor, RawVoxels};
+
+#[cfg(feature = "watch")]
+pub use watch::{watch_file, watch_file_with, FileWatcher, Reload, WatchOptions};
+
+pub use camera::Camera;
+
+pub use convention::AxisConvention;
+
+pub use coord::{VoxelCoord, WorldCoord};
+
+pub use diff::{DotVoxDiff, ModelChange};
+
+pub use dot_vox_data::{ChunkKind, ChunkSizeOverflow, DotVoxData, WriteOptions};
 
-pub use types::Rotation;
+pub use error::{Error, Limit, Warning};
 
-pub use dot_vox_data::DotVoxData;
+pub use flatten::Overlap;
 
-pub use parser::{Dict, Material};
+pub use gc::{GcOptions, GcReport};
+
+pub use index::{ModelEntry, VoxIndex};
+
+pub use integrity::{check_integrity, IntegrityIssue};
+
+pub use instance::{Instance, Instances, WorldVoxel, WorldVoxels};
+
+pub use layers::{LayerOrderError, Layers};
+
+pub use parser::{
+    Dict, DuplicateKeys, Material, MaterialBuilder, ParseMode, ParseOptions, RawChunk, Sections,
+    SUPPORTED_VERSION,
+};
+
+pub use prefab::Prefab;
+
+pub use render_settings::RenderSettings;
 
 pub use model::Model;
 pub use model::Size;
 pub use model::Voxel;
+pub use model::{Slice, Slices, UpscaleMode};
+
+pub use scene::{scatter, Frame, Layer, NodeBounds, Position, SceneNode, ShapeModel};
 
-pub use scene::*;
+// Previous location of the items now in `raw`, kept until the next major
+// release.
+#[doc(hidden)]
+pub use scene::{
+    parse_layer, parse_scene_group, parse_scene_shape, parse_scene_transform, NodeHeader, RawLayer,
+    SceneGroup, SceneShape, SceneTransform,
+};
 
 pub use palette::Color;
 pub use palette::DEFAULT_PALETTE;
+pub use palette::{Palette, PaletteChange, PaletteExtension};
 
 /// Loads the supplied [MagicaVoxel](https://ephtracy.github.io/) `.vox` file
 ///
@@ -41,6 +146,14 @@ pub use palette::DEFAULT_PALETTE;
 /// information (RGBA), and a `Vec<`[`Material`]`>` containing all the
 /// specialized materials.
 ///
+/// With the `mmap` feature, the file is memory-mapped instead of read into a
+/// buffer, which is faster for large files. The file must then not be modified
+/// or truncated by another process while it is loaded, as that is undefined
+/// behavior.
+///
+/// With the `flate2` feature, gzip-compressed files are decompressed first,
+/// whatever their extension.
+///
 /// # Panics
 ///
 /// No panics should occur with this library -- if you find one, please raise a
@@ -48,8 +161,7 @@ pub use palette::DEFAULT_PALETTE;
 ///
 /// # Errors
 ///
-/// All errors are strings, and should describe the issue that caused them to
-/// occur.
+/// Returns an [`Error`] describing why the file could not be read or parsed.
 ///
 /// # Examples
 ///
@@ -58,9 +170,12 @@ pub use palette::DEFAULT_PALETTE;
 /// ```
 /// use dot_vox::*;
 ///
-/// let result = load("src/resources/placeholder.vox");
+/// let result = load("src/resources/placeholder.vox").unwrap();
+/// // Chunks this crate does not handle are kept as is.
+/// let unknown: Vec<&str> = result.unknown_chunks.iter().map(|c| c.id.as_str()).collect();
+/// assert_eq!(unknown, ["rLIT", "rLIT", "rAIR", "rLEN", "POST", "rDIS"]);
 /// assert_eq!(
-///     result.unwrap(),
+///     result,
 ///     DotVoxData {
 ///         version: 150,
 ///         models: vec!(Model {
@@ -94,7 +209,6 @@ pub use palette::DEFAULT_PALETTE;
 ///         }),
 ///         palette: DEFAULT_PALETTE.to_vec(),
 ///         materials: (0..256)
-///             .into_iter()
 ///             .map(|i| Material {
 ///                 id: i,
 ///                 properties: {
@@ -109,19 +223,89 @@ pub use palette::DEFAULT_PALETTE;
 ///             })
 ///             .collect(),
 ///         scenes: placeholder::SCENES.to_vec(),
+///         synthetic_scene: false,
 ///         layers: placeholder::LAYERS.to_vec(),
+///         palette_extension: None,
+///         index_map: None,
+///         render_objects: Vec::new(),
+///         cameras: Vec::new(),
+///         extension_chunks: Vec::new(),
+///         unknown_chunks: result.unknown_chunks.clone(),
+///         chunk_order: Vec::new(),
 ///     }
 /// );
 /// ```
-pub fn load(filename: &str) -> Result<DotVoxData, &'static str> {
-    match File::open(filename) {
-        Ok(mut f) => {
-            let mut buffer = Vec::new();
-            f.read_to_end(&mut buffer).expect("Unable to read file");
-            load_bytes(&buffer)
-        }
-        Err(_) => Err("Unable to load file"),
+pub fn load(filename: &str) -> Result<DotVoxData, Error> {
+    parse_file(Path::new(filename), load_bytes)
+}
+
+/// Reads the file at `path` as [`load`] does, decompressing it or mapping it
+/// into memory depending on the features enabled, and parses its content
+/// with `parse`.
+pub(crate) fn parse_file<T>(
+    path: &Path,
+    parse: impl FnOnce(&[u8]) -> Result<T, Error>,
+) -> Result<T, Error> {
+    #[cfg_attr(not(feature = "flate2"), allow(unused_mut))]
+    let mut file = File::open(path)?;
+    #[cfg(feature = "flate2")]
+    if gzip::is_compressed(&mut file)? {
+        return parse(&read_vox(gzip::decoder(file))?);
+    }
+    #[cfg(feature = "mmap")]
+    if file.metadata()?.len() > 0 {
+        // SAFETY: the map only lives for the duration of the parse, which
+        // copies every value out of it. The file must not be modified in the
+        // meantime, as documented on `load`.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        return parse(&map);
     }
+    parse(&read_vox(file)?)
+}
+
+/// Reads a .vox file from `reader`, e.g. an archive entry or a network
+/// stream.
+///
+/// The header is checked before anything else is read, so that other data
+/// is rejected early. The rest is read until the end of the stream, as
+/// parsing needs the whole file in memory.
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let reader = std::io::Cursor::new(include_bytes!("resources/placeholder.vox"));
+/// let result = load_reader(reader).unwrap();
+/// assert_eq!(result.models.len(), 1);
+/// ```
+///
+/// # Errors
+///
+/// Returns an [`Error`] if reading fails or the data could not be parsed.
+pub fn load_reader<R: Read>(reader: R) -> Result<DotVoxData, Error> {
+    load_reader_with(reader, ParseOptions::default())
+}
+
+/// Reads a .vox file from `reader`, using the given [`ParseOptions`]. See
+/// [`load_reader`].
+///
+/// # Errors
+///
+/// Returns an [`Error`] if reading fails, or as [`load_bytes_with`] if the
+/// data could not be parsed.
+pub fn load_reader_with<R: Read>(reader: R, options: ParseOptions) -> Result<DotVoxData, Error> {
+    load_bytes_with(&read_vox(reader)?, options)
+}
+
+/// Reads the whole of `reader`, checking the header before reading the rest.
+fn read_vox<R: Read>(mut reader: R) -> Result<Vec<u8>, Error> {
+    let mut buffer = Vec::new();
+    let magic = parser::MAGIC_NUMBER.len() as u64;
+    (&mut reader).take(magic).read_to_end(&mut buffer)?;
+    if buffer.len() as u64 == magic && !buffer.starts_with(parser::MAGIC_NUMBER.as_bytes()) {
+        return Err(Error::InvalidMagic);
+    }
+    reader.read_to_end(&mut buffer)?;
+    Ok(buffer)
 }
 
 /// Parses the byte array as a .vox file.
@@ -139,8 +323,7 @@ pub fn load(filename: &str) -> Result<DotVoxData, &'static str> {
 ///
 /// # Errors
 ///
-/// All errors are strings, and should describe the issue that caused them to
-/// occur.
+/// Returns an [`Error`] describing why the file could not be read or parsed.
 ///
 /// # Examples
 ///
@@ -149,9 +332,12 @@ pub fn load(filename: &str) -> Result<DotVoxData, &'static str> {
 /// ```
 /// use dot_vox::*;
 ///
-/// let result = load_bytes(include_bytes!("resources/placeholder.vox"));
+/// let result = load_bytes(include_bytes!("resources/placeholder.vox")).unwrap();
+/// // Chunks this crate does not handle are kept as is.
+/// let unknown: Vec<&str> = result.unknown_chunks.iter().map(|c| c.id.as_str()).collect();
+/// assert_eq!(unknown, ["rLIT", "rLIT", "rAIR", "rLEN", "POST", "rDIS"]);
 /// assert_eq!(
-///     result.unwrap(),
+///     result,
 ///     DotVoxData {
 ///         version: 150,
 ///         models: vec!(Model {
@@ -185,7 +371,6 @@ pub fn load(filename: &str) -> Result<DotVoxData, &'static str> {
 ///         }),
 ///         palette: DEFAULT_PALETTE.to_vec(),
 ///         materials: (0..256)
-///             .into_iter()
 ///             .map(|i| Material {
 ///                 id: i,
 ///                 properties: {
@@ -200,15 +385,84 @@ pub fn load(filename: &str) -> Result<DotVoxData, &'static str> {
 ///             })
 ///             .collect(),
 ///         scenes: placeholder::SCENES.to_vec(),
+///         synthetic_scene: false,
 ///         layers: placeholder::LAYERS.to_vec(),
+///         palette_extension: None,
+///         index_map: None,
+///         render_objects: Vec::new(),
+///         cameras: Vec::new(),
+///         extension_chunks: Vec::new(),
+///         unknown_chunks: result.unknown_chunks.clone(),
+///         chunk_order: Vec::new(),
 ///     }
 /// );
 /// ```
-pub fn load_bytes(bytes: &[u8]) -> Result<DotVoxData, &'static str> {
-    match parse_vox_file(bytes) {
-        Ok((_, parsed)) => Ok(parsed),
-        Err(_) => Err("Not a valid MagicaVoxel .vox file"),
+pub fn load_bytes(bytes: &[u8]) -> Result<DotVoxData, Error> {
+    parser::load_vox_file(bytes, &ParseOptions::default())
+}
+
+/// Parses the byte array as a .vox file, using the given [`ParseOptions`].
+///
+/// # Errors
+///
+/// Returns an [`Error`] describing why the data could not be parsed,
+/// [`Error::InvalidChunk`] if a chunk is invalid in [`ParseMode::Strict`], or
+/// [`Error::Integrity`] if the integrity check is enabled and fails.
+pub fn load_bytes_with(bytes: &[u8], options: ParseOptions) -> Result<DotVoxData, Error> {
+    load_bytes_with_warnings(bytes, &options).map(|(data, _)| data)
+}
+
+/// Parses the byte array like [`load_bytes_with`], also reporting the
+/// [`Warning`]s raised.
+pub(crate) fn load_bytes_with_warnings(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<(DotVoxData, Vec<Warning>), Error> {
+    if options.integrity_check {
+        let issues = integrity::check_integrity_with(bytes, options);
+        if !issues.is_empty() {
+            return Err(Error::Integrity(issues));
+        }
     }
+
+    parser::load_vox_file_with_warnings(bytes, options)
+}
+
+/// Parses the byte array as a .vox file, recovering from truncated or
+/// corrupt chunks instead of failing.
+///
+/// Everything parsed before the truncation point is returned, along with a
+/// [`Warning`] for each chunk that was cut off or skipped. This is meant for
+/// files that were only partially downloaded or written. Options apply as for
+/// [`load_bytes_with`], except that [`ParseMode::Strict`] does not fail on
+/// invalid chunks.
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let bytes = include_bytes!("resources/placeholder.vox");
+/// let (data, warnings) =
+///     load_bytes_partial(&bytes[..bytes.len() - 10], ParseOptions::default()).unwrap();
+/// assert_eq!(data.models.len(), 1);
+/// assert!(!warnings.is_empty());
+/// ```
+///
+/// # Errors
+///
+/// Returns an [`Error`] if the data does not start with a valid header, or
+/// [`Error::Integrity`] if the integrity check is enabled and fails.
+pub fn load_bytes_partial(
+    bytes: &[u8],
+    options: ParseOptions,
+) -> Result<(DotVoxData, Vec<Warning>), Error> {
+    if options.integrity_check {
+        let issues = integrity::check_integrity_with(bytes, &options);
+        if !issues.is_empty() {
+            return Err(Error::Integrity(issues));
+        }
+    }
+
+    parser::load_vox_file_partial(bytes, &options)
 }
 
 /// Data extracted from placeholder.vox for example and testing purposes
@@ -250,7 +504,6 @@ pub mod placeholder {
 
         /// Layers extracted from placeholder.vox
         pub static ref LAYERS: Vec<Layer> = (0..8)
-            .into_iter()
             .map(|layer| Layer {
                 attributes: {
                     let mut map = Dict::new();
@@ -270,7 +523,6 @@ mod tests {
 
     lazy_static! {
         static ref DEFAULT_MATERIALS: Vec<Material> = (0..256)
-            .into_iter()
             .map(|i| Material {
                 id: i,
                 properties: {
@@ -326,7 +578,15 @@ mod tests {
             palette,
             materials,
             scenes,
+            synthetic_scene: false,
             layers,
+            palette_extension: None,
+            index_map: None,
+            render_objects: Vec::new(),
+            cameras: Vec::new(),
+            extension_chunks: Vec::new(),
+            unknown_chunks: Vec::new(),
+            chunk_order: Vec::new(),
         }
     }
 
@@ -336,7 +596,7 @@ mod tests {
         actual
             .models
             .into_iter()
-            .zip(expected.models.into_iter())
+            .zip(expected.models)
             .for_each(|(actual, expected)| {
                 assert_eq!(actual.size, expected.size);
                 vec::are_eq(actual.voxels, expected.voxels);
@@ -365,21 +625,19 @@ mod tests {
     #[test]
     fn not_present_file_causes_error() {
         let result = load("src/resources/not_here.vox");
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Unable to load file");
+        assert!(matches!(result, Err(Error::Io(_))));
     }
 
     #[test]
     fn non_vox_file_causes_error() {
         let result = load("src/resources/not_a.vox");
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Not a valid MagicaVoxel .vox file");
+        assert!(matches!(result, Err(Error::InvalidMagic)));
     }
 
     #[test]
     fn can_parse_vox_file_with_palette() {
         let bytes = include_bytes!("resources/placeholder.vox").to_vec();
-        let result = super::parse_vox_file(&bytes);
+        let result = raw::parse_vox_file(&bytes);
         assert!(result.is_ok());
         let (_, models) = result.unwrap();
         compare_data(
@@ -396,7 +654,7 @@ mod tests {
     #[test]
     fn can_parse_vox_file_with_materials() {
         let bytes = include_bytes!("resources/placeholder-with-materials.vox").to_vec();
-        let result = super::parse_vox_file(&bytes);
+        let result = raw::parse_vox_file(&bytes);
         assert!(result.is_ok());
         let (_, voxel_data) = result.unwrap();
         let mut materials: Vec<Material> = DEFAULT_MATERIALS.to_vec();
@@ -428,9 +686,12 @@ mod tests {
         let mut buffer = Vec::new();
         let write_result = data.write_vox(&mut buffer);
         assert!(write_result.is_ok());
-        let load_result = load_bytes(&buffer);
+        // Keep files without a scene graph as they are, to compare them.
+        let load_result = load_bytes_with(&buffer, ParseOptions::default().synthesize_scene(false));
         assert!(load_result.is_ok());
-        compare_data(load_result.unwrap(), data);
+        let loaded = load_result.unwrap();
+        assert!(loaded.equivalent(&data));
+        compare_data(loaded, data);
     }
 
     #[test]
@@ -447,4 +708,189 @@ mod tests {
             placeholder::LAYERS.to_vec(),
         ));
     }
+
+    #[test]
+    fn material_ids_match_voxel_palette_indices() {
+        let result = load("src/resources/single-voxel-with-material.vox").unwrap();
+        let voxel = result.models[0].voxels[0];
+        let material = result
+            .materials
+            .iter()
+            .find(|material| material.id == voxel.i as u32 + 1)
+            .unwrap();
+        assert_eq!(material.material_type(), Some("_metal"));
+    }
+
+    #[test]
+    fn materials_are_written_sorted_by_id() {
+        let mut materials = DEFAULT_MATERIALS.to_vec();
+        materials.reverse();
+        let data = placeholder(
+            DEFAULT_PALETTE.to_vec(),
+            materials,
+            placeholder::SCENES.to_vec(),
+            placeholder::LAYERS.to_vec(),
+        );
+
+        let mut buffer = Vec::new();
+        data.write_vox(&mut buffer).unwrap();
+        let ids: Vec<u32> = load_bytes(&buffer)
+            .unwrap()
+            .materials
+            .iter()
+            .map(|material| material.id)
+            .collect();
+        assert_eq!(ids, (0..256).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn remap_to_palette_picks_nearest_colors() {
+        let mut data = placeholder(
+            DEFAULT_PALETTE.to_vec(),
+            DEFAULT_MATERIALS.to_vec(),
+            Vec::new(),
+            Vec::new(),
+        );
+        let target: Vec<Color> = DEFAULT_PALETTE.iter().rev().copied().collect();
+        data.remap_to_palette(&target);
+        assert_eq!(data.palette, target);
+        for (voxel, i) in data.models[0].voxels.iter().zip([225, 215, 235, 5]) {
+            assert_eq!(target[voxel.i as usize], DEFAULT_PALETTE[i]);
+        }
+    }
+
+    #[test]
+    fn frame_scale_and_float_translation_survive_round_trip() {
+        let mut frame = Frame::new({
+            let mut map = Dict::new();
+            map.insert("_t".to_owned(), "0.5 -2 1e3".to_owned());
+            map
+        });
+        frame.set_scale(2.5);
+        let scenes = vec![SceneNode::Transform {
+            attributes: Dict::new(),
+            frames: vec![frame],
+            child: 1,
+            layer_id: u32::MAX,
+        }];
+        let data = placeholder(Vec::new(), Vec::new(), scenes, Vec::new());
+
+        let mut buffer = Vec::new();
+        data.write_vox(&mut buffer).unwrap();
+        let (data, warnings) = load_bytes_partial(&buffer, ParseOptions::default()).unwrap();
+        assert_eq!(warnings, [Warning::NonStandardScale { node_id: 0 }]);
+        match &data.scenes[0] {
+            SceneNode::Transform { frames, .. } => {
+                assert_eq!(frames[0].scale(), Some(2.5));
+                assert_eq!(frames[0].position(), None);
+                assert_eq!(frames[0].position_f32(), Some([0.5, -2.0, 1000.0]));
+                assert_eq!(
+                    frames[0].transform().translation,
+                    Position {
+                        x: 1,
+                        y: -2,
+                        z: 1000
+                    }
+                );
+            }
+            node => panic!("Expected a transform node, got {:?}", node),
+        }
+    }
+
+    /// `axes.vox` was saved by MagicaVoxel. Each shape is placed by the sum
+    /// of the `_t` fields above it, minus the pivot of its model at
+    /// `size / 2`; the bounds below follow from the voxel extents of each
+    /// model, e.g. `0 - 20 + 18 = -2` for the lowest `y` of node 5.
+    #[test]
+    fn magicavoxel_fixture_places_models_as_expected() {
+        let data = load("src/resources/axes.vox").unwrap();
+        let expected = [
+            (5, 0, [-20, -2, 63], [19, 1, 102]),
+            (7, 0, [-20, -2, 111], [19, 1, 150]),
+            (9, 0, [-20, -2, 159], [19, 1, 198]),
+            (13, 1, [-20, 127, 0], [19, 130, 39]),
+            (15, 1, [-20, 187, 0], [19, 190, 39]),
+            (17, 1, [-20, 245, 0], [19, 248, 39]),
+            (21, 2, [106, -2, 1], [145, 1, 39]),
+            (23, 2, [154, -2, 1], [193, 1, 39]),
+            (25, 2, [200, -2, 1], [239, 1, 39]),
+            (27, 3, [-16, -16, 0], [15, 15, 24]),
+        ]
+        .map(|(node_id, model_id, min, max)| NodeBounds {
+            node_id,
+            model_id,
+            min,
+            max,
+        });
+        assert_eq!(data.node_bounds(), expected);
+    }
+
+    #[test]
+    fn partial_load_recovers_truncated_file() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        let truncated = &bytes[..bytes.len() - 10];
+        assert!(load_bytes(truncated).is_err());
+
+        let (data, warnings) = load_bytes_partial(truncated, ParseOptions::default()).unwrap();
+        assert_eq!(data.models, load_bytes(bytes).unwrap().models);
+        match &warnings[..] {
+            [Warning::Truncated {
+                id: main,
+                offset: 8,
+                declared: Some(declared),
+                available,
+            }, Warning::Truncated { id: last, .. }] => {
+                assert_eq!(main, "MAIN");
+                assert_eq!(declared - available, 10);
+                assert_ne!(last, "MAIN");
+            }
+            warnings => panic!("Unexpected warnings {:?}", warnings),
+        }
+    }
+
+    #[test]
+    fn partial_load_of_complete_file_has_no_warnings() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        let (data, warnings) = load_bytes_partial(bytes, ParseOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(data, load_bytes(bytes).unwrap());
+        assert!(matches!(
+            load_bytes_partial(&bytes[..6], ParseOptions::default()),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn load_reader_matches_load_bytes() {
+        let bytes = include_bytes!("resources/placeholder-with-materials.vox");
+        assert_eq!(load_reader(&bytes[..]).unwrap(), load_bytes(bytes).unwrap());
+        assert!(matches!(
+            load_reader(&bytes[..20]),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn load_reader_rejects_other_data_early() {
+        struct Reader(usize);
+        impl std::io::Read for Reader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let len = buf.len().min(4);
+                self.0 += len;
+                assert!(self.0 <= 4, "Read past the header");
+                buf[..len].fill(0);
+                Ok(len)
+            }
+        }
+        assert!(matches!(load_reader(Reader(0)), Err(Error::InvalidMagic)));
+    }
+
+    #[test]
+    fn empty_file_causes_error() {
+        let path = std::env::temp_dir().join("dot_vox_empty_file_causes_error.vox");
+        File::create(&path).unwrap();
+        let result = load(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(Error::InvalidMagic)));
+    }
 }
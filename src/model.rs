@@ -1,14 +1,15 @@
-use nom::{
-    multi::count,
-    number::complete::{le_u32, le_u8},
-    sequence::tuple,
-    IResult,
-};
+use nom::{bytes::complete::take, number::complete::le_u32, sequence::tuple, IResult};
 
-use crate::parser::validate_count;
+use crate::{parser::validate_count, surface::NEIGHBORS, Axis, Position, Transform};
+
+#[cfg(feature = "ahash")]
+use ahash::AHashMap as HashMap;
+
+#[cfg(not(feature = "ahash"))]
+use std::collections::HashMap;
 
 /// A renderable voxel model.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Model {
     /// The size of the model in voxels.
     pub size: Size,
@@ -26,6 +27,336 @@ impl Model {
         // - 4 bytes for the voxel length u32
         40 + 4 * self.voxels.len() as u32
     }
+
+    /// The voxel placed at the translation of a transform node, i.e. the
+    /// center of the model rounded down. MagicaVoxel positions models by this
+    /// pivot rather than by their `[0][0][0]` corner.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let model = Model {
+    ///     size: Size { x: 4, y: 3, z: 1 },
+    ///     voxels: Vec::new(),
+    /// };
+    /// assert_eq!(model.pivot(), [2, 1, 0]);
+    /// ```
+    pub fn pivot(&self) -> [i32; 3] {
+        [self.size.x, self.size.y, self.size.z].map(|s| (s / 2) as i32)
+    }
+
+    /// The smallest world coordinates of the voxels of this model when
+    /// placed by `transform`. Without a rotation, this is where the voxel at
+    /// `[0][0][0]` ends up: the translation minus the
+    /// [`pivot`](Model::pivot).
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let model = Model {
+    ///     size: Size { x: 4, y: 3, z: 1 },
+    ///     voxels: Vec::new(),
+    /// };
+    /// let transform = Transform {
+    ///     rotation: Rotation::IDENTITY,
+    ///     translation: Position { x: 10, y: 0, z: 0 },
+    /// };
+    /// assert_eq!(model.world_min_corner(&transform), Position { x: 8, y: -1, z: 0 });
+    /// ```
+    pub fn world_min_corner(&self, transform: &Transform) -> Position {
+        let corner = |x: u32, y: u32, z: u32| {
+            let voxel = Voxel {
+                x: x.saturating_sub(1).min(u8::MAX as u32) as u8,
+                y: y.saturating_sub(1).min(u8::MAX as u32) as u8,
+                z: z.saturating_sub(1).min(u8::MAX as u32) as u8,
+                i: 0,
+            };
+            transform.voxel_position(self.size, &voxel)
+        };
+        let (a, b) = (
+            corner(1, 1, 1),
+            corner(self.size.x, self.size.y, self.size.z),
+        );
+        Position {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+            z: a.z.min(b.z),
+        }
+    }
+
+    /// Iterates over the slices of this model along `axis`, from the lowest
+    /// to the highest coordinate along that axis. See [`Slice`] for how cells
+    /// are laid out.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = load("src/resources/placeholder.vox").unwrap();
+    /// let slices: Vec<Slice> = data.models[0].slices(Axis::Z).collect();
+    /// assert_eq!(slices.len(), 2);
+    /// assert_eq!(slices[0].get(0, 0), Some(225));
+    /// assert_eq!(slices[0].get(1, 0), None);
+    /// ```
+    pub fn slices(&self, axis: Axis) -> Slices<'_> {
+        let (depth, width, height) = match axis {
+            Axis::X => (self.size.x, self.size.y, self.size.z),
+            Axis::Y => (self.size.y, self.size.x, self.size.z),
+            Axis::Z => (self.size.z, self.size.x, self.size.y),
+        };
+        Slices {
+            model: self,
+            axis,
+            width,
+            height,
+            depths: 0..depth,
+        }
+    }
+
+    /// Scales this model up by an integer `factor` along every axis.
+    ///
+    /// Returns `None` if `factor` is zero, or if the scaled model would not fit
+    /// in the 256 voxels per axis allowed by the format.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = load("src/resources/placeholder.vox").unwrap();
+    /// let model = data.models[0].upscale(2, UpscaleMode::Nearest).unwrap();
+    /// assert_eq!(model.size, Size { x: 4, y: 4, z: 4 });
+    /// assert_eq!(model.voxels.len(), 32);
+    /// ```
+    pub fn upscale(&self, factor: u32, mode: UpscaleMode) -> Option<Model> {
+        let max = Size::MAX_AXIS;
+        let too_large = |s: u32| s.checked_mul(factor).map_or(true, |s| s > max);
+        if factor == 0 || too_large(self.size.x) || too_large(self.size.y) || too_large(self.size.z)
+        {
+            return None;
+        }
+
+        let colors: HashMap<[i32; 3], u8> = self
+            .voxels
+            .iter()
+            .map(|v| ([v.x as i32, v.y as i32, v.z as i32], v.i))
+            .collect();
+        let color = |[x, y, z]: [i32; 3]| colors.get(&[x, y, z]).copied();
+
+        // Every occupied cell, followed by the empty cells next to them, which
+        // the smooth mode may partially fill.
+        let mut cells: Vec<[i32; 3]> = self
+            .voxels
+            .iter()
+            .map(|v| [v.x as i32, v.y as i32, v.z as i32])
+            .collect();
+        if mode == UpscaleMode::Smooth {
+            let mut empty: Vec<[i32; 3]> = cells
+                .iter()
+                .flat_map(|[x, y, z]| {
+                    NEIGHBORS
+                        .iter()
+                        .map(move |[dx, dy, dz]| [x + dx, y + dy, z + dz])
+                })
+                .filter(|cell| {
+                    color(*cell).is_none()
+                        && cell[0] >= 0
+                        && cell[1] >= 0
+                        && cell[2] >= 0
+                        && (cell[0] as u32) < self.size.x
+                        && (cell[1] as u32) < self.size.y
+                        && (cell[2] as u32) < self.size.z
+                })
+                .collect();
+            empty.sort_unstable();
+            empty.dedup();
+            cells.extend(empty);
+        }
+
+        let factor = factor as i32;
+        // Which corner of the scaled up cell a sub-voxel lies in along one axis,
+        // or 0 for the middle of odd factors.
+        let side = |p: i32| {
+            if 2 * p + 1 < factor {
+                -1
+            } else if 2 * p + 1 > factor {
+                1
+            } else {
+                0
+            }
+        };
+
+        let mut voxels = Vec::new();
+        for cell in cells {
+            let center = color(cell);
+            for sz in 0..factor {
+                for sy in 0..factor {
+                    for sx in 0..factor {
+                        let value = match mode {
+                            UpscaleMode::Nearest => center,
+                            UpscaleMode::Smooth => {
+                                smooth_corner(&color, cell, [side(sx), side(sy), side(sz)])
+                                    .unwrap_or(center)
+                            }
+                        };
+                        if let Some(i) = value {
+                            voxels.push(Voxel {
+                                x: (cell[0] * factor + sx) as u8,
+                                y: (cell[1] * factor + sy) as u8,
+                                z: (cell[2] * factor + sz) as u8,
+                                i,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        let factor = factor as u32;
+        Some(Model {
+            size: Size {
+                x: self.size.x * factor,
+                y: self.size.y * factor,
+                z: self.size.z * factor,
+            },
+            voxels,
+        })
+    }
+}
+
+/// The 3D extension of the Scale2x rule: the corner of a cell takes the value
+/// of the three neighbors towards that corner if they agree with each other,
+/// and each differs from the neighbor on the opposite side of the cell.
+///
+/// Returns `None` when the rule does not apply, in which case the corner keeps
+/// the value of the cell.
+fn smooth_corner(
+    color: &impl Fn([i32; 3]) -> Option<u8>,
+    [x, y, z]: [i32; 3],
+    [sx, sy, sz]: [i32; 3],
+) -> Option<Option<u8>> {
+    if sx == 0 || sy == 0 || sz == 0 {
+        return None;
+    }
+
+    let a = color([x + sx, y, z]);
+    let b = color([x, y + sy, z]);
+    let c = color([x, y, z + sz]);
+    let applies = a == b
+        && b == c
+        && a != color([x - sx, y, z])
+        && b != color([x, y - sy, z])
+        && c != color([x, y, z - sz]);
+    applies.then_some(a)
+}
+
+/// How new voxels are filled in by [`Model::upscale`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpscaleMode {
+    /// Every voxel becomes a solid block of voxels of the same color.
+    Nearest,
+    /// Like [`Nearest`](UpscaleMode::Nearest), but corners are rounded off
+    /// following a 3D variant of the Scale2x algorithm: convex corners are
+    /// carved and concave corners filled in, without changing the topology of
+    /// the model.
+    Smooth,
+}
+
+/// Returns the coordinate of a voxel along `axis`, followed by its
+/// coordinates within the slice.
+fn slice_coordinates(axis: Axis, voxel: &Voxel) -> (u8, u8, u8) {
+    match axis {
+        Axis::X => (voxel.x, voxel.y, voxel.z),
+        Axis::Y => (voxel.y, voxel.x, voxel.z),
+        Axis::Z => (voxel.z, voxel.x, voxel.y),
+    }
+}
+
+/// Iterator over the [`Slice`]s of a model, created by [`Model::slices`].
+///
+/// Each slice is built when requested, by a pass over the voxels of the
+/// model, so only one slice is held in memory at a time.
+#[derive(Clone, Debug)]
+pub struct Slices<'a> {
+    model: &'a Model,
+    axis: Axis,
+    width: u32,
+    height: u32,
+    depths: std::ops::Range<u32>,
+}
+
+impl<'a> Slices<'a> {
+    fn build(&self, depth: u32) -> Slice {
+        let mut cells = vec![None; self.width as usize * self.height as usize];
+        for voxel in &self.model.voxels {
+            let (d, u, v) = slice_coordinates(self.axis, voxel);
+            if d as u32 == depth && (u as u32) < self.width && (v as u32) < self.height {
+                cells[v as usize * self.width as usize + u as usize] = Some(voxel.i);
+            }
+        }
+
+        Slice {
+            axis: self.axis,
+            depth,
+            width: self.width,
+            height: self.height,
+            cells,
+        }
+    }
+}
+
+impl<'a> Iterator for Slices<'a> {
+    type Item = Slice;
+
+    fn next(&mut self) -> Option<Slice> {
+        let depth = self.depths.next()?;
+        Some(self.build(depth))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.depths.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Slices<'a> {
+    fn next_back(&mut self) -> Option<Slice> {
+        let depth = self.depths.next_back()?;
+        Some(self.build(depth))
+    }
+}
+
+impl<'a> ExactSizeIterator for Slices<'a> {}
+
+/// A 2D cross-section of a model, one voxel thick.
+///
+/// Cells are addressed by `(u, v)` coordinates, which are the remaining two
+/// axes in order: `(y, z)` for slices along X, `(x, z)` along Y, and `(x, y)`
+/// along Z.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Slice {
+    /// The axis this slice is perpendicular to.
+    pub axis: Axis,
+    /// The coordinate of this slice along [`axis`](Slice::axis).
+    pub depth: u32,
+    /// Number of cells along `u`.
+    pub width: u32,
+    /// Number of cells along `v`.
+    pub height: u32,
+    /// Palette index of each cell, if occupied, in row-major order (`u`
+    /// varying fastest).
+    pub cells: Vec<Option<u8>>,
+}
+
+impl Slice {
+    /// Palette index of the voxel at `(u, v)`, if any.
+    pub fn get(&self, u: u32, v: u32) -> Option<u8> {
+        if u >= self.width || v >= self.height {
+            return None;
+        }
+        self.cells[(v * self.width + u) as usize]
+    }
+
+    /// Whether the cell at `(u, v)` is occupied.
+    pub fn is_occupied(&self, u: u32, v: u32) -> bool {
+        self.get(u, v).is_some()
+    }
 }
 
 /// The dimensions of a model in voxels.
@@ -39,11 +370,44 @@ pub struct Size {
     pub z: u32,
 }
 
+impl Size {
+    /// Largest size along each axis, as [`Voxel`] coordinates are stored in a
+    /// `u8`. MagicaVoxel uses the same limit.
+    pub const MAX_AXIS: u32 = u8::MAX as u32 + 1;
+
+    /// Whether voxel coordinates can address the whole model, i.e. no axis is
+    /// larger than [`Size::MAX_AXIS`].
+    pub fn is_within_limits(&self) -> bool {
+        self.x <= Self::MAX_AXIS && self.y <= Self::MAX_AXIS && self.z <= Self::MAX_AXIS
+    }
+
+    /// This size with every axis reduced to at most [`Size::MAX_AXIS`].
+    pub fn clamped(&self) -> Size {
+        Size {
+            x: self.x.min(Self::MAX_AXIS),
+            y: self.y.min(Self::MAX_AXIS),
+            z: self.z.min(Self::MAX_AXIS),
+        }
+    }
+
+    /// Whether this build supports models larger than [`Size::MAX_AXIS`]
+    /// along any axis. Always `false` for now, as voxel coordinates are
+    /// stored in a `u8`, but callers can check this rather than assume it.
+    pub const fn extended_sizes_supported() -> bool {
+        false
+    }
+}
+
 /// A voxel.
 ///
 /// A point in 3D space, with an indexed color attached. Uses Right handed Z up
 /// coordinate system.
+///
+/// Laid out as four bytes: `x`, `y`, `z`, then `i`, as stored in `XYZI`
+/// chunks. See [`gpu`](crate::gpu).
+#[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
 pub struct Voxel {
     /// The X coordinate for the voxel.
     pub x: u8,
@@ -59,26 +423,129 @@ pub struct Voxel {
     pub i: u8,
 }
 
+/// Parses the content of a `SIZE` chunk.
 pub fn parse_size(i: &[u8]) -> IResult<&[u8], Size> {
     let (i, (x, y, z)) = tuple((le_u32, le_u32, le_u32))(i)?;
     Ok((i, Size { x, y, z }))
 }
 
-fn parse_voxel(input: &[u8]) -> IResult<&[u8], Voxel> {
-    let (input, (x, y, z, i)) = tuple((le_u8, le_u8, le_u8, le_u8))(input)?;
-    Ok((
-        input,
-        Voxel {
-            x,
-            y,
-            z,
-            i: i.saturating_sub(1),
-        },
-    ))
-}
-
+/// Parses the content of an `XYZI` chunk. Palette indices are converted to
+/// the 0-based [`Voxel::i`].
 pub fn parse_voxels(i: &[u8]) -> IResult<&[u8], Vec<Voxel>> {
     let (i, n) = le_u32(i)?;
     let n = validate_count(i, n, 4)?;
-    count(parse_voxel, n)(i)
+    // Voxels are sliced straight out of the input, which is much faster than
+    // parsing them one by one for large models.
+    let (i, bytes) = take(n * 4)(i)?;
+    let voxels = bytes
+        .chunks_exact(4)
+        .map(|voxel| Voxel {
+            x: voxel[0],
+            y: voxel[1],
+            z: voxel[2],
+            i: voxel[3].saturating_sub(1),
+        })
+        .collect();
+    Ok((i, voxels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(side: u8) -> Model {
+        let mut voxels = Vec::new();
+        for z in 0..side {
+            for y in 0..side {
+                for x in 0..side {
+                    voxels.push(Voxel { x, y, z, i: 7 });
+                }
+            }
+        }
+        let side = side as u32;
+        Model {
+            size: Size {
+                x: side,
+                y: side,
+                z: side,
+            },
+            voxels,
+        }
+    }
+
+    #[test]
+    fn upscale_rejects_oversized_results() {
+        let empty = Model {
+            size: Size { x: 2, y: 1, z: 1 },
+            voxels: Vec::new(),
+        };
+        assert!(empty.upscale(0, UpscaleMode::Nearest).is_none());
+        assert!(empty.upscale(128, UpscaleMode::Nearest).is_some());
+        assert!(empty.upscale(129, UpscaleMode::Nearest).is_none());
+        assert!(empty.upscale(u32::MAX, UpscaleMode::Nearest).is_none());
+    }
+
+    #[test]
+    fn smooth_upscale_rounds_corners_of_a_cube() {
+        let model = cube(3).upscale(2, UpscaleMode::Smooth).unwrap();
+        // Each of the 8 corners of the cube loses its outermost sub-voxel.
+        assert_eq!(model.voxels.len(), 6 * 6 * 6 - 8);
+        assert!(!model.voxels.iter().any(|v| (v.x, v.y, v.z) == (0, 0, 0)));
+        assert!(model.voxels.iter().any(|v| (v.x, v.y, v.z) == (1, 0, 0)));
+    }
+
+    #[test]
+    fn smooth_upscale_keeps_isolated_voxels() {
+        let model = cube(1).upscale(2, UpscaleMode::Smooth).unwrap();
+        assert_eq!(model.voxels.len(), 8);
+    }
+
+    #[test]
+    fn slices_cover_every_layer() {
+        let model = cube(3);
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            let slices: Vec<Slice> = model.slices(axis).collect();
+            assert_eq!(slices.len(), 3);
+            for slice in slices {
+                assert!(slice.cells.iter().all(|cell| *cell == Some(7)));
+            }
+        }
+    }
+
+    #[test]
+    fn world_min_corner_bounds_every_voxel() {
+        let model = Model {
+            size: Size { x: 3, y: 4, z: 5 },
+            voxels: vec![
+                Voxel {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                    i: 1,
+                },
+                Voxel {
+                    x: 2,
+                    y: 3,
+                    z: 4,
+                    i: 1,
+                },
+            ],
+        };
+        assert_eq!(model.pivot(), [1, 2, 2]);
+        for rotation in crate::Rotation::all() {
+            let transform = Transform {
+                rotation,
+                translation: Position { x: 7, y: -2, z: 30 },
+            };
+            let corner = model.world_min_corner(&transform);
+            let positions = model
+                .voxels
+                .iter()
+                .map(|voxel| transform.voxel_position(model.size, voxel));
+            let min = positions.fold([i32::MAX; 3], |min, p| {
+                [min[0].min(p.x), min[1].min(p.y), min[2].min(p.z)]
+            });
+            assert_eq!([corner.x, corner.y, corner.z], min, "{rotation:?}");
+        }
+    }
 }
@@ -1,5 +1,18 @@
+use crate::{parser::validate_count, DotVoxData, Voxel};
 use nom::sequence::tuple;
-use nom::{combinator::all_consuming, multi::many0, number::complete::le_u8, IResult};
+use nom::{
+    combinator::all_consuming,
+    multi::{count, many0},
+    number::complete::{le_u32, le_u8},
+    IResult,
+};
+use std::mem::size_of;
+
+#[cfg(feature = "ahash")]
+use ahash::AHashMap as HashMap;
+
+#[cfg(not(feature = "ahash"))]
+use std::collections::HashMap;
 
 lazy_static! {
   /// The default palette used by [MagicaVoxel](https://ephtracy.github.io/) -- this is supplied if no palette
@@ -11,23 +24,382 @@ lazy_static! {
         .collect();
 }
 
+/// Parses the content of an `RGBA` chunk.
 pub fn extract_palette(i: &[u8]) -> IResult<&[u8], Vec<Color>> {
     all_consuming(many0(parse_color))(i)
 }
 
+/// Parses the content of an `IMAP` chunk, which must hold 256 entries.
+pub fn extract_index_map(i: &[u8]) -> IResult<&[u8], Vec<u8>> {
+    all_consuming(count(le_u8, 256))(i)
+}
+
 fn parse_color(input: &[u8]) -> IResult<&[u8], Color> {
     let (input, (r, g, b, a)) = tuple((le_u8, le_u8, le_u8, le_u8))(input)?;
     Ok((input, Color { r, g, b, a }))
 }
 
+/// Parses the content of an `XPAL` chunk, see [`PaletteExtension`].
+pub fn parse_palette_extension(i: &[u8]) -> IResult<&[u8], PaletteExtension> {
+    let (i, n) = le_u32(i)?;
+    let n = validate_count(i, n, size_of::<u32>())?;
+    let (i, colors) = count(parse_color, n)(i)?;
+    let (i, n) = le_u32(i)?;
+    let n = validate_count(i, n, size_of::<u32>())?;
+    let (i, remaps) = count(parse_remap, n)(i)?;
+    Ok((i, PaletteExtension { colors, remaps }))
+}
+
+fn parse_remap(i: &[u8]) -> IResult<&[u8], Vec<u32>> {
+    let (i, n) = le_u32(i)?;
+    let n = validate_count(i, n, size_of::<u32>())?;
+    count(le_u32, n)(i)
+}
+
+/// Non-standard extension lifting the limit of 255 colors per file, stored in
+/// an `XPAL` chunk that other readers ignore.
+///
+/// Each model gets its own remap table, translating the palette indices of its
+/// voxels into indices of a color table of any size shared by all models.
+/// Models without a remap table use the regular palette.
+///
+/// Files using this extension still open in MagicaVoxel, but display the
+/// voxel indices through the regular palette. Call
+/// [`DotVoxData::quantize_palette_extension`] before writing to bake the
+/// extension into the regular palette instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaletteExtension {
+    /// Colors shared by all models.
+    pub colors: Vec<Color>,
+    /// Remap table of each model, indexed by model then by
+    /// [`Voxel::i`](crate::Voxel::i), pointing into
+    /// [`colors`](PaletteExtension::colors).
+    pub remaps: Vec<Vec<u32>>,
+}
+
+impl PaletteExtension {
+    /// Serializes the content of the `XPAL` chunk.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.colors.len() as u32).to_le_bytes());
+        for color in self.colors.iter() {
+            bytes.extend_from_slice(&<[u8; 4]>::from(color));
+        }
+        bytes.extend_from_slice(&(self.remaps.len() as u32).to_le_bytes());
+        for remap in self.remaps.iter() {
+            bytes.extend_from_slice(&(remap.len() as u32).to_le_bytes());
+            for index in remap {
+                bytes.extend_from_slice(&index.to_le_bytes());
+            }
+        }
+        bytes
+    }
+}
+
+impl DotVoxData {
+    /// The palette index shown at the given position of the MagicaVoxel
+    /// palette editor, going through [`DotVoxData::index_map`] if present.
+    pub fn palette_index_at(&self, display_position: u8) -> u8 {
+        match &self.index_map {
+            Some(index_map) => index_map
+                .get(display_position as usize)
+                .copied()
+                .unwrap_or(display_position),
+            None => display_position,
+        }
+    }
+
+    /// The position at which the palette index `i` (see
+    /// [`Voxel::i`](crate::Voxel::i)) is shown in the MagicaVoxel palette
+    /// editor, going through [`DotVoxData::index_map`] if present.
+    pub fn display_position_of(&self, i: u8) -> Option<u8> {
+        match &self.index_map {
+            Some(index_map) => index_map
+                .iter()
+                .position(|&index| index == i)
+                .map(|position| position as u8),
+            None => Some(i),
+        }
+    }
+
+    /// The color of a voxel of the given model, going through the
+    /// [`PaletteExtension`] if the model has a remap table.
+    pub fn voxel_color(&self, model_id: u32, voxel: &Voxel) -> Option<Color> {
+        let remap = self
+            .palette_extension
+            .as_ref()
+            .and_then(|extension| Some((extension, extension.remaps.get(model_id as usize)?)))
+            .filter(|(_, remap)| !remap.is_empty());
+        match remap {
+            Some((extension, remap)) => {
+                let index = *remap.get(voxel.i as usize)?;
+                extension.colors.get(index as usize).copied()
+            }
+            None => self.palette.get(voxel.i as usize).copied(),
+        }
+    }
+
+    /// Bakes the [`PaletteExtension`] into the regular palette, so that the
+    /// file can be opened by other readers with the right colors.
+    ///
+    /// If the voxels use more than 255 distinct colors, the 255 most used ones
+    /// are kept, and the others replaced by the closest kept color. Returns the
+    /// number of colors that had to be replaced.
+    pub fn quantize_palette_extension(&mut self) -> usize {
+        if self.palette_extension.is_none() {
+            return 0;
+        }
+
+        let colors: Vec<Vec<Color>> = self
+            .models
+            .iter()
+            .enumerate()
+            .map(|(model_id, model)| {
+                model
+                    .voxels
+                    .iter()
+                    .map(|voxel| {
+                        self.voxel_color(model_id as u32, voxel).unwrap_or(Color {
+                            r: 0,
+                            g: 0,
+                            b: 0,
+                            a: 255,
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut usage: HashMap<[u8; 4], usize> = HashMap::new();
+        for color in colors.iter().flatten() {
+            *usage.entry(color.into()).or_default() += 1;
+        }
+        let mut by_usage: Vec<([u8; 4], usize)> = usage.into_iter().collect();
+        by_usage.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        let replaced = by_usage.len().saturating_sub(u8::MAX as usize);
+
+        let mut palette: Vec<Color> = by_usage
+            .iter()
+            .take(u8::MAX as usize)
+            .map(|([r, g, b, a], _)| Color {
+                r: *r,
+                g: *g,
+                b: *b,
+                a: *a,
+            })
+            .collect();
+        for (model, colors) in self.models.iter_mut().zip(colors) {
+            for (voxel, color) in model.voxels.iter_mut().zip(colors) {
+                voxel.i = palette.nearest(&color).unwrap_or(0);
+            }
+        }
+        palette.resize(
+            256,
+            Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+        );
+
+        self.palette = palette;
+        self.palette_extension = None;
+        replaced
+    }
+
+    /// Reorders the palette by how many voxels use each color, most used
+    /// first, so that the colors in use occupy the lowest indices. Colors
+    /// used equally often, and unused colors, keep their relative order.
+    ///
+    /// Voxels, materials, the [`index_map`](DotVoxData::index_map) and the
+    /// [`PaletteExtension`] remap tables are updated to match, so the file
+    /// looks the same. The last palette slot, which voxels cannot reference,
+    /// stays in place.
+    ///
+    /// Returns the new index of each previous palette index.
+    pub fn reindex_by_usage(&mut self) -> Vec<u8> {
+        if self.palette.is_empty() {
+            self.palette = DEFAULT_PALETTE.to_vec();
+        }
+        let slots = self.palette.len().min(u8::MAX as usize);
+
+        let mut usage = vec![0usize; slots];
+        for voxel in self.models.iter().flat_map(|model| &model.voxels) {
+            if let Some(count) = usage.get_mut(voxel.i as usize) {
+                *count += 1;
+            }
+        }
+        let mut order: Vec<usize> = (0..slots).collect();
+        order.sort_by_key(|&index| std::cmp::Reverse(usage[index]));
+
+        let mut remap: Vec<u8> = (0..=u8::MAX).collect();
+        for (new, &old) in order.iter().enumerate() {
+            remap[old] = new as u8;
+        }
+        let palette = self.palette.clone();
+        for (new, &old) in order.iter().enumerate() {
+            self.palette[new] = palette[old];
+        }
+
+        for voxel in self.models.iter_mut().flat_map(|model| &mut model.voxels) {
+            voxel.i = remap[voxel.i as usize];
+        }
+        for material in self.materials.iter_mut() {
+            if let Some(index) = (material.id as usize).checked_sub(1) {
+                if let Some(&new) = remap.get(index) {
+                    material.id = new as u32 + 1;
+                }
+            }
+        }
+        self.materials.sort_by_key(|material| material.id);
+        if let Some(index_map) = &mut self.index_map {
+            for index in index_map.iter_mut() {
+                *index = remap[*index as usize];
+            }
+        }
+        if let Some(extension) = &mut self.palette_extension {
+            for table in extension
+                .remaps
+                .iter_mut()
+                .filter(|table| !table.is_empty())
+            {
+                let previous = table.clone();
+                for (old, &new) in remap.iter().enumerate() {
+                    if let (Some(&color), Some(slot)) =
+                        (previous.get(old), table.get_mut(new as usize))
+                    {
+                        *slot = color;
+                    }
+                }
+            }
+        }
+        remap
+    }
+}
+
+/// Helpers for comparing and searching palettes, implemented for the slice of
+/// [`Color`]s stored in [`DotVoxData::palette`](crate::DotVoxData::palette).
+pub trait Palette {
+    /// Lists every palette slot that differs between `self` and `other`,
+    /// including slots only present in one of them.
+    fn diff(&self, other: &[Color]) -> Vec<PaletteChange>;
+
+    /// Returns the index of the entry closest to `color`, using the squared
+    /// euclidean distance between RGBA components. Only the first 255 entries
+    /// are considered, as those are the only ones a [`Voxel`](crate::Voxel)
+    /// can reference.
+    fn nearest(&self, color: &Color) -> Option<u8>;
+}
+
+/// A single differing slot reported by [`Palette::diff`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PaletteChange {
+    /// Index of the slot in the palette.
+    pub index: usize,
+    /// The color in the palette `diff` was called on, if the slot exists.
+    pub old: Option<Color>,
+    /// The color in the other palette, if the slot exists.
+    pub new: Option<Color>,
+}
+
+impl Palette for [Color] {
+    fn diff(&self, other: &[Color]) -> Vec<PaletteChange> {
+        (0..self.len().max(other.len()))
+            .filter_map(|index| {
+                let old = self.get(index).copied();
+                let new = other.get(index).copied();
+                (old != new).then_some(PaletteChange { index, old, new })
+            })
+            .collect()
+    }
+
+    fn nearest(&self, color: &Color) -> Option<u8> {
+        let distance = |other: &Color| -> u32 {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+            d(color.r, other.r) + d(color.g, other.g) + d(color.b, other.b) + d(color.a, other.a)
+        };
+
+        self.iter()
+            .take(u8::MAX as usize)
+            .enumerate()
+            .min_by_key(|(_, other)| distance(other))
+            .map(|(index, _)| index as u8)
+    }
+}
+
+/// An RGBA color, as stored in the palette.
+///
+/// Laid out as four bytes in RGBA order, matching the `Rgba8Unorm` texture
+/// formats. See [`gpu`](crate::gpu).
+#[repr(C)]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "bytemuck", derive(bytemuck::Pod, bytemuck::Zeroable))]
 pub struct Color {
+    /// The red component.
     pub r: u8,
+    /// The green component.
     pub g: u8,
+    /// The blue component.
     pub b: u8,
+    /// The alpha component.
     pub a: u8,
 }
 
+impl Color {
+    /// Parses a color from a hex string in the `#RRGGBBAA` or `#RRGGBB` format.
+    /// The leading `#` is optional, and the alpha defaults to `255` if omitted.
+    ///
+    /// ```
+    /// use dot_vox::Color;
+    ///
+    /// assert_eq!(
+    ///     Color::from_hex("#ff800040"),
+    ///     Some(Color { r: 255, g: 128, b: 0, a: 64 })
+    /// );
+    /// assert_eq!(Color::from_hex("ff8000").map(|c| c.a), Some(255));
+    /// assert_eq!(Color::from_hex("#ff80"), None);
+    /// ```
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return None;
+        }
+
+        let component = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Color {
+            r: component(0)?,
+            g: component(2)?,
+            b: component(4)?,
+            a: if hex.len() == 8 { component(6)? } else { 255 },
+        })
+    }
+
+    /// Formats this color as a lowercase `#rrggbbaa` hex string.
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+    }
+
+    /// Linearly interpolates every component between `self` (at `t = 0`) and
+    /// `other` (at `t = 1`). `t` is clamped to `[0, 1]`.
+    pub fn lerp(&self, other: &Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color {
+            r: lerp(self.r, other.r),
+            g: lerp(self.g, other.g),
+            b: lerp(self.b, other.b),
+            a: lerp(self.a, other.a),
+        }
+    }
+
+    /// The relative luminance of this color in `[0, 1]`, using the Rec. 709
+    /// coefficients on the (non-linearized) RGB components. Alpha is ignored.
+    pub fn luminance(&self) -> f32 {
+        (0.2126 * self.r as f32 + 0.7152 * self.g as f32 + 0.0722 * self.b as f32) / 255.0
+    }
+}
+
 impl From<Color> for [u8; 4] {
     fn from(color: Color) -> Self {
         [color.r, color.g, color.b, color.a]
@@ -38,3 +410,287 @@ impl From<&Color> for [u8; 4] {
         [color.r, color.g, color.b, color.a]
     }
 }
+
+impl From<[f32; 4]> for Color {
+    /// Converts normalized RGBA components, clamping each to `[0, 1]`.
+    fn from(color: [f32; 4]) -> Self {
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color {
+            r: to_u8(color[0]),
+            g: to_u8(color[1]),
+            b: to_u8(color[2]),
+            a: to_u8(color[3]),
+        }
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [
+            color.r as f32 / 255.0,
+            color.g as f32 / 255.0,
+            color.b as f32 / 255.0,
+            color.a as f32 / 255.0,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_changed_and_missing_slots() {
+        let mut other = DEFAULT_PALETTE[..10].to_vec();
+        other[3] = Color {
+            r: 1,
+            g: 2,
+            b: 3,
+            a: 4,
+        };
+        let diff = DEFAULT_PALETTE[..11].diff(&other);
+        assert_eq!(
+            diff,
+            vec![
+                PaletteChange {
+                    index: 3,
+                    old: Some(DEFAULT_PALETTE[3]),
+                    new: Some(other[3]),
+                },
+                PaletteChange {
+                    index: 10,
+                    old: Some(DEFAULT_PALETTE[10]),
+                    new: None,
+                },
+            ]
+        );
+        assert!(DEFAULT_PALETTE.diff(&DEFAULT_PALETTE).is_empty());
+    }
+
+    #[test]
+    fn nearest_finds_exact_and_close_matches() {
+        for (i, color) in DEFAULT_PALETTE.iter().enumerate().take(255) {
+            let nearest = DEFAULT_PALETTE.nearest(color).unwrap() as usize;
+            assert_eq!(DEFAULT_PALETTE[nearest], DEFAULT_PALETTE[i]);
+        }
+        let palette = [
+            Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            Color {
+                r: 250,
+                g: 250,
+                b: 250,
+                a: 255,
+            },
+        ];
+        let light_grey = Color {
+            r: 200,
+            g: 200,
+            b: 200,
+            a: 255,
+        };
+        assert_eq!(palette.nearest(&light_grey), Some(1));
+        assert_eq!([].nearest(&light_grey), None);
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        for color in DEFAULT_PALETTE.iter() {
+            assert_eq!(Color::from_hex(&color.to_hex()), Some(*color));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_hex() {
+        assert_eq!(Color::from_hex(""), None);
+        assert_eq!(Color::from_hex("#12345"), None);
+        assert_eq!(Color::from_hex("#gg0000"), None);
+        assert_eq!(Color::from_hex("#ééé"), None);
+    }
+
+    #[test]
+    fn float_conversion_round_trips() {
+        let color = Color {
+            r: 12,
+            g: 34,
+            b: 56,
+            a: 255,
+        };
+        let floats: [f32; 4] = color.into();
+        assert_eq!(Color::from(floats), color);
+        assert_eq!(
+            Color::from([2.0, -1.0, 0.5, 1.0]),
+            Color {
+                r: 255,
+                g: 0,
+                b: 128,
+                a: 255
+            }
+        );
+    }
+
+    #[test]
+    fn lerp_and_luminance() {
+        let black = Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        let white = Color {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        };
+        assert_eq!(black.lerp(&white, 0.0), black);
+        assert_eq!(black.lerp(&white, 1.0), white);
+        assert_eq!(black.lerp(&white, 0.5).r, 128);
+        assert_eq!(black.luminance(), 0.0);
+        assert!((white.luminance() - 1.0).abs() < 1e-6);
+    }
+
+    fn extended_data() -> DotVoxData {
+        let mut data = crate::load_bytes(include_bytes!("resources/placeholder.vox")).unwrap();
+        let colors: Vec<Color> = (0..300u32)
+            .map(|n| Color {
+                r: (n % 256) as u8,
+                g: (n / 256) as u8,
+                b: 0,
+                a: 255,
+            })
+            .collect();
+        let model = &mut data.models[0];
+        model.voxels = (0..300u32)
+            .map(|n| Voxel {
+                x: (n % 10) as u8,
+                y: (n / 10) as u8,
+                z: 0,
+                i: (n % 255) as u8,
+            })
+            .collect();
+        data.models.push(crate::Model {
+            size: data.models[0].size,
+            voxels: data.models[0].voxels.clone(),
+        });
+        data.palette_extension = Some(PaletteExtension {
+            colors,
+            remaps: vec![(0..255).collect(), (45..300).collect()],
+        });
+        data
+    }
+
+    #[test]
+    fn palette_extension_round_trips() {
+        let data = extended_data();
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+        let reloaded = crate::load_bytes(&bytes).unwrap();
+        assert_eq!(reloaded.palette_extension, data.palette_extension);
+    }
+
+    #[test]
+    fn voxel_color_uses_remap_table() {
+        let mut data = extended_data();
+        let voxel = data.models[0].voxels[3];
+        let color = |r| Color {
+            r,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        assert_eq!(data.voxel_color(0, &voxel), Some(color(3)));
+        assert_eq!(data.voxel_color(1, &voxel), Some(color(48)));
+
+        data.palette_extension.as_mut().unwrap().remaps[1].clear();
+        assert_eq!(data.voxel_color(1, &voxel), data.palette.get(3).copied());
+    }
+
+    #[test]
+    fn quantize_bakes_extension_into_palette() {
+        let mut data = extended_data();
+        let expected: Vec<Color> = data.models[0]
+            .voxels
+            .iter()
+            .map(|voxel| data.voxel_color(0, voxel).unwrap())
+            .collect();
+
+        // Colors 255 to 299 are the least used, only by the second model.
+        assert_eq!(data.quantize_palette_extension(), 45);
+        assert_eq!(data.palette_extension, None);
+        assert_eq!(data.palette.len(), 256);
+        for (voxel, color) in data.models[0].voxels.iter().zip(expected) {
+            assert_eq!(data.palette[voxel.i as usize], color);
+        }
+    }
+
+    #[test]
+    fn index_map_round_trips_and_translates_indices() {
+        let mut data = crate::load_bytes(include_bytes!("resources/placeholder.vox")).unwrap();
+        assert_eq!(data.index_map, None);
+        assert_eq!(data.palette_index_at(7), 7);
+        assert_eq!(data.display_position_of(7), Some(7));
+
+        data.index_map = Some((0..=255).rev().collect());
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+        let reloaded = crate::load_bytes(&bytes).unwrap();
+        assert_eq!(reloaded.index_map, data.index_map);
+        assert_eq!(reloaded.palette_index_at(0), 255);
+        assert_eq!(reloaded.display_position_of(7), Some(248));
+    }
+
+    #[test]
+    fn index_map_must_have_256_entries() {
+        assert!(extract_index_map(&[0; 256]).is_ok());
+        assert!(extract_index_map(&[0; 255]).is_err());
+        assert!(extract_index_map(&[0; 257]).is_err());
+    }
+
+    #[test]
+    fn reindex_by_usage_moves_used_colors_first() {
+        let load = || {
+            let mut data = crate::load("src/resources/placeholder-with-materials.vox").unwrap();
+            let voxel = |x, i| Voxel { x, y: 0, z: 0, i };
+            data.models[0].voxels = vec![voxel(0, 200), voxel(1, 5), voxel(2, 200), voxel(3, 200)];
+            data.index_map = Some((0..=255).rev().collect());
+            data
+        };
+        let (mut data, before) = (load(), load());
+
+        let remap = data.reindex_by_usage();
+        assert_eq!(remap[200], 0);
+        assert_eq!(remap[5], 1);
+        assert_eq!(remap[0], 2);
+        assert_eq!(remap[255], 255);
+        let indices: Vec<u8> = data.models[0].voxels.iter().map(|v| v.i).collect();
+        assert_eq!(indices, [0, 1, 0, 0]);
+
+        for (voxel, original) in data.models[0].voxels.iter().zip(&before.models[0].voxels) {
+            assert_eq!(
+                data.palette[voxel.i as usize],
+                before.palette[original.i as usize]
+            );
+            let material = |data: &DotVoxData, i: u8| {
+                data.materials
+                    .iter()
+                    .find(|material| material.id == i as u32 + 1)
+                    .map(|material| material.properties.clone())
+            };
+            assert_eq!(material(&data, voxel.i), material(&before, original.i));
+        }
+        for position in 0..=255 {
+            let old = before.palette_index_at(position);
+            assert_eq!(data.palette_index_at(position), remap[old as usize]);
+        }
+        assert_eq!(data.palette[255], before.palette[255]);
+
+        // Already ordered by usage.
+        let identity: Vec<u8> = (0..=255).collect();
+        assert_eq!(data.reindex_by_usage(), identity);
+    }
+}
@@ -1,45 +1,129 @@
 use crate::{
-    model, palette, scene, Color, DotVoxData, Frame, Layer, Model, RawLayer, SceneGroup, SceneNode,
-    SceneShape, SceneTransform, Size, Voxel, DEFAULT_PALETTE,
+    camera, model, palette, scene, surface, Camera, ChunkKind, Color, DotVoxData, Error, Frame,
+    Layer, Limit, Model, PaletteExtension, RawLayer, SceneGroup, SceneNode, SceneShape,
+    SceneTransform, Size, Voxel, Warning, DEFAULT_PALETTE,
 };
 use nom::{
     bytes::complete::{tag, take},
-    combinator::{flat_map, map_res},
-    error::make_error,
-    multi::{fold_many_m_n, many0},
-    number::complete::le_u32,
-    sequence::pair,
-    IResult,
+    combinator::{flat_map, map, map_res},
+    error::{make_error, ErrorKind},
+    multi::{count, many0},
+    number::complete::{le_f32, le_u32},
+    sequence::{pair, tuple},
+    IResult, Offset,
+};
+use std::{
+    borrow::Cow,
+    collections::hash_map::Entry,
+    fmt,
+    mem::size_of,
+    ops::{BitOr, BitOrAssign},
+    str,
+    str::Utf8Error,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
 };
-use std::{mem::size_of, str, str::Utf8Error};
 
 #[cfg(feature = "ahash")]
 use ahash::AHashMap as HashMap;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
 #[cfg(not(feature = "ahash"))]
 use std::collections::HashMap;
 
-const MAGIC_NUMBER: &str = "VOX ";
+pub(crate) const MAGIC_NUMBER: &str = "VOX ";
+
+/// The newest `.vox` file version whose content this crate understands.
+/// Newer files are parsed on a best-effort basis, see
+/// [`ParseOptions::reject_newer_versions`].
+pub const SUPPORTED_VERSION: u32 = 200;
 
 #[derive(Debug, PartialEq)]
 pub enum Chunk {
     Main(Vec<Chunk>),
+    /// The number of models, from the legacy `PACK` chunk.
+    Pack(u32),
     Size(Size),
     Voxels(Vec<Voxel>),
     Palette(Vec<Color>),
+    PaletteExtension(PaletteExtension),
+    IndexMap(Vec<u8>),
+    RenderObject(Dict),
+    Camera(Camera),
     Material(Material),
     TransformNode(SceneTransform),
     GroupNode(SceneGroup),
     ShapeNode(SceneShape),
     Layer(RawLayer),
-    Unknown(String),
-    Invalid(Vec<u8>),
+    Unknown(RawChunk),
+    Invalid(ChunkLocation),
+    /// A chunk of a section not requested with [`ParseOptions::sections`].
+    Skipped,
+}
+
+impl Chunk {
+    /// The kind of this chunk, for the chunks that are always kept when read.
+    fn kind(&self) -> Option<ChunkKind> {
+        match self {
+            Chunk::TransformNode(_) | Chunk::GroupNode(_) | Chunk::ShapeNode(_) => {
+                Some(ChunkKind::SceneGraph)
+            }
+            Chunk::Layer(_) => Some(ChunkKind::Layers),
+            Chunk::Palette(_) => Some(ChunkKind::Palette),
+            Chunk::PaletteExtension(_) => Some(ChunkKind::PaletteExtension),
+            Chunk::IndexMap(_) => Some(ChunkKind::IndexMap),
+            Chunk::Material(_) => Some(ChunkKind::Materials),
+            Chunk::RenderObject(_) => Some(ChunkKind::RenderObjects),
+            Chunk::Camera(_) => Some(ChunkKind::Cameras),
+            _ => None,
+        }
+    }
+}
+
+/// Position of a chunk in the file being parsed, for error reporting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ChunkLocation {
+    /// Id of the chunk, decoded lossily.
+    pub id: String,
+    /// Offset of the chunk header in the file.
+    pub offset: usize,
+    /// Ids of the chunks enclosing this one, outermost first.
+    pub path: Vec<String>,
+}
+
+impl fmt::Display for ChunkLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} chunk at offset {} (", self.id, self.offset)?;
+        for parent in self.path.iter() {
+            write!(f, "{} > ", parent)?;
+        }
+        write!(f, "{})", self.id)
+    }
+}
+
+/// A chunk not recognized by this crate, kept as is so that it can be written
+/// back by [`DotVoxData::write_vox`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawChunk {
+    /// The chunk id.
+    pub id: String,
+    /// The content of the chunk.
+    pub content: Vec<u8>,
+    /// The children of the chunk, as they appear in the file.
+    pub children: Vec<u8>,
 }
 
 /// A material used to render this model.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Material {
     /// The Material's ID.  Corresponds to an index in the palette.
+    ///
+    /// Like the indices stored in the file, material IDs are 1-based: the
+    /// material applying to a [`Voxel`] has an ID of `voxel.i + 1`.
     pub id: u32,
     /// Properties of the material, mapped by property name.
     pub properties: Dict,
@@ -167,6 +251,29 @@ impl Material {
         None
     }
 
+    /// Returns a builder for a material with the given id and no properties.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let material = Material::builder(9)
+    ///     .material_type("_metal")
+    ///     .metal(0.8)
+    ///     .roughness(0.2)
+    ///     .build();
+    /// assert_eq!(material.material_type(), Some("_metal"));
+    /// assert_eq!(material.metalness(), Some(0.8));
+    /// assert_eq!(material.roughness(), Some(0.2));
+    /// ```
+    pub fn builder(id: u32) -> MaterialBuilder {
+        MaterialBuilder {
+            material: Material {
+                id,
+                properties: Dict::new(),
+            },
+        }
+    }
+
     fn get_f32(&self, prop: &str) -> Option<f32> {
         if let Some(t) = self.properties.get(prop) {
             match t.parse::<f32>() {
@@ -181,22 +288,723 @@ impl Material {
     }
 }
 
+/// Builds a [`Material`] with typed setters, created by [`Material::builder`].
+/// Each setter writes the field read by the matching getter of [`Material`].
+#[derive(Clone, Debug)]
+pub struct MaterialBuilder {
+    material: Material,
+}
+
+impl MaterialBuilder {
+    /// Sets the `_type` field, such as `_diffuse`, `_metal`, `_glass`,
+    /// `_emit`, `_blend` or `_media`. See [`Material::material_type`].
+    pub fn material_type(self, material_type: &str) -> Self {
+        self.property("_type", material_type)
+    }
+
+    /// Sets the `_weight` field, see [`Material::weight`].
+    pub fn weight(self, value: f32) -> Self {
+        self.property("_weight", &value.to_string())
+    }
+
+    /// Sets the `_metal` field, see [`Material::metalness`].
+    pub fn metal(self, value: f32) -> Self {
+        self.property("_metal", &value.to_string())
+    }
+
+    /// Sets the `_rough` field, see [`Material::roughness`].
+    pub fn roughness(self, value: f32) -> Self {
+        self.property("_rough", &value.to_string())
+    }
+
+    /// Sets the `_sp` field, see [`Material::specular`].
+    pub fn specular(self, value: f32) -> Self {
+        self.property("_sp", &value.to_string())
+    }
+
+    /// Sets the `_ior` field, see [`Material::refractive_index`].
+    pub fn refractive_index(self, value: f32) -> Self {
+        self.property("_ior", &value.to_string())
+    }
+
+    /// Sets the `_emit` field, see [`Material::emission`].
+    pub fn emit(self, value: f32) -> Self {
+        self.property("_emit", &value.to_string())
+    }
+
+    /// Sets the `_ldr` field, see [`Material::low_dynamic_range_scale`].
+    pub fn low_dynamic_range_scale(self, value: f32) -> Self {
+        self.property("_ldr", &value.to_string())
+    }
+
+    /// Sets the `_att` field, see [`Material::attenuation`].
+    pub fn attenuation(self, value: f32) -> Self {
+        self.property("_att", &value.to_string())
+    }
+
+    /// Sets the `_flux` field, see [`Material::radiant_flux`].
+    pub fn radiant_flux(self, value: f32) -> Self {
+        self.property("_flux", &value.to_string())
+    }
+
+    /// Sets the `_g` field, see [`Material::phase`].
+    pub fn phase(self, value: f32) -> Self {
+        self.property("_g", &value.to_string())
+    }
+
+    /// Sets the `_alpha` field, see [`Material::opacity`].
+    pub fn opacity(self, value: f32) -> Self {
+        self.property("_alpha", &value.to_string())
+    }
+
+    /// Sets the `_trans` field, see [`Material::transparency`].
+    pub fn transparency(self, value: f32) -> Self {
+        self.property("_trans", &value.to_string())
+    }
+
+    /// Sets the `_d` field, see [`Material::density`].
+    pub fn density(self, value: f32) -> Self {
+        self.property("_d", &value.to_string())
+    }
+
+    /// Sets the `_media` field, see [`Material::media`].
+    pub fn media(self, value: f32) -> Self {
+        self.property("_media", &value.to_string())
+    }
+
+    /// Sets the `_media_type` field, see [`Material::media_type`].
+    pub fn media_type(self, media_type: &str) -> Self {
+        self.property("_media_type", media_type)
+    }
+
+    /// Sets the field `name` to `value`, for properties without a typed
+    /// setter.
+    pub fn property(mut self, name: &str, value: &str) -> Self {
+        self.material
+            .properties
+            .insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Returns the material built.
+    pub fn build(self) -> Material {
+        self.material
+    }
+}
+
 /// General dictionary.
 pub type Dict = HashMap<String, String>;
 
+/// Decodes UTF-8, failing on invalid sequences.
 pub fn to_str(i: &[u8]) -> Result<String, Utf8Error> {
     let res = str::from_utf8(i)?;
     Ok(res.to_owned())
 }
 
+/// Decodes UTF-8, replacing invalid sequences with `U+FFFD`. Some localized
+/// builds of MagicaVoxel write names in legacy encodings such as Latin-1.
+pub fn to_str_lossy(i: &[u8]) -> String {
+    let res = String::from_utf8_lossy(i);
+    if let Cow::Owned(_) = res {
+        debug!("Replaced invalid UTF-8 in string: {}", res);
+    }
+    res.into_owned()
+}
+
+/// Options controlling how a `.vox` file is parsed, used by
+/// [`load_bytes_with`](crate::load_bytes_with).
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let options = ParseOptions::default().surface_only(true);
+/// let result = load_bytes_with(include_bytes!("resources/placeholder.vox"), options);
+/// assert!(result.is_ok());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    mode: ParseMode,
+    surface_only: bool,
+    pub(crate) integrity_check: bool,
+    reject_newer_versions: bool,
+    /// Keeps the children of a truncated `MAIN` chunk that are present.
+    recover_truncated: bool,
+    skip_scene_synthesis: bool,
+    progress: Option<Progress>,
+    cancel: Option<Arc<AtomicBool>>,
+    duplicate_keys: DuplicateKeys,
+    skipped_sections: Sections,
+    /// Ids of the chunks collected into [`DotVoxData::extension_chunks`].
+    extension_ids: Vec<String>,
+    limits: Limits,
+    /// What the file being parsed has used of `limits`, shared between the
+    /// threads decoding it.
+    usage: Arc<Usage>,
+}
+
+/// Limits set on [`ParseOptions`], `None` when unlimited.
+#[derive(Clone, Copy, Debug, Default)]
+struct Limits {
+    total_voxels: Option<usize>,
+    nodes: Option<usize>,
+    dict_entries: Option<usize>,
+    depth: Option<usize>,
+}
+
+/// Nesting depth of chunks allowed when [`ParseOptions::max_depth`] is not set.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Default)]
+struct Usage {
+    total_voxels: AtomicUsize,
+    nodes: AtomicUsize,
+    dict_entries: AtomicUsize,
+    /// The first limit exceeded, if any.
+    exceeded: std::sync::OnceLock<Limit>,
+}
+
+/// Callback set by [`ParseOptions::progress`].
+#[derive(Clone)]
+struct Progress(Arc<dyn Fn(usize, usize) + Send + Sync>);
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
+
+/// How strictly malformed content is handled, see [`ParseOptions::mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject malformed content.
+    Strict,
+    /// Recover from malformed content where possible.
+    #[default]
+    Lenient,
+}
+
+/// A set of the sections of a `.vox` file, see [`ParseOptions::sections`].
+/// Combine sections with `|`.
+///
+/// The default is the empty set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Sections(u8);
+
+impl Sections {
+    /// No section.
+    pub const NONE: Sections = Sections(0);
+    /// Models, from the `SIZE` and `XYZI` chunks, and the legacy `PACK`
+    /// chunk.
+    pub const MODELS: Sections = Sections(1 << 0);
+    /// The palette, from the `RGBA`, `XPAL` and `IMAP` chunks.
+    pub const PALETTE: Sections = Sections(1 << 1);
+    /// Materials, from the `MATL` and `MATT` chunks.
+    pub const MATERIALS: Sections = Sections(1 << 2);
+    /// The scene graph and layers, from the `nTRN`, `nGRP`, `nSHP` and `LAYR`
+    /// chunks.
+    pub const SCENES: Sections = Sections(1 << 3);
+    /// Render settings and cameras, from the `rOBJ` and `rCAM` chunks.
+    pub const RENDER: Sections = Sections(1 << 4);
+    /// Extension and unknown chunks.
+    pub const OTHER: Sections = Sections(1 << 5);
+    /// Every section.
+    pub const ALL: Sections = Sections((1 << 6) - 1);
+
+    /// Whether every section of `other` is in `self`.
+    pub const fn contains(self, other: Sections) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The section holding chunks with the given id.
+    fn of(id: &str) -> Sections {
+        match id {
+            "PACK" | "SIZE" | "XYZI" => Sections::MODELS,
+            "RGBA" | "XPAL" | "IMAP" => Sections::PALETTE,
+            "MATL" | "MATT" => Sections::MATERIALS,
+            "nTRN" | "nGRP" | "nSHP" | "LAYR" => Sections::SCENES,
+            "rOBJ" | "rCAM" => Sections::RENDER,
+            _ => Sections::OTHER,
+        }
+    }
+}
+
+impl BitOr for Sections {
+    type Output = Sections;
+
+    fn bitor(self, other: Sections) -> Sections {
+        Sections(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Sections {
+    fn bitor_assign(&mut self, other: Sections) {
+        self.0 |= other.0;
+    }
+}
+
+/// What to do with a key found more than once in a dictionary, see
+/// [`ParseOptions::duplicate_keys`].
+///
+/// Some exporters write the same key, such as `_t`, several times. To see
+/// every value, parse the dictionary with
+/// [`raw::parse_dict_entries`](crate::raw::parse_dict_entries), or use
+/// [`DotVoxDataRef`](crate::DotVoxDataRef), whose dictionaries keep every
+/// entry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Keep the value of the last entry.
+    #[default]
+    LastWins,
+    /// Keep the value of the first entry.
+    FirstWins,
+    /// Make the chunk containing the dictionary invalid.
+    Reject,
+}
+
+impl ParseOptions {
+    /// Sets how strictly malformed content is handled. Defaults to
+    /// [`ParseMode::Lenient`].
+    ///
+    /// - Strings that are not valid UTF-8 make the chunk containing them
+    ///   invalid in strict mode. In lenient mode they are decoded with invalid
+    ///   sequences replaced by `U+FFFD`.
+    /// - Model sizes larger than [`Size::MAX_AXIS`] along any axis make the
+    ///   `SIZE` chunk invalid in strict mode. In lenient mode they are clamped.
+    /// - Invalid, truncated or corrupt chunks fail the whole parse with
+    ///   [`Error::InvalidChunk`] in strict mode. In lenient mode they are
+    ///   skipped, keeping the content of every other chunk.
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets what to do with keys found more than once in a dictionary.
+    /// Defaults to [`DuplicateKeys::LastWins`].
+    ///
+    /// With [`DuplicateKeys::Reject`], the chunk containing the dictionary is
+    /// invalid, which fails the parse in [`ParseMode::Strict`] and skips the
+    /// chunk otherwise.
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// Rejects files for which [`check_integrity`](crate::check_integrity)
+    /// reports any issue, i.e. files where the sizes declared by chunks do not
+    /// exactly match their content.
+    pub fn integrity_check(mut self, integrity_check: bool) -> Self {
+        self.integrity_check = integrity_check;
+        self
+    }
+
+    /// Discards the interior voxels of every model while parsing, i.e. voxels
+    /// whose six neighbors within the same model are all occupied. Only the
+    /// shell of each model is kept, which is all a viewer needs.
+    ///
+    /// Neighbors are only looked up within a model: neither the scene graph
+    /// nor transparent materials are taken into account. Use
+    /// [`DotVoxData::strip_hidden`] for that.
+    pub fn surface_only(mut self, surface_only: bool) -> Self {
+        self.surface_only = surface_only;
+        self
+    }
+
+    /// Gives files without a scene graph, as written by very old versions of
+    /// MagicaVoxel, a synthetic one placing every model, so that scene APIs
+    /// work the same for every file. Defaults to `true`. See
+    /// [`DotVoxData::synthesize_scene`].
+    pub fn synthesize_scene(mut self, synthesize_scene: bool) -> Self {
+        self.skip_scene_synthesis = !synthesize_scene;
+        self
+    }
+
+    /// Calls `progress` with the number of bytes parsed so far and the total
+    /// number of bytes after each chunk, e.g. to show a progress bar while a
+    /// large file is loading. With the `rayon` feature, chunks are decoded in
+    /// parallel and `progress` may be called from any thread, though never
+    /// concurrently.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let done = Arc::new(Mutex::new(0));
+    /// let reported = done.clone();
+    /// let options = ParseOptions::default().progress(move |bytes_done, bytes_total| {
+    ///     *reported.lock().unwrap() = bytes_done * 100 / bytes_total;
+    /// });
+    /// load_bytes_with(include_bytes!("resources/placeholder.vox"), options).unwrap();
+    /// assert_eq!(*done.lock().unwrap(), 100);
+    /// ```
+    pub fn progress(mut self, progress: impl Fn(usize, usize) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Progress(Arc::new(progress)));
+        self
+    }
+
+    /// Aborts parsing with [`Error::Cancelled`] once `cancel` is set, e.g.
+    /// from a loading screen, for files that take a while to parse. The flag
+    /// is checked before each chunk.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    /// use std::sync::{atomic::AtomicBool, Arc};
+    ///
+    /// let cancel = Arc::new(AtomicBool::new(true));
+    /// let options = ParseOptions::default().cancel_flag(cancel);
+    /// let result = load_bytes_with(include_bytes!("resources/placeholder.vox"), options);
+    /// assert!(matches!(result, Err(Error::Cancelled)));
+    /// ```
+    pub fn cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Fails to parse files whose version is newer than
+    /// [`SUPPORTED_VERSION`] with [`Error::UnsupportedVersion`], rather than
+    /// parsing them on a best-effort basis. Such files may contain content
+    /// that this crate does not understand and drops.
+    /// [`load_bytes_partial`](crate::load_bytes_partial) reports them with
+    /// [`Warning::NewerVersion`] otherwise.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut bytes = include_bytes!("resources/placeholder.vox").to_vec();
+    /// bytes[4..8].copy_from_slice(&(SUPPORTED_VERSION + 1).to_le_bytes());
+    /// let options = ParseOptions::default().reject_newer_versions(true);
+    /// let result = load_bytes_with(&bytes, options);
+    /// assert!(matches!(result, Err(Error::UnsupportedVersion(201))));
+    /// ```
+    pub fn reject_newer_versions(mut self, reject_newer_versions: bool) -> Self {
+        self.reject_newer_versions = reject_newer_versions;
+        self
+    }
+
+    /// Restricts parsing to the given sections, to save the time and memory
+    /// spent on the others. Defaults to [`Sections::ALL`].
+    ///
+    /// The chunks of other sections are skipped without being decoded, leaving
+    /// the corresponding fields of [`DotVoxData`] empty, and the palette set to
+    /// [`DEFAULT_PALETTE`]. Without [`Sections::SCENES`], no scene graph is
+    /// [synthesized](ParseOptions::synthesize_scene) either.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let options = ParseOptions::default().sections(Sections::MODELS | Sections::PALETTE);
+    /// let data = load_bytes_with(include_bytes!("resources/placeholder.vox"), options).unwrap();
+    /// assert_eq!(data.models.len(), 1);
+    /// assert_eq!(data.palette.len(), 256);
+    /// assert!(data.materials.is_empty());
+    /// assert!(data.scenes.is_empty());
+    /// ```
+    pub fn sections(mut self, sections: Sections) -> Self {
+        self.skipped_sections = Sections(Sections::ALL.0 & !sections.0);
+        self
+    }
+
+    /// Registers `id` as an application-defined chunk. Chunks with this id and
+    /// no children are collected into [`DotVoxData::extension_chunks`] instead
+    /// of [`DotVoxData::unknown_chunks`]. Ids of chunks known to this crate
+    /// are decoded as usual.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut data = load("src/resources/placeholder.vox").unwrap();
+    /// data.extension_chunks.push(("gAME".to_owned(), b"spawn".to_vec()));
+    /// let mut bytes = Vec::new();
+    /// data.write_vox(&mut bytes).unwrap();
+    ///
+    /// let options = ParseOptions::default().extension_chunk("gAME");
+    /// let reloaded = load_bytes_with(&bytes, options).unwrap();
+    /// assert_eq!(reloaded.extension_chunks, [("gAME".to_owned(), b"spawn".to_vec())]);
+    /// ```
+    pub fn extension_chunk(mut self, id: &str) -> Self {
+        self.extension_ids.push(id.to_owned());
+        self
+    }
+
+    /// Limits the total number of voxels of all models, to bound the memory
+    /// used by files from untrusted sources. Files with more voxels fail to
+    /// parse with [`Error::LimitExceeded`] before their voxels are allocated.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let options = ParseOptions::default().max_total_voxels(3);
+    /// let result = load_bytes_with(include_bytes!("resources/placeholder.vox"), options);
+    /// assert!(matches!(result, Err(Error::LimitExceeded(Limit::TotalVoxels))));
+    /// ```
+    pub fn max_total_voxels(mut self, max_total_voxels: usize) -> Self {
+        self.limits.total_voxels = Some(max_total_voxels);
+        self
+    }
+
+    /// Limits the number of scene graph nodes, failing the parse with
+    /// [`Error::LimitExceeded`] for files with more. See
+    /// [`ParseOptions::max_total_voxels`].
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.limits.nodes = Some(max_nodes);
+        self
+    }
+
+    /// Limits the total number of entries of all dictionaries, such as node
+    /// attributes and material properties, failing the parse with
+    /// [`Error::LimitExceeded`] for files with more. See
+    /// [`ParseOptions::max_total_voxels`].
+    pub fn max_dict_entries(mut self, max_dict_entries: usize) -> Self {
+        self.limits.dict_entries = Some(max_dict_entries);
+        self
+    }
+
+    /// Limits how deeply chunks may be nested, failing the parse with
+    /// [`Error::LimitExceeded`] for files nesting them deeper. Chunks are
+    /// decoded recursively, so this bounds the stack used on crafted files.
+    /// Defaults to 64; `MAIN` is at depth 1 and its children at depth 2.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let options = ParseOptions::default().max_depth(0);
+    /// let result = load_bytes_with(include_bytes!("resources/placeholder.vox"), options);
+    /// assert!(matches!(result, Err(Error::LimitExceeded(Limit::Depth))));
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.limits.depth = Some(max_depth);
+        self
+    }
+
+    /// The nesting depth set by [`max_depth`](ParseOptions::max_depth), or
+    /// its default.
+    pub(crate) fn depth_limit(&self) -> usize {
+        self.limits.depth.unwrap_or(DEFAULT_MAX_DEPTH)
+    }
+
+    /// A copy of these options with nothing used of the limits yet, for
+    /// parsing a new file.
+    fn for_new_file(&self) -> ParseOptions {
+        ParseOptions {
+            usage: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Accounts for `amount` more of `limit`, returning whether it is still
+    /// within the limit.
+    fn consume(&self, limit: Limit, amount: usize) -> bool {
+        let (max, used) = match limit {
+            Limit::TotalVoxels => (self.limits.total_voxels, &self.usage.total_voxels),
+            Limit::Nodes => (self.limits.nodes, &self.usage.nodes),
+            Limit::DictEntries => (self.limits.dict_entries, &self.usage.dict_entries),
+            Limit::Depth => unreachable!("depth is checked by check_depth"),
+        };
+        let Some(max) = max else {
+            return true;
+        };
+        let total = used
+            .fetch_add(amount, Ordering::Relaxed)
+            .saturating_add(amount);
+        if total > max {
+            let _ = self.usage.exceeded.set(limit);
+            return false;
+        }
+        true
+    }
+
+    /// Checks the nesting `depth` of a chunk, returning whether it is within
+    /// the limit.
+    fn check_depth(&self, depth: usize) -> bool {
+        if depth <= self.depth_limit() {
+            return true;
+        }
+        let _ = self.usage.exceeded.set(Limit::Depth);
+        false
+    }
+
+    fn exceeded_limit(&self) -> Option<Limit> {
+        self.usage.exceeded.get().copied()
+    }
+
+    /// Whether parsing should stop early.
+    fn should_stop(&self) -> bool {
+        self.is_cancelled() || self.exceeded_limit().is_some()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+}
+
+/// Parses a whole `.vox` file with the default [`ParseOptions`].
 pub fn parse_vox_file(i: &[u8]) -> IResult<&[u8], DotVoxData> {
+    parse_vox_file_with(i, &ParseOptions::default())
+}
+
+/// Parses a whole `.vox` file.
+pub fn parse_vox_file_with<'a>(
+    i: &'a [u8],
+    options: &ParseOptions,
+) -> IResult<&'a [u8], DotVoxData> {
+    let (i, (version, main)) = parse_version_and_main(i, &options.for_new_file())?;
+    Ok((i, map_chunk_to_data(version, main, options)))
+}
+
+/// Parses a `.vox` file, failing on the first invalid chunk in
+/// [`ParseMode::Strict`].
+pub(crate) fn load_vox_file(bytes: &[u8], options: &ParseOptions) -> Result<DotVoxData, Error> {
+    load_vox_file_with_warnings(bytes, options).map(|(data, _)| data)
+}
+
+/// Parses a `.vox` file like [`load_vox_file`], also reporting the chunks
+/// skipped in [`ParseMode::Lenient`] and newer file versions.
+pub(crate) fn load_vox_file_with_warnings(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<(DotVoxData, Vec<Warning>), Error> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("parse", bytes = bytes.len()).entered();
+    let (version, main) = load_version_and_main(bytes, options)?;
+    if options.mode == ParseMode::Strict {
+        if let Chunk::Main(children) = &main {
+            let invalid = children.iter().find_map(|chunk| match chunk {
+                Chunk::Invalid(location) => Some(location),
+                _ => None,
+            });
+            if let Some(location) = invalid {
+                return Err(location.clone().into());
+            }
+        }
+    }
+    let warnings = warnings(bytes, &main);
+    Ok((map_chunk_to_data(version, main, options), warnings))
+}
+
+/// Parses a `.vox` file, keeping everything before the first truncated or
+/// invalid chunk, and reporting the chunks skipped.
+pub(crate) fn load_vox_file_partial(
+    bytes: &[u8],
+    options: &ParseOptions,
+) -> Result<(DotVoxData, Vec<Warning>), Error> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("parse_partial", bytes = bytes.len()).entered();
+    let options = ParseOptions {
+        recover_truncated: true,
+        ..options.clone()
+    };
+    let (version, main) = load_version_and_main(bytes, &options)?;
+    let warnings = warnings(bytes, &main);
+    Ok((map_chunk_to_data(version, main, &options), warnings))
+}
+
+/// The problems found in `bytes` while parsing it into `main`.
+fn warnings(bytes: &[u8], main: &Chunk) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    if let Some(version) = newer_version(bytes) {
+        warnings.push(Warning::NewerVersion(version));
+    }
+    let main_offset = MAGIC_NUMBER.len() + size_of::<u32>();
+    if let Some(warning) = truncation(bytes, main_offset) {
+        warnings.push(warning);
+    }
+    if let Chunk::Main(children) = main {
+        for chunk in children {
+            match chunk {
+                Chunk::Invalid(location) => {
+                    warnings.push(truncation(bytes, location.offset).unwrap_or_else(|| {
+                        Warning::InvalidChunk {
+                            id: location.id.clone(),
+                            offset: location.offset,
+                            path: location.path.clone(),
+                        }
+                    }))
+                }
+                Chunk::TransformNode(transform)
+                    if transform.frames.iter().any(|f| f.contains_key("_s")) =>
+                {
+                    warnings.push(Warning::NonStandardScale {
+                        node_id: transform.header.id,
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+    warnings
+}
+
+/// The version of the file in `bytes`, if it is newer than
+/// [`SUPPORTED_VERSION`].
+fn newer_version(bytes: &[u8]) -> Option<u32> {
+    let version = bytes
+        .strip_prefix(MAGIC_NUMBER.as_bytes())?
+        .first_chunk::<4>()?;
+    Some(u32::from_le_bytes(*version)).filter(|&version| version > SUPPORTED_VERSION)
+}
+
+/// Reports the chunk at `offset` of `bytes` if it extends past the end.
+fn truncation(bytes: &[u8], offset: usize) -> Option<Warning> {
+    let header = bytes.get(offset..)?;
+    let id = String::from_utf8_lossy(&header[..header.len().min(4)]).into_owned();
+    let declared = match header {
+        [_, _, _, _, c0, c1, c2, c3, n0, n1, n2, n3, ..] => {
+            let content = u32::from_le_bytes([*c0, *c1, *c2, *c3]) as usize;
+            let children = u32::from_le_bytes([*n0, *n1, *n2, *n3]) as usize;
+            Some(12usize.saturating_add(content).saturating_add(children))
+        }
+        _ => None,
+    };
+    if declared.is_some_and(|declared| declared <= header.len()) {
+        return None;
+    }
+    Some(Warning::Truncated {
+        id,
+        offset,
+        declared,
+        available: header.len(),
+    })
+}
+
+fn parse_version_and_main<'a>(
+    i: &'a [u8],
+    options: &ParseOptions,
+) -> IResult<&'a [u8], (u32, Chunk)> {
+    let file = i;
     let (i, _) = tag(MAGIC_NUMBER)(i)?;
     let (i, version) = le_u32(i)?;
-    let (i, main) = parse_chunk(i)?;
-    Ok((i, map_chunk_to_data(version, main)))
+    let (i, main) = parse_chunk(i, file, &[], options)?;
+    if options.should_stop() {
+        return Err(nom::Err::Failure(make_error(i, ErrorKind::Verify)));
+    }
+    Ok((i, (version, main)))
+}
+
+/// Parses the header and `MAIN` chunk of `bytes`, converting errors.
+fn load_version_and_main(bytes: &[u8], options: &ParseOptions) -> Result<(u32, Chunk), Error> {
+    if let Some(version) = newer_version(bytes) {
+        if options.reject_newer_versions {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        event!(
+            warn,
+            { version = version, supported = SUPPORTED_VERSION },
+            "File version is newer than supported, some content may be lost"
+        );
+    }
+    let options = options.for_new_file();
+    match parse_version_and_main(bytes, &options) {
+        Ok((_, result)) => Ok(result),
+        Err(_) if options.is_cancelled() => Err(Error::Cancelled),
+        Err(error) => match options.exceeded_limit() {
+            Some(limit) => Err(Error::LimitExceeded(limit)),
+            None => Err(Error::from_nom(bytes, error)),
+        },
+    }
 }
 
-fn map_chunk_to_data(version: u32, main: Chunk) -> DotVoxData {
+pub(crate) fn map_chunk_to_data(version: u32, main: Chunk, options: &ParseOptions) -> DotVoxData {
     match main {
         Chunk::Main(children) => {
             let mut size_holder: Option<Size> = None;
@@ -205,18 +1013,53 @@ fn map_chunk_to_data(version: u32, main: Chunk) -> DotVoxData {
             let mut materials: Vec<Material> = vec![];
             let mut scene: Vec<SceneNode> = vec![];
             let mut layers: Vec<Layer> = Vec::new();
+            let mut palette_extension = None;
+            let mut index_map = None;
+            let mut render_objects = Vec::new();
+            let mut cameras = Vec::new();
+            let mut extension_chunks = Vec::new();
+            let mut unknown_chunks = Vec::new();
+            let mut chunk_order = Vec::new();
 
             for chunk in children {
+                let kind = chunk.kind();
                 match chunk {
                     Chunk::Size(size) => size_holder = Some(size),
                     Chunk::Voxels(voxels) => {
                         if let Some(size) = size_holder {
-                            models.push(Model { size, voxels })
+                            let mut model = Model { size, voxels };
+                            if options.surface_only {
+                                surface::strip_interior(&mut model);
+                            }
+                            models.push(model);
+                            chunk_order.push(ChunkKind::Models);
                         }
                     }
                     Chunk::Palette(palette) => palette_holder = palette,
+                    Chunk::PaletteExtension(extension) => palette_extension = Some(extension),
+                    Chunk::IndexMap(map) => index_map = Some(map),
+                    Chunk::RenderObject(dict) => render_objects.push(dict),
+                    Chunk::Camera(camera) => cameras.push(camera),
+                    Chunk::Unknown(chunk)
+                        if chunk.children.is_empty()
+                            && options.extension_ids.contains(&chunk.id) =>
+                    {
+                        extension_chunks.push((chunk.id, chunk.content));
+                        chunk_order.push(ChunkKind::Extensions);
+                    }
+                    Chunk::Unknown(chunk) => {
+                        unknown_chunks.push(chunk);
+                        chunk_order.push(ChunkKind::Unknown);
+                    }
                     Chunk::Material(material) => materials.push(material),
                     Chunk::TransformNode(scene_transform) => {
+                        if scene_transform.frames.iter().any(|f| f.contains_key("_s")) {
+                            event!(
+                                warn,
+                                { node_id = scene_transform.header.id },
+                                "Transform node uses the non-standard '_s' scale attribute"
+                            );
+                        }
                         scene.push(SceneNode::Transform {
                             attributes: scene_transform.header.attributes,
                             frames: scene_transform.frames.into_iter().map(Frame::new).collect(),
@@ -236,9 +1079,10 @@ fn map_chunk_to_data(version: u32, main: Chunk) -> DotVoxData {
                         if layer.id as usize != layers.len() {
                             // Not sure if this actually happens in practice, but nothing in the
                             // spec prohibits it.
-                            debug!(
-                                "Unexpected layer id {} encountered, layers may be out of order.",
-                                layer.id
+                            event!(
+                                debug,
+                                { layer_id = layer.id, expected = layers.len() },
+                                "Unexpected layer id encountered, layers may be out of order"
                             );
                         }
 
@@ -246,18 +1090,35 @@ fn map_chunk_to_data(version: u32, main: Chunk) -> DotVoxData {
                             attributes: layer.attributes,
                         });
                     }
+                    // The number of models is implied by the `SIZE` and `XYZI`
+                    // chunks, and written on request by `WriteOptions::pack`.
+                    Chunk::Pack(_) | Chunk::Skipped => {}
                     _ => debug!("Unmapped chunk {:?}", chunk),
                 }
+                chunk_order.extend(kind);
             }
 
-            DotVoxData {
+            let mut data = DotVoxData {
                 version,
                 models,
                 palette: palette_holder,
                 materials,
                 scenes: scene,
+                synthetic_scene: false,
                 layers,
+                palette_extension,
+                index_map,
+                render_objects,
+                cameras,
+                extension_chunks,
+                unknown_chunks,
+                chunk_order: ChunkKind::shortest(chunk_order),
+            };
+            if !options.skip_scene_synthesis && !options.skipped_sections.contains(Sections::SCENES)
+            {
+                data.synthesize_scene();
             }
+            data
         }
         _ => DotVoxData {
             version,
@@ -265,135 +1126,462 @@ fn map_chunk_to_data(version: u32, main: Chunk) -> DotVoxData {
             palette: vec![],
             materials: vec![],
             scenes: vec![],
+            synthetic_scene: false,
             layers: vec![],
+            palette_extension: None,
+            index_map: None,
+            render_objects: vec![],
+            cameras: vec![],
+            extension_chunks: vec![],
+            unknown_chunks: vec![],
+            chunk_order: vec![],
         },
     }
 }
 
-fn parse_chunk(i: &[u8]) -> IResult<&[u8], Chunk> {
+/// Parses a chunk of `file` starting at `i`, within the chunks whose ids are
+/// listed in `path`.
+fn parse_chunk<'a>(
+    i: &'a [u8],
+    file: &[u8],
+    path: &[String],
+    options: &ParseOptions,
+) -> IResult<&'a [u8], Chunk> {
+    let (i, parts) = split_chunk(i, file, options)?;
+    Ok((i, decode_chunk(&parts, file, path, options)))
+}
+
+/// The header and content of a chunk, before decoding.
+struct ChunkParts<'a> {
+    offset: usize,
+    id: &'a str,
+    content: &'a [u8],
+    children_size: u32,
+    children: &'a [u8],
+}
+
+impl ChunkParts<'_> {
+    /// Number of bytes taken by the chunk in the file.
+    fn len(&self) -> usize {
+        12 + self.content.len() + self.children.len()
+    }
+}
+
+/// Splits the chunk of `file` starting at `i` into its parts, without
+/// decoding its content.
+fn split_chunk<'a>(
+    i: &'a [u8],
+    file: &[u8],
+    options: &ParseOptions,
+) -> IResult<&'a [u8], ChunkParts<'a>> {
+    let offset = file.offset(i);
     let (i, id) = map_res(take(4usize), str::from_utf8)(i)?;
     let (i, (content_size, children_size)) = pair(le_u32, le_u32)(i)?;
-    let (i, chunk_content) = take(content_size)(i)?;
-    let (i, child_content) = take(children_size)(i)?;
-    let chunk = build_chunk(id, chunk_content, children_size, child_content);
-    Ok((i, chunk))
+    let (i, content, children) = if options.recover_truncated && id == "MAIN" {
+        // Keep whatever part of the children is present.
+        let (content, i) = i.split_at(i.len().min(content_size as usize));
+        let (children, i) = i.split_at(i.len().min(children_size as usize));
+        (i, content, children)
+    } else {
+        let (i, content) = take(content_size)(i)?;
+        let (i, children) = take(children_size)(i)?;
+        (i, content, children)
+    };
+    let parts = ChunkParts {
+        offset,
+        id,
+        content,
+        children_size,
+        children,
+    };
+    Ok((i, parts))
+}
+
+/// Decodes a chunk split by [`split_chunk`], within the chunks whose ids are
+/// listed in `path`.
+fn decode_chunk(parts: &ChunkParts, file: &[u8], path: &[String], options: &ParseOptions) -> Chunk {
+    build_chunk(
+        parts.id,
+        parts.content,
+        parts.children_size,
+        parts.children,
+        file,
+        path,
+        options,
+    )
+    .unwrap_or_else(|| {
+        let location = ChunkLocation {
+            id: parts.id.to_owned(),
+            offset: parts.offset,
+            path: path.to_vec(),
+        };
+        event!(
+            warn,
+            {
+                chunk_id = location.id,
+                offset = location.offset,
+                path = location.path.join(" > "),
+            },
+            "Unable to parse chunk"
+        );
+        Chunk::Invalid(location)
+    })
+}
+
+/// Decodes a chunk within `MAIN` that is not `MAIN` itself.
+pub(crate) fn decode_child(
+    id: &str,
+    content: &[u8],
+    children: &[u8],
+    options: &ParseOptions,
+) -> Option<Chunk> {
+    build_chunk(
+        id,
+        content,
+        children.len() as u32,
+        children,
+        &[],
+        &[],
+        options,
+    )
 }
 
-fn build_chunk(id: &str, chunk_content: &[u8], children_size: u32, child_content: &[u8]) -> Chunk {
+fn build_chunk(
+    id: &str,
+    chunk_content: &[u8],
+    children_size: u32,
+    child_content: &[u8],
+    file: &[u8],
+    path: &[String],
+    options: &ParseOptions,
+) -> Option<Chunk> {
+    if !options.check_depth(path.len() + 1) {
+        return None;
+    }
+    if id != "MAIN" && options.skipped_sections.contains(Sections::of(id)) {
+        return Some(Chunk::Skipped);
+    }
     if children_size == 0 {
         match id {
-            "SIZE" => build_size_chunk(chunk_content),
-            "XYZI" => build_voxel_chunk(chunk_content),
+            "SIZE" => build_size_chunk(chunk_content, options),
+            "XYZI" => build_voxel_chunk(chunk_content, options),
             "RGBA" => build_palette_chunk(chunk_content),
-            "MATL" => build_material_chunk(chunk_content),
-            "nTRN" => build_scene_transform_chunk(chunk_content),
-            "nGRP" => build_scene_group_chunk(chunk_content),
-            "nSHP" => build_scene_shape_chunk(chunk_content),
-            "LAYR" => build_layer_chunk(chunk_content),
+            "XPAL" => build_palette_extension_chunk(chunk_content),
+            "IMAP" => build_index_map_chunk(chunk_content),
+            "PACK" => build_pack_chunk(chunk_content),
+            "MATL" => build_material_chunk(chunk_content, options),
+            "MATT" => build_legacy_material_chunk(chunk_content),
+            "nTRN" => build_scene_transform_chunk(chunk_content, options),
+            "nGRP" => build_scene_group_chunk(chunk_content, options),
+            "nSHP" => build_scene_shape_chunk(chunk_content, options),
+            "LAYR" => build_layer_chunk(chunk_content, options),
+            "rOBJ" => build_render_object_chunk(chunk_content, options),
+            "rCAM" => build_camera_chunk(chunk_content, options),
             _ => {
-                debug!("Unknown childless chunk {:?}", id);
-                Chunk::Unknown(id.to_owned())
+                event!(debug, { chunk_id = id }, "Unknown childless chunk");
+                Some(build_unknown_chunk(id, chunk_content, child_content))
             }
         }
+    } else if id != "MAIN" {
+        event!(debug, { chunk_id = id }, "Unknown chunk with children");
+        Some(build_unknown_chunk(id, chunk_content, child_content))
     } else {
-        let result: IResult<&[u8], Vec<Chunk>> = many0(parse_chunk)(child_content);
-        let child_chunks = match result {
-            Ok((_, result)) => result,
-            result => {
-                debug!("Failed to parse child chunks, due to {:?}", result);
-                vec![]
+        let path = [path, &[id.to_owned()]].concat();
+        // Find the boundaries of every child first, which is cheap, so that
+        // they can be decoded independently.
+        let result: IResult<&[u8], Vec<ChunkParts>> = many0(|i| {
+            if options.should_stop() {
+                return Err(nom::Err::Failure(make_error(i, ErrorKind::Verify)));
             }
+            split_chunk(i, file, options)
+        })(child_content);
+        let (rest, children) = match result {
+            Ok(result) => result,
+            Err(_) => (child_content, vec![]),
         };
-        match id {
-            "MAIN" => Chunk::Main(child_chunks),
-            _ => {
-                debug!("Unknown chunk with children {:?}", id);
-                Chunk::Unknown(id.to_owned())
+
+        let done = Mutex::new(file.offset(child_content));
+        #[cfg(feature = "tracing")]
+        let parent = tracing::Span::current();
+        let decode = |parts: &ChunkParts| {
+            if options.should_stop() {
+                // The result is discarded anyway.
+                return Chunk::Main(Vec::new());
+            }
+            // Chunks may be decoded on other threads, which do not inherit the
+            // current span.
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!(parent: &parent, "chunk", id = parts.id, offset = parts.offset)
+                .entered();
+            let chunk = decode_chunk(parts, file, &path, options);
+            if let Some(Progress(progress)) = &options.progress {
+                let mut done = done.lock().unwrap_or_else(PoisonError::into_inner);
+                *done += parts.len();
+                progress(*done, file.len());
             }
+            chunk
+        };
+        #[cfg(feature = "rayon")]
+        let mut child_chunks: Vec<Chunk> = children.par_iter().map(decode).collect();
+        #[cfg(not(feature = "rayon"))]
+        let mut child_chunks: Vec<Chunk> = children.iter().map(decode).collect();
+        if !rest.is_empty() {
+            // The remaining children are truncated or corrupt.
+            let location = ChunkLocation {
+                id: String::from_utf8_lossy(&rest[..rest.len().min(4)]).into_owned(),
+                offset: file.offset(rest),
+                path,
+            };
+            event!(
+                warn,
+                {
+                    chunk_id = location.id,
+                    offset = location.offset,
+                    path = location.path.join(" > "),
+                },
+                "Unable to parse truncated or corrupt chunk"
+            );
+            child_chunks.push(Chunk::Invalid(location));
         }
+        Some(Chunk::Main(child_chunks))
+    }
+}
+
+fn build_unknown_chunk(id: &str, chunk_content: &[u8], child_content: &[u8]) -> Chunk {
+    Chunk::Unknown(RawChunk {
+        id: id.to_owned(),
+        content: chunk_content.to_vec(),
+        children: child_content.to_vec(),
+    })
+}
+
+fn build_material_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    if let Ok((_, material)) = parse_material(chunk_content, options) {
+        return Some(Chunk::Material(material));
     }
+    None
 }
 
-fn build_material_chunk(chunk_content: &[u8]) -> Chunk {
-    if let Ok((_, material)) = parse_material(chunk_content) {
-        return Chunk::Material(material);
+fn build_legacy_material_chunk(chunk_content: &[u8]) -> Option<Chunk> {
+    match parse_legacy_material(chunk_content) {
+        Ok((_, material)) => Some(Chunk::Material(material)),
+        _ => None,
     }
-    Chunk::Invalid(chunk_content.to_vec())
 }
 
-fn build_palette_chunk(chunk_content: &[u8]) -> Chunk {
+fn build_palette_chunk(chunk_content: &[u8]) -> Option<Chunk> {
     if let Ok((_, palette)) = palette::extract_palette(chunk_content) {
-        return Chunk::Palette(palette);
+        return Some(Chunk::Palette(palette));
     }
-    Chunk::Invalid(chunk_content.to_vec())
+    None
 }
 
-fn build_size_chunk(chunk_content: &[u8]) -> Chunk {
-    match model::parse_size(chunk_content) {
-        Ok((_, size)) => Chunk::Size(size),
-        _ => Chunk::Invalid(chunk_content.to_vec()),
+fn build_palette_extension_chunk(chunk_content: &[u8]) -> Option<Chunk> {
+    match palette::parse_palette_extension(chunk_content) {
+        Ok((_, extension)) => Some(Chunk::PaletteExtension(extension)),
+        _ => None,
     }
 }
 
-fn build_voxel_chunk(chunk_content: &[u8]) -> Chunk {
+fn build_pack_chunk(chunk_content: &[u8]) -> Option<Chunk> {
+    let (_, count) = le_u32::<_, nom::error::Error<&[u8]>>(chunk_content).ok()?;
+    Some(Chunk::Pack(count))
+}
+
+fn build_index_map_chunk(chunk_content: &[u8]) -> Option<Chunk> {
+    match palette::extract_index_map(chunk_content) {
+        Ok((_, index_map)) => Some(Chunk::IndexMap(index_map)),
+        _ => None,
+    }
+}
+
+fn build_size_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    let (_, size) = model::parse_size(chunk_content).ok()?;
+    if size.is_within_limits() {
+        return Some(Chunk::Size(size));
+    }
+    match options.mode {
+        ParseMode::Strict => None,
+        ParseMode::Lenient => {
+            event!(
+                warn,
+                { x = size.x, y = size.y, z = size.z },
+                "Model size exceeds the voxel coordinate range"
+            );
+            Some(Chunk::Size(size.clamped()))
+        }
+    }
+}
+
+fn build_voxel_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    let (_, count) = le_u32::<_, nom::error::Error<_>>(chunk_content).ok()?;
+    if !options.consume(Limit::TotalVoxels, count as usize) {
+        return None;
+    }
     match model::parse_voxels(chunk_content) {
-        Ok((_, voxels)) => Chunk::Voxels(voxels),
-        _ => Chunk::Invalid(chunk_content.to_vec()),
+        Ok((_, voxels)) => Some(Chunk::Voxels(voxels)),
+        _ => None,
+    }
+}
+
+fn build_scene_transform_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    if !options.consume(Limit::Nodes, 1) {
+        return None;
+    }
+    match scene::parse_scene_transform(chunk_content, options) {
+        Ok((_, transform_node)) => Some(Chunk::TransformNode(transform_node)),
+        _ => None,
+    }
+}
+
+fn build_scene_group_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    if !options.consume(Limit::Nodes, 1) {
+        return None;
+    }
+    match scene::parse_scene_group(chunk_content, options) {
+        Ok((_, group_node)) => Some(Chunk::GroupNode(group_node)),
+        _ => None,
     }
 }
 
-fn build_scene_transform_chunk(chunk_content: &[u8]) -> Chunk {
-    match scene::parse_scene_transform(chunk_content) {
-        Ok((_, transform_node)) => Chunk::TransformNode(transform_node),
-        _ => Chunk::Invalid(chunk_content.to_vec()),
+fn build_scene_shape_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    if !options.consume(Limit::Nodes, 1) {
+        return None;
+    }
+    match scene::parse_scene_shape(chunk_content, options) {
+        Ok((_, shape_node)) => Some(Chunk::ShapeNode(shape_node)),
+        _ => None,
     }
 }
 
-fn build_scene_group_chunk(chunk_content: &[u8]) -> Chunk {
-    match scene::parse_scene_group(chunk_content) {
-        Ok((_, group_node)) => Chunk::GroupNode(group_node),
-        _ => Chunk::Invalid(chunk_content.to_vec()),
+fn build_render_object_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    match parse_dict(chunk_content, options) {
+        Ok((_, dict)) => Some(Chunk::RenderObject(dict)),
+        _ => None,
     }
 }
 
-fn build_scene_shape_chunk(chunk_content: &[u8]) -> Chunk {
-    match scene::parse_scene_shape(chunk_content) {
-        Ok((_, shape_node)) => Chunk::ShapeNode(shape_node),
-        _ => Chunk::Invalid(chunk_content.to_vec()),
+fn build_camera_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    match camera::parse_camera(chunk_content, options) {
+        Ok((_, camera)) => Some(Chunk::Camera(camera)),
+        _ => None,
     }
 }
 
-fn build_layer_chunk(chunk_content: &[u8]) -> Chunk {
-    match scene::parse_layer(chunk_content) {
-        Ok((_, layer)) => Chunk::Layer(layer),
-        _ => Chunk::Invalid(chunk_content.to_vec()),
+fn build_layer_chunk(chunk_content: &[u8], options: &ParseOptions) -> Option<Chunk> {
+    match scene::parse_layer(chunk_content, options) {
+        Ok((_, layer)) => Some(Chunk::Layer(layer)),
+        _ => None,
     }
 }
 
-pub fn parse_material(i: &[u8]) -> IResult<&[u8], Material> {
-    let (i, (id, properties)) = pair(le_u32, parse_dict)(i)?;
+/// Parses the content of a `MATL` chunk.
+pub fn parse_material<'a>(i: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], Material> {
+    let (i, (id, properties)) = pair(le_u32, |i| parse_dict(i, options))(i)?;
     Ok((i, Material { id, properties }))
 }
 
-pub(crate) fn parse_dict(i: &[u8]) -> IResult<&[u8], Dict> {
-    let (i, n) = le_u32(i)?;
-    let n = validate_count(i, n, size_of::<u32>() * 2)?;
+/// Properties of the legacy `MATT` chunk, in the order of their bits, with
+/// the `MATL` keys they are converted to.
+const LEGACY_MATERIAL_PROPERTIES: [&str; 7] = [
+    "_plastic", "_rough", "_spec", "_ior", "_att", "_flux", "_glow",
+];
 
-    let init = move || Dict::with_capacity(n);
-    let fold = |mut map: Dict, (key, value)| {
-        map.insert(key, value);
-        map
+/// Parses the content of a `MATT` chunk, written by MagicaVoxel before 0.99,
+/// converting it to the `MATL` representation.
+pub fn parse_legacy_material(i: &[u8]) -> IResult<&[u8], Material> {
+    let (i, (id, material_type, weight, property_bits)) =
+        tuple((le_u32, le_u32, le_f32, le_u32))(i)?;
+    let material_type = match material_type {
+        0 => "_diffuse",
+        1 => "_metal",
+        2 => "_glass",
+        3 => "_emit",
+        _ => {
+            return Err(nom::Err::Error(make_error(
+                i,
+                nom::error::ErrorKind::Switch,
+            )))
+        }
     };
-    fold_many_m_n(n, n, parse_dict_entry, init, fold)(i)
+
+    let mut properties = Dict::new();
+    properties.insert("_type".to_owned(), material_type.to_owned());
+    properties.insert("_weight".to_owned(), weight.to_string());
+    let mut i = i;
+    for (bit, key) in LEGACY_MATERIAL_PROPERTIES.iter().enumerate() {
+        if property_bits & (1 << bit) != 0 {
+            let (rest, value) = le_f32(i)?;
+            properties.insert((*key).to_owned(), value.to_string());
+            i = rest;
+        }
+    }
+    // Bit 7 flags `_flux` as the total power, and carries no value.
+    if property_bits & (1 << 7) != 0 {
+        properties.insert("_total_power".to_owned(), "1".to_owned());
+    }
+
+    Ok((i, Material { id, properties }))
+}
+
+/// Parses a `DICT`: a count followed by that many key and value strings.
+pub fn parse_dict<'a>(i: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], Dict> {
+    let (mut i, n) = le_u32(i)?;
+    let n = validate_count(i, n, size_of::<u32>() * 2)?;
+    if !options.consume(Limit::DictEntries, n) {
+        return Err(nom::Err::Failure(make_error(i, ErrorKind::TooLarge)));
+    }
+
+    let mut map = Dict::with_capacity(n);
+    for _ in 0..n {
+        let (rest, (key, value)) = parse_dict_entry(i, options)?;
+        match map.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+            Entry::Occupied(mut entry) => match options.duplicate_keys {
+                DuplicateKeys::LastWins => {
+                    entry.insert(value);
+                }
+                DuplicateKeys::FirstWins => {}
+                DuplicateKeys::Reject => {
+                    event!(debug, { key = entry.key() }, "Duplicate dictionary key");
+                    return Err(nom::Err::Error(make_error(i, ErrorKind::Verify)));
+                }
+            },
+        }
+        i = rest;
+    }
+    Ok((i, map))
+}
+
+/// Parses a dictionary as a list of its entries in file order, keeping keys
+/// found more than once.
+pub fn parse_dict_entries<'a>(
+    i: &'a [u8],
+    options: &ParseOptions,
+) -> IResult<&'a [u8], Vec<(String, String)>> {
+    let (i, n) = le_u32(i)?;
+    let n = validate_count(i, n, size_of::<u32>() * 2)?;
+    if !options.consume(Limit::DictEntries, n) {
+        return Err(nom::Err::Failure(make_error(i, ErrorKind::TooLarge)));
+    }
+    count(|i| parse_dict_entry(i, options), n)(i)
 }
 
-fn parse_dict_entry(i: &[u8]) -> IResult<&[u8], (String, String)> {
-    pair(parse_string, parse_string)(i)
+fn parse_dict_entry<'a>(
+    i: &'a [u8],
+    options: &ParseOptions,
+) -> IResult<&'a [u8], (String, String)> {
+    pair(|i| parse_string(i, options), |i| parse_string(i, options))(i)
 }
 
-fn parse_string(i: &[u8]) -> IResult<&[u8], String> {
+fn parse_string<'a>(i: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], String> {
     let bytes = flat_map(le_u32, take);
-    map_res(bytes, to_str)(i)
+    match options.mode {
+        ParseMode::Strict => map_res(bytes, to_str)(i),
+        ParseMode::Lenient => map(bytes, to_str_lossy)(i),
+    }
 }
 
 /// Validate that a given count of items is possible to achieve given the size
@@ -436,7 +1624,7 @@ mod tests {
     #[test]
     fn can_parse_size_chunk() {
         let bytes = include_bytes!("resources/valid_size.bytes").to_vec();
-        let result = parse_chunk(&bytes);
+        let result = parse_chunk(&bytes, &bytes, &[], &ParseOptions::default());
         assert!(result.is_ok());
         let (_, size) = result.unwrap();
         assert_eq!(
@@ -452,7 +1640,7 @@ mod tests {
     #[test]
     fn can_parse_voxels_chunk() {
         let bytes = include_bytes!("resources/valid_voxels.bytes").to_vec();
-        let result = parse_chunk(&bytes);
+        let result = parse_chunk(&bytes, &bytes, &[], &ParseOptions::default());
         assert!(result.is_ok());
         let (_, voxels) = result.unwrap();
         match voxels {
@@ -492,7 +1680,7 @@ mod tests {
     #[test]
     fn can_parse_palette_chunk() {
         let bytes = include_bytes!("resources/valid_palette.bytes").to_vec();
-        let result = parse_chunk(&bytes);
+        let result = parse_chunk(&bytes, &bytes, &[], &ParseOptions::default());
         assert!(result.is_ok());
         let (_, palette) = result.unwrap();
         match palette {
@@ -504,7 +1692,7 @@ mod tests {
     #[test]
     fn can_parse_a_material_chunk() {
         let bytes = include_bytes!("resources/valid_material.bytes").to_vec();
-        let result = parse_material(&bytes);
+        let result = parse_material(&bytes, &ParseOptions::default());
         match result {
             Ok((_, material)) => {
                 assert_eq!(material.id, 0);
@@ -520,4 +1708,432 @@ mod tests {
             _ => panic!("Expected Done, got {:?}", result),
         }
     }
+
+    fn latin1_layer_chunk() -> Vec<u8> {
+        let mut content = Vec::new();
+        content.extend_from_slice(&0u32.to_le_bytes());
+        content.extend_from_slice(&1u32.to_le_bytes());
+        for string in [&b"_name"[..], &b"Caf\xe9"[..]] {
+            content.extend_from_slice(&(string.len() as u32).to_le_bytes());
+            content.extend_from_slice(string);
+        }
+        content.extend_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut chunk = b"LAYR".to_vec();
+        chunk.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(&0u32.to_le_bytes());
+        chunk.extend_from_slice(&content);
+        chunk
+    }
+
+    #[test]
+    fn lenient_mode_replaces_invalid_utf8() {
+        let bytes = latin1_layer_chunk();
+        let (_, chunk) = parse_chunk(&bytes, &bytes, &[], &ParseOptions::default()).unwrap();
+        match chunk {
+            Chunk::Layer(layer) => {
+                assert_eq!(
+                    layer.attributes.get("_name"),
+                    Some(&"Caf\u{FFFD}".to_owned())
+                )
+            }
+            chunk => panic!("Expecting Layer chunk, got {:?}", chunk),
+        }
+    }
+
+    #[test]
+    fn strict_mode_rejects_invalid_utf8() {
+        let bytes = latin1_layer_chunk();
+        let options = ParseOptions::default().mode(ParseMode::Strict);
+        let (_, chunk) = parse_chunk(&bytes, &bytes, &[], &options).unwrap();
+        assert!(matches!(chunk, Chunk::Invalid(_)));
+    }
+
+    #[test]
+    fn unknown_chunks_are_written_back() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.unknown_chunks.push(RawChunk {
+            id: "PARN".to_owned(),
+            content: vec![1, 2, 3],
+            children: b"CHLD\x01\x00\x00\x00\x00\x00\x00\x00\x04".to_vec(),
+        });
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+        let reloaded = crate::load_bytes(&bytes).unwrap();
+        assert_eq!(reloaded.unknown_chunks, data.unknown_chunks);
+        assert_eq!(reloaded.unknown_chunks.len(), 7);
+    }
+
+    #[test]
+    fn extension_chunks_are_collected_for_registered_ids() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.extension_chunks.push(("gAME".to_owned(), vec![1, 2]));
+        data.extension_chunks.push(("gAME".to_owned(), vec![3]));
+        data.unknown_chunks.push(RawChunk {
+            id: "oTHR".to_owned(),
+            content: vec![4],
+            children: vec![],
+        });
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+
+        let options = ParseOptions::default().extension_chunk("gAME");
+        let reloaded = crate::load_bytes_with(&bytes, options).unwrap();
+        assert_eq!(reloaded, data);
+
+        // Without registering the id, they are unknown chunks.
+        let reloaded = crate::load_bytes(&bytes).unwrap();
+        assert!(reloaded.extension_chunks.is_empty());
+        assert_eq!(reloaded.unknown_chunks.len(), data.unknown_chunks.len() + 2);
+
+        data.extension_chunks.push(("toolong".to_owned(), vec![]));
+        assert!(data.write_vox(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn legacy_material_is_converted() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&12u32.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&0.5f32.to_le_bytes());
+        bytes.extend_from_slice(&0b1000_0010u32.to_le_bytes());
+        bytes.extend_from_slice(&0.25f32.to_le_bytes());
+        let chunk = build_legacy_material_chunk(&bytes);
+
+        let Some(Chunk::Material(material)) = chunk else {
+            panic!("Expected a material, got {:?}", chunk);
+        };
+        assert_eq!(material.id, 12);
+        assert_eq!(material.material_type(), Some("_metal"));
+        assert_eq!(material.weight(), Some(0.5));
+        assert_eq!(material.roughness(), Some(0.25));
+        assert_eq!(material.properties["_total_power"], "1");
+        assert_eq!(material.properties.len(), 4);
+
+        bytes.truncate(bytes.len() - 4);
+        assert!(build_legacy_material_chunk(&bytes).is_none());
+    }
+
+    #[test]
+    fn invalid_chunks_record_their_location() {
+        let mut children = Vec::new();
+        children.extend_from_slice(b"nTRN\x02\x00\x00\x00\x00\x00\x00\x00\x01\x02");
+        children.extend_from_slice(b"SIZ");
+        let mut bytes = b"MAIN\x00\x00\x00\x00".to_vec();
+        bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&children);
+
+        let (_, chunk) = parse_chunk(&bytes, &bytes, &[], &ParseOptions::default()).unwrap();
+        let Chunk::Main(children) = chunk else {
+            panic!("Expected a MAIN chunk, got {:?}", chunk);
+        };
+        let locations: Vec<ChunkLocation> = children
+            .into_iter()
+            .filter_map(|chunk| match chunk {
+                Chunk::Invalid(location) => Some(location),
+                _ => None,
+            })
+            .collect();
+        let main = vec!["MAIN".to_owned()];
+        assert_eq!(
+            locations,
+            [
+                ChunkLocation {
+                    id: "nTRN".to_owned(),
+                    offset: 12,
+                    path: main.clone(),
+                },
+                ChunkLocation {
+                    id: "SIZ".to_owned(),
+                    offset: 26,
+                    path: main,
+                },
+            ]
+        );
+        assert_eq!(
+            locations[0].to_string(),
+            "\"nTRN\" chunk at offset 12 (MAIN > nTRN)"
+        );
+    }
+
+    #[test]
+    fn strict_mode_fails_on_invalid_chunks() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        // Corrupt the dictionary of the first nTRN chunk by claiming more
+        // entries than it holds.
+        let offset = bytes.windows(4).position(|id| id == b"nTRN").unwrap();
+        let mut bytes = bytes.to_vec();
+        bytes[offset + 16] = 0xff;
+
+        let lenient = crate::load_bytes(&bytes).unwrap();
+        assert_eq!(lenient.models.len(), 1);
+
+        let strict = ParseOptions::default().mode(ParseMode::Strict);
+        match crate::load_bytes_with(&bytes, strict) {
+            Err(Error::InvalidChunk {
+                id,
+                offset: at,
+                path,
+            }) => {
+                assert_eq!(id, "nTRN");
+                assert_eq!(at, offset);
+                assert_eq!(path, ["MAIN"]);
+            }
+            result => panic!("Expected an invalid chunk error, got {:?}", result),
+        }
+
+        let valid = include_bytes!("resources/placeholder.vox");
+        assert!(
+            crate::load_bytes_with(valid, ParseOptions::default().mode(ParseMode::Strict)).is_ok()
+        );
+    }
+
+    #[test]
+    fn oversized_models_are_clamped_or_rejected() {
+        let mut content = Vec::new();
+        for axis in [1000u32, 256, 3] {
+            content.extend_from_slice(&axis.to_le_bytes());
+        }
+        let lenient = build_size_chunk(&content, &ParseOptions::default());
+        assert_eq!(
+            lenient,
+            Some(Chunk::Size(Size {
+                x: 256,
+                y: 256,
+                z: 3
+            }))
+        );
+        let strict = ParseOptions::default().mode(ParseMode::Strict);
+        assert_eq!(build_size_chunk(&content, &strict), None);
+
+        content[..4].copy_from_slice(&256u32.to_le_bytes());
+        assert_eq!(
+            build_size_chunk(&content, &strict),
+            Some(Chunk::Size(Size {
+                x: 256,
+                y: 256,
+                z: 3
+            }))
+        );
+        assert!(!Size::extended_sizes_supported());
+    }
+
+    #[test]
+    fn progress_is_reported_after_each_chunk() {
+        let bytes = include_bytes!("resources/placeholder-with-materials.vox");
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = reports.clone();
+        let options = ParseOptions::default().progress(move |done, total| {
+            recorded.lock().unwrap().push((done, total));
+        });
+        let (_, (_, main)) = parse_version_and_main(bytes, &options).unwrap();
+
+        let reports = reports.lock().unwrap();
+        let Chunk::Main(children) = main else {
+            panic!("Expected MAIN");
+        };
+        assert_eq!(reports.len(), children.len());
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(reports.iter().all(|&(_, total)| total == bytes.len()));
+        assert_eq!(reports.last().map(|&(done, _)| done), Some(bytes.len()));
+    }
+
+    #[test]
+    fn cancelling_stops_parsing_between_chunks() {
+        let bytes = include_bytes!("resources/placeholder-with-materials.vox");
+        let cancel = Arc::new(AtomicBool::new(false));
+        let chunks = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let (flag, parsed) = (cancel.clone(), chunks.clone());
+        let options = ParseOptions::default()
+            .cancel_flag(cancel)
+            .progress(move |_, _| {
+                parsed.fetch_add(1, Ordering::Relaxed);
+                flag.store(true, Ordering::Relaxed);
+            });
+        assert!(matches!(
+            crate::load_bytes_with(bytes, options.clone()),
+            Err(Error::Cancelled)
+        ));
+        // Chunks already being decoded on other threads are finished.
+        #[cfg(not(feature = "rayon"))]
+        assert_eq!(chunks.load(Ordering::Relaxed), 1);
+        assert!(matches!(
+            crate::load_bytes_partial(bytes, options.clone()),
+            Err(Error::Cancelled)
+        ));
+        assert!(parse_vox_file_with(bytes, &options).is_err());
+    }
+
+    #[test]
+    fn duplicate_keys_follow_the_policy() {
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        for value in ["1 2 3", "4 5 6"] {
+            for string in ["_t", value] {
+                bytes.extend((string.len() as u32).to_le_bytes());
+                bytes.extend(string.as_bytes());
+            }
+        }
+        let parse = |duplicate_keys| {
+            let options = ParseOptions::default().duplicate_keys(duplicate_keys);
+            parse_dict(&bytes, &options).map(|(_, dict)| dict["_t"].clone())
+        };
+        assert_eq!(parse(DuplicateKeys::LastWins).unwrap(), "4 5 6");
+        assert_eq!(parse(DuplicateKeys::FirstWins).unwrap(), "1 2 3");
+        assert!(parse(DuplicateKeys::Reject).is_err());
+
+        let (_, entries) = parse_dict_entries(&bytes, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            entries,
+            [
+                ("_t".to_owned(), "1 2 3".to_owned()),
+                ("_t".to_owned(), "4 5 6".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_bound_the_content_of_a_file() {
+        let bytes = include_bytes!("resources/transforms.vox");
+        let data = crate::load_bytes(bytes).unwrap();
+        let voxels: usize = data.models.iter().map(|model| model.voxels.len()).sum();
+        let load = |options: ParseOptions| crate::load_bytes_with(bytes, options);
+
+        let options = ParseOptions::default()
+            .max_total_voxels(voxels)
+            .max_nodes(data.scenes.len());
+        // The same options can be used for several files.
+        assert_eq!(load(options.clone()).unwrap(), data);
+        assert_eq!(load(options).unwrap(), data);
+
+        assert!(matches!(
+            load(ParseOptions::default().max_total_voxels(voxels - 1)),
+            Err(Error::LimitExceeded(Limit::TotalVoxels))
+        ));
+        assert!(matches!(
+            load(ParseOptions::default().max_nodes(data.scenes.len() - 1)),
+            Err(Error::LimitExceeded(Limit::Nodes))
+        ));
+        assert!(matches!(
+            load(ParseOptions::default().max_dict_entries(1)),
+            Err(Error::LimitExceeded(Limit::DictEntries))
+        ));
+        assert!(matches!(
+            crate::load_bytes_partial(bytes, ParseOptions::default().max_nodes(0)),
+            Err(Error::LimitExceeded(Limit::Nodes))
+        ));
+    }
+
+    #[test]
+    fn deeply_nested_chunks_exceed_the_depth_limit() {
+        let nested = |depth: usize| {
+            let mut bytes = b"VOX ".to_vec();
+            bytes.extend_from_slice(&150u32.to_le_bytes());
+            for i in 1..=depth {
+                bytes.extend_from_slice(b"MAIN");
+                bytes.extend_from_slice(&0u32.to_le_bytes());
+                bytes.extend_from_slice(&((12 * (depth - i)) as u32).to_le_bytes());
+            }
+            bytes
+        };
+
+        let options = ParseOptions::default().max_depth(10);
+        assert!(crate::load_bytes_with(&nested(10), options.clone()).is_ok());
+        assert!(matches!(
+            crate::load_bytes_with(&nested(11), options),
+            Err(Error::LimitExceeded(Limit::Depth))
+        ));
+        // Fails instead of overflowing the stack.
+        assert!(matches!(
+            crate::load_bytes(&nested(100_000)),
+            Err(Error::LimitExceeded(Limit::Depth))
+        ));
+    }
+
+    #[test]
+    fn sections_skip_the_chunks_of_other_sections() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        let data = crate::load_bytes(bytes).unwrap();
+        let load = |sections| {
+            crate::load_bytes_with(bytes, ParseOptions::default().sections(sections)).unwrap()
+        };
+
+        assert_eq!(load(Sections::ALL), data);
+        let models = load(Sections::MODELS);
+        assert_eq!(models.models, data.models);
+        assert_eq!(models.palette, DEFAULT_PALETTE.to_vec());
+        assert!(models.materials.is_empty());
+        assert!(models.scenes.is_empty());
+        assert!(models.layers.is_empty());
+        assert!(models.unknown_chunks.is_empty());
+
+        let mut sections = Sections::SCENES;
+        sections |= Sections::MATERIALS;
+        assert!(sections.contains(Sections::SCENES));
+        assert!(!sections.contains(Sections::SCENES | Sections::MODELS));
+        let scenes = load(sections);
+        assert!(scenes.models.is_empty());
+        assert_eq!(scenes.scenes, data.scenes);
+        assert_eq!(scenes.materials, data.materials);
+    }
+
+    #[test]
+    fn newer_versions_are_reported() {
+        let mut bytes = include_bytes!("resources/placeholder.vox").to_vec();
+        let (_, warnings) = crate::load_bytes_partial(&bytes, ParseOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+
+        bytes[4..8].copy_from_slice(&(SUPPORTED_VERSION + 1).to_le_bytes());
+        let data = crate::load_bytes(&bytes).unwrap();
+        assert_eq!(data.version, SUPPORTED_VERSION + 1);
+        let (_, warnings) = crate::load_bytes_partial(&bytes, ParseOptions::default()).unwrap();
+        assert_eq!(warnings, [Warning::NewerVersion(SUPPORTED_VERSION + 1)]);
+
+        let options = ParseOptions::default().reject_newer_versions(true);
+        assert!(matches!(
+            crate::load_bytes_partial(&bytes, options),
+            Err(Error::UnsupportedVersion(version)) if version == SUPPORTED_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn material_builder_matches_getters() {
+        let material = Material::builder(3)
+            .material_type("_glass")
+            .weight(0.5)
+            .metal(0.8)
+            .roughness(0.2)
+            .specular(1.5)
+            .refractive_index(0.3)
+            .emit(2.0)
+            .low_dynamic_range_scale(0.1)
+            .attenuation(0.25)
+            .radiant_flux(3.0)
+            .phase(-0.5)
+            .opacity(0.75)
+            .transparency(0.6)
+            .density(0.05)
+            .media(1.0)
+            .media_type("_scatter")
+            .property("_unit", "1")
+            .build();
+        assert_eq!(material.id, 3);
+        assert_eq!(material.material_type(), Some("_glass"));
+        assert_eq!(material.weight(), Some(0.5));
+        assert_eq!(material.metalness(), Some(0.8));
+        assert_eq!(material.roughness(), Some(0.2));
+        assert_eq!(material.specular(), Some(1.5));
+        assert_eq!(material.refractive_index(), Some(0.3));
+        assert_eq!(material.emission(), Some(2.0));
+        assert_eq!(material.low_dynamic_range_scale(), Some(0.1));
+        assert_eq!(material.attenuation(), Some(0.25));
+        assert_eq!(material.radiant_flux(), Some(3.0));
+        assert_eq!(material.phase(), Some(-0.5));
+        assert_eq!(material.opacity(), Some(0.75));
+        assert_eq!(material.transparency(), Some(0.6));
+        assert_eq!(material.density(), Some(0.05));
+        assert_eq!(material.media(), Some(1.0));
+        assert_eq!(material.media_type(), Some("_scatter"));
+        assert_eq!(material.properties["_emit"], "2");
+        assert_eq!(material.properties["_unit"], "1");
+    }
 }
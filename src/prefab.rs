@@ -0,0 +1,356 @@
+use crate::{
+    BuildError, Color, Dict, DotVoxData, Frame, Material, Model, Palette, Position, Rotation,
+    SceneNode, ShapeModel, Transform,
+};
+
+/// A model bundled with the colors and materials it uses, ready to be
+/// inserted into other files.
+///
+/// Voxel palette indices and material ids refer to the prefab's own
+/// [`palette`](Prefab::palette), which only holds the colors actually used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Prefab {
+    /// The model.
+    pub model: Model,
+    /// The colors used by the model.
+    pub palette: Vec<Color>,
+    /// Materials of the palette entries, with 1-based ids like
+    /// [`DotVoxData::materials`].
+    pub materials: Vec<Material>,
+}
+
+impl Prefab {
+    /// Extracts the model `model_id` of `data`, with the subset of the palette
+    /// and materials it uses. Returns `None` if there is no such model.
+    pub fn from_model(data: &DotVoxData, model_id: u32) -> Option<Prefab> {
+        let mut model = data.models.get(model_id as usize)?.clone();
+        let mut remap = [None; 256];
+        let mut palette = Vec::new();
+        for voxel in model.voxels.iter_mut() {
+            let index = *remap[voxel.i as usize].get_or_insert_with(|| {
+                palette.push(
+                    data.palette
+                        .get(voxel.i as usize)
+                        .copied()
+                        .unwrap_or(Color {
+                            r: 0,
+                            g: 0,
+                            b: 0,
+                            a: 255,
+                        }),
+                );
+                (palette.len() - 1) as u8
+            });
+            voxel.i = index;
+        }
+        let materials = data
+            .materials
+            .iter()
+            .filter_map(|material| {
+                let index = remap
+                    .get((material.id as usize).checked_sub(1)?)
+                    .copied()??;
+                Some(Material {
+                    id: index as u32 + 1,
+                    properties: material.properties.clone(),
+                })
+            })
+            .collect();
+
+        Some(Prefab {
+            model,
+            palette,
+            materials,
+        })
+    }
+
+    /// Adds the model to `data`, placed under the root group of the scene
+    /// graph by a new transform node moving its center by `transform`, and
+    /// returns the id of that transform node. A root transform and group are
+    /// created if `data` has no scene graph, and
+    /// [`BuildError::NotAGroup`] is returned for the root node if its scene
+    /// graph does not start with them.
+    ///
+    /// Each color of the prefab reuses a palette slot of `data` with the same
+    /// color and material, or takes over a slot no voxel uses. When all slots
+    /// are taken, the nearest color is used instead.
+    pub fn instantiate_into(
+        &self,
+        data: &mut DotVoxData,
+        transform: Transform,
+    ) -> Result<u32, BuildError> {
+        let root_group = root_group(data)?;
+        let model_id = self.add_model(data);
+        Ok(place_model(
+            data,
+            root_group,
+            model_id,
+            transform.translation,
+            transform.rotation,
+        ))
+    }
+
+    /// Adds the model to `data`, merging its colors into the palette, and
+    /// returns its id.
+    pub(crate) fn add_model(&self, data: &mut DotVoxData) -> u32 {
+        let remap = self.merge_palette(data);
+        let mut model = self.model.clone();
+        for voxel in model.voxels.iter_mut() {
+            voxel.i = remap.get(voxel.i as usize).copied().unwrap_or(0);
+        }
+        data.models.push(model);
+        (data.models.len() - 1) as u32
+    }
+
+    /// Finds or allocates a slot of `data.palette` for each prefab color.
+    fn merge_palette(&self, data: &mut DotVoxData) -> Vec<u8> {
+        if data.palette.is_empty() {
+            data.palette = crate::DEFAULT_PALETTE.to_vec();
+        }
+        // Pad short palettes so that every slot searched below exists.
+        let len = data.palette.len();
+        if len < crate::DEFAULT_PALETTE.len() {
+            data.palette
+                .extend_from_slice(&crate::DEFAULT_PALETTE[len..]);
+        }
+        let mut used = [false; 256];
+        for voxel in data.models.iter().flat_map(|model| &model.voxels) {
+            used[voxel.i as usize] = true;
+        }
+
+        let mut remap = Vec::with_capacity(self.palette.len());
+        for (index, color) in self.palette.iter().enumerate() {
+            let material = self.material(index);
+            let existing = (0..u8::MAX as usize).find(|&slot| {
+                data.palette.get(slot) == Some(color) && material_of(data, slot) == material
+            });
+            let slot = existing
+                .or_else(|| (0..u8::MAX as usize).find(|&slot| !used[slot]))
+                .map(|slot| slot as u8)
+                .or_else(|| data.palette.nearest(color))
+                .unwrap_or(0);
+            if !used[slot as usize] {
+                used[slot as usize] = true;
+                data.palette[slot as usize] = *color;
+                let id = slot as u32 + 1;
+                data.materials.retain(|material| material.id != id);
+                if let Some(properties) = material {
+                    data.materials.push(Material {
+                        id,
+                        properties: properties.clone(),
+                    });
+                }
+            }
+            remap.push(slot);
+        }
+        remap
+    }
+
+    fn material(&self, index: usize) -> Option<&Dict> {
+        self.materials
+            .iter()
+            .find(|material| material.id as usize == index + 1)
+            .map(|material| &material.properties)
+    }
+}
+
+fn material_of(data: &DotVoxData, slot: usize) -> Option<&Dict> {
+    data.materials
+        .iter()
+        .find(|material| material.id as usize == slot + 1)
+        .map(|material| &material.properties)
+}
+
+/// Returns the index of the group node under the root transform node,
+/// creating both if `data` has no scene graph.
+pub(crate) fn root_group(data: &mut DotVoxData) -> Result<usize, BuildError> {
+    let root_group = match data.scenes.first() {
+        None => {
+            let layer_id = layer_id(data);
+            data.scenes.push(transform_node(Dict::new(), 1, layer_id));
+            data.scenes.push(SceneNode::Group {
+                attributes: Dict::new(),
+                children: Vec::new(),
+            });
+            1
+        }
+        Some(SceneNode::Transform { child, .. }) => *child as usize,
+        Some(_) => return Err(BuildError::NotAGroup { node_id: 0 }),
+    };
+    if !matches!(data.scenes.get(root_group), Some(SceneNode::Group { .. })) {
+        return Err(BuildError::NotAGroup { node_id: 0 });
+    }
+    Ok(root_group)
+}
+
+/// Adds a transform and shape node placing the model `model_id` under the
+/// group node `root_group`, and returns the id of the transform node. The
+/// scene graph is no longer [synthetic](DotVoxData::synthetic_scene) after.
+pub(crate) fn place_model(
+    data: &mut DotVoxData,
+    root_group: usize,
+    model_id: u32,
+    translation: Position,
+    rotation: Rotation,
+) -> u32 {
+    let transform_id = data.scenes.len() as u32;
+    let frame = Frame::default()
+        .with_position(translation)
+        .with_orientation(rotation)
+        .attributes;
+    let layer_id = layer_id(data);
+    data.scenes
+        .push(transform_node(frame, transform_id + 1, layer_id));
+    data.scenes.push(SceneNode::Shape {
+        attributes: Dict::new(),
+        models: vec![ShapeModel {
+            model_id,
+            attributes: Dict::new(),
+        }],
+    });
+    if let Some(SceneNode::Group { children, .. }) = data.scenes.get_mut(root_group) {
+        children.push(transform_id);
+    }
+    data.synthetic_scene = false;
+    transform_id
+}
+
+/// Layer of new transform nodes: the first layer, if any.
+fn layer_id(data: &DotVoxData) -> u32 {
+    if data.layers.is_empty() {
+        u32::MAX
+    } else {
+        0
+    }
+}
+
+pub(crate) fn transform_node(frame: Dict, child: u32, layer_id: u32) -> SceneNode {
+    SceneNode::Transform {
+        attributes: Dict::new(),
+        frames: vec![Frame::new(frame)],
+        child,
+        layer_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Size, Voxel};
+
+    fn source() -> DotVoxData {
+        let mut data = crate::load("src/resources/placeholder-with-materials.vox").unwrap();
+        data.models[0] = Model {
+            size: Size { x: 2, y: 1, z: 1 },
+            voxels: vec![
+                Voxel {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                    i: 40,
+                },
+                Voxel {
+                    x: 1,
+                    y: 0,
+                    z: 0,
+                    i: 215,
+                },
+            ],
+        };
+        data
+    }
+
+    #[test]
+    fn from_model_keeps_only_used_colors_and_materials() {
+        let data = source();
+        let prefab = Prefab::from_model(&data, 0).unwrap();
+        assert_eq!(prefab.palette, [data.palette[40], data.palette[215]]);
+        assert_eq!(prefab.model.voxels[0].i, 0);
+        assert_eq!(prefab.model.voxels[1].i, 1);
+        let ids: Vec<u32> = prefab.materials.iter().map(|m| m.id).collect();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!(prefab.materials[1].properties["_type"], "_metal");
+        assert!(Prefab::from_model(&data, 1).is_none());
+    }
+
+    #[test]
+    fn instantiate_merges_palette_and_scene() {
+        let source = source();
+        let prefab = Prefab::from_model(&source, 0).unwrap();
+        let mut target = crate::load("src/resources/placeholder.vox").unwrap();
+        let nodes = target.scenes.len() as u32;
+        let translation = Position { x: 5, y: 0, z: 0 };
+
+        let id = prefab
+            .instantiate_into(
+                &mut target,
+                Transform {
+                    rotation: Rotation::IDENTITY,
+                    translation,
+                },
+            )
+            .unwrap();
+        assert_eq!(id, nodes);
+        assert_eq!(target.models.len(), 2);
+        for (voxel, original) in target.models[1].voxels.iter().zip(&source.models[0].voxels) {
+            assert_eq!(
+                target.palette[voxel.i as usize],
+                source.palette[original.i as usize]
+            );
+            assert_eq!(
+                material_of(&target, voxel.i as usize),
+                material_of(&source, original.i as usize)
+            );
+        }
+        let instance = target.instances().next_back().unwrap();
+        assert_eq!(instance.model_id, 1);
+        assert_eq!(instance.translation, translation);
+    }
+
+    #[test]
+    fn instantiate_creates_scene_graph() {
+        let prefab = Prefab::from_model(&source(), 0).unwrap();
+        let mut target = crate::load("src/resources/placeholder.vox").unwrap();
+        target.scenes.clear();
+        target.models.clear();
+        target.palette.clear();
+        let id = prefab
+            .instantiate_into(&mut target, Transform::IDENTITY)
+            .unwrap();
+        assert_eq!(id, 2);
+        assert_eq!(target.scenes.len(), 4);
+        assert_eq!(target.instances().count(), 1);
+    }
+
+    #[test]
+    fn instantiate_rejects_scenes_without_root_group() {
+        let prefab = Prefab::from_model(&source(), 0).unwrap();
+        let mut target = crate::load("src/resources/placeholder.vox").unwrap();
+        target.scenes.swap(0, 1);
+        assert_eq!(
+            prefab.instantiate_into(&mut target, Transform::IDENTITY),
+            Err(BuildError::NotAGroup { node_id: 0 })
+        );
+        assert_eq!(target.models.len(), 1);
+    }
+
+    #[test]
+    fn instantiate_pads_short_palettes() {
+        let prefab = Prefab::from_model(&source(), 0).unwrap();
+        let mut target = crate::load("src/resources/placeholder.vox").unwrap();
+        target.palette.truncate(4);
+        for (i, voxel) in target.models[0].voxels.iter_mut().enumerate() {
+            voxel.i = i as u8 % 4;
+        }
+        prefab
+            .instantiate_into(&mut target, Transform::IDENTITY)
+            .unwrap();
+        assert_eq!(target.palette.len(), crate::DEFAULT_PALETTE.len());
+        for (voxel, original) in target.models[1].voxels.iter().zip(&prefab.model.voxels) {
+            assert_eq!(
+                target.palette[voxel.i as usize],
+                prefab.palette[original.i as usize]
+            );
+        }
+    }
+}
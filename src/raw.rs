@@ -0,0 +1,43 @@
+//! Parsers for the content of individual chunks, for tools handling chunk
+//! payloads outside of a complete `.vox` file, e.g. embedded in a game
+//! archive.
+//!
+//! Each parser takes the content of a chunk, without its id and size header,
+//! and returns a [`nom::IResult`] with the remaining input. Parsers taking
+//! [`ParseOptions`](crate::ParseOptions) honor its [`ParseMode`](crate::ParseMode)
+//! when decoding strings.
+//!
+//! # Stability
+//!
+//! This module is exempt from semantic versioning: its items follow the chunk
+//! layout and the parser implementation, and may change in minor releases.
+//! Prefer the types at the crate root where they cover your use case.
+//!
+//! ```
+//! use dot_vox::{raw, ParseOptions};
+//!
+//! // A `LAYR` chunk: id 3, one attribute, reserved id.
+//! let mut chunk = vec![3, 0, 0, 0, 1, 0, 0, 0];
+//! for string in ["_name", "walls"] {
+//!     chunk.extend_from_slice(&(string.len() as u32).to_le_bytes());
+//!     chunk.extend_from_slice(string.as_bytes());
+//! }
+//! chunk.extend_from_slice(&u32::MAX.to_le_bytes());
+//!
+//! let (_, layer) = raw::parse_layer(&chunk, &ParseOptions::default()).unwrap();
+//! assert_eq!(layer.id, 3);
+//! assert_eq!(layer.attributes["_name"], "walls");
+//! ```
+
+pub use crate::camera::parse_camera;
+pub use crate::model::{parse_size, parse_voxels};
+pub use crate::palette::{extract_index_map, extract_palette, parse_palette_extension};
+pub use crate::parser::{
+    parse_dict, parse_dict_entries, parse_legacy_material, parse_material, parse_vox_file,
+    parse_vox_file_with,
+};
+pub use crate::scene::{
+    parse_layer, parse_scene_group, parse_scene_shape, parse_scene_transform, NodeHeader, RawLayer,
+    SceneGroup, SceneShape, SceneTransform, ShapeModel,
+};
+pub use crate::visitor::{read_chunks, FileChunk};
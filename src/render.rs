@@ -0,0 +1,490 @@
+//! Minimal software ray casting over the voxels of a scene.
+//!
+//! These types are meant as a reference for how the scene graph places voxels
+//! in world space, rather than as a renderer. See the `raycast` example for
+//! how they fit together.
+
+use crate::{surface::Occupancy, Color, DotVoxData, VoxelCoord};
+
+#[cfg(feature = "ahash")]
+use ahash::AHashMap as HashMap;
+
+#[cfg(not(feature = "ahash"))]
+use std::collections::HashMap;
+
+/// A pinhole camera in the right handed, Z up world space of MagicaVoxel,
+/// casting rays through the pixels of an image. Unrelated to the cameras
+/// stored in files, see [`Camera`](crate::Camera).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayCamera {
+    /// Position of the eye.
+    pub position: [f32; 3],
+    /// Point the camera looks at.
+    pub target: [f32; 3],
+    /// Up direction, usually `[0.0, 0.0, 1.0]`.
+    pub up: [f32; 3],
+    /// Vertical field of view, in radians.
+    pub fov_y: f32,
+}
+
+impl RayCamera {
+    /// The ray going through the center of a pixel of an image of the given
+    /// dimensions. Pixel `(0, 0)` is the top left corner.
+    pub fn ray(&self, x: u32, y: u32, width: u32, height: u32) -> Ray {
+        let forward = normalize(sub(self.target, self.position));
+        let right = normalize(cross(forward, self.up));
+        let up = cross(right, forward);
+
+        let half_height = (self.fov_y / 2.0).tan();
+        let half_width = half_height * width as f32 / height as f32;
+        let u = ((x as f32 + 0.5) / width as f32 * 2.0 - 1.0) * half_width;
+        let v = (1.0 - (y as f32 + 0.5) / height as f32 * 2.0) * half_height;
+
+        let direction = [
+            forward[0] + right[0] * u + up[0] * v,
+            forward[1] + right[1] * u + up[1] * v,
+            forward[2] + right[2] * u + up[2] * v,
+        ];
+        Ray {
+            origin: self.position,
+            direction: normalize(direction),
+        }
+    }
+}
+
+/// A half-line in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    /// Start of the ray.
+    pub origin: [f32; 3],
+    /// Direction of the ray. Does not need to be normalized.
+    pub direction: [f32; 3],
+}
+
+/// The first voxel hit by a [`Ray`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// World space coordinates of the voxel.
+    pub position: [i32; 3],
+    /// Normal of the face of the voxel the ray entered through.
+    pub normal: [i32; 3],
+    /// Palette index of the voxel.
+    pub index: u8,
+    /// Distance along the ray, in multiples of the ray's direction.
+    pub distance: f32,
+}
+
+/// The voxel of a model instance hit by [`DotVoxData::pick`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pick {
+    /// Index of the instance in [`DotVoxData::instances`].
+    pub instance: usize,
+    /// Index of the model in [`DotVoxData::models`].
+    pub model_id: u32,
+    /// Index of the voxel in [`Model::voxels`](crate::Model::voxels).
+    pub voxel: usize,
+    /// Model space coordinates of the voxel.
+    pub coord: VoxelCoord,
+    /// World space coordinates and palette index of the voxel, normal of the
+    /// face the ray entered through, and distance along the ray.
+    pub hit: Hit,
+}
+
+impl DotVoxData {
+    /// Finds the first voxel of any [`Instance`](crate::Instance) along the
+    /// ray starting at `origin`, in world space.
+    ///
+    /// Instances whose bounds the ray misses, or enters after the closest hit
+    /// so far, are skipped; the others are traversed cell by cell. Where
+    /// instances overlap, the one placed last wins.
+    pub fn pick(&self, origin: [f32; 3], direction: [f32; 3]) -> Option<Pick> {
+        let ray = Ray { origin, direction };
+        let mut occupancies: HashMap<u32, Occupancy> = HashMap::new();
+        let mut closest: Option<Pick> = None;
+        for (instance, placed) in self.placed_models().iter().enumerate() {
+            let Some(model) = self.models.get(placed.model_id as usize) else {
+                continue;
+            };
+            if model.voxels.is_empty() {
+                continue;
+            }
+            let (min, max) = placed.world_bounds(model.size);
+            let max_f = max.map(|m| m as f32 + 1.0);
+            match (entry(&ray, min, max_f), &closest) {
+                (None, _) => continue,
+                (Some((t, _)), Some(closest)) if t > closest.hit.distance => continue,
+                _ => {}
+            }
+
+            let occupancy = occupancies
+                .entry(placed.model_id)
+                .or_insert_with(|| Occupancy::new(model));
+            let local = |cell| placed.local_position(model.size, cell);
+            let extent = [0, 1, 2].map(|k| (max[k] - min[k] + 1) as u32);
+            let Some((position, normal, distance)) = march(&ray, min, extent, |cell| {
+                local(cell)
+                    .is_some_and(|[x, y, z]| occupancy.contains(x as i32, y as i32, z as i32))
+            }) else {
+                continue;
+            };
+            if closest.is_some_and(|closest| distance > closest.hit.distance) {
+                continue;
+            }
+            let Some(coord) = local(position) else {
+                continue;
+            };
+            let Some(voxel) = model
+                .voxels
+                .iter()
+                .position(|voxel| [voxel.x, voxel.y, voxel.z] == coord)
+            else {
+                continue;
+            };
+            closest = Some(Pick {
+                instance,
+                model_id: placed.model_id,
+                voxel,
+                coord: coord.into(),
+                hit: Hit {
+                    position,
+                    normal,
+                    index: model.voxels[voxel].i,
+                    distance,
+                },
+            });
+        }
+        closest
+    }
+}
+
+/// Sparse grid holding the palette index of every voxel of a scene, in world
+/// space. Memory use depends on the number of voxels only, however far apart
+/// models are placed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VoxelGrid {
+    /// Smallest world coordinates covered by the grid.
+    pub min: [i32; 3],
+    /// Number of cells along each axis, saturating at `u32::MAX`.
+    pub extent: [u32; 3],
+    cells: HashMap<[i32; 3], u8>,
+}
+
+impl VoxelGrid {
+    /// Places every voxel of the scene in a grid covering all of them. Where
+    /// models overlap, the model placed last wins.
+    pub fn from_scene(data: &DotVoxData) -> VoxelGrid {
+        let mut cells = HashMap::new();
+        let mut min = [i32::MAX; 3];
+        let mut max = [i32::MIN; 3];
+        for placed in data.placed_models() {
+            if let Some(model) = data.models.get(placed.model_id as usize) {
+                for voxel in model.voxels.iter() {
+                    let position = placed.voxel_position(model.size, voxel);
+                    for axis in 0..3 {
+                        min[axis] = min[axis].min(position[axis]);
+                        max[axis] = max[axis].max(position[axis]);
+                    }
+                    cells.insert(position, voxel.i);
+                }
+            }
+        }
+        if cells.is_empty() {
+            min = [0; 3];
+            max = [-1; 3];
+        }
+
+        let extent = [0, 1, 2]
+            .map(|axis| u32::try_from(max[axis] as i64 - min[axis] as i64 + 1).unwrap_or(u32::MAX));
+        VoxelGrid { min, extent, cells }
+    }
+
+    /// Palette index of the voxel at the given world position, if any.
+    pub fn get(&self, position: [i32; 3]) -> Option<u8> {
+        self.cells.get(&position).copied()
+    }
+
+    /// Finds the first voxel along `ray`, stepping through the grid one cell
+    /// at a time (Amanatides & Woo's DDA).
+    pub fn raycast(&self, ray: &Ray) -> Option<Hit> {
+        let (position, normal, distance) =
+            march(ray, self.min, self.extent, |cell| self.get(cell).is_some())?;
+        Some(Hit {
+            position,
+            normal,
+            index: self.get(position)?,
+            distance,
+        })
+    }
+}
+
+/// Steps through the cells of the box starting at `min` along `ray` (Amanatides
+/// & Woo's DDA), until `occupied` returns `true`. Returns the cell, the normal
+/// of the face the ray entered it through, and the distance along the ray.
+fn march(
+    ray: &Ray,
+    min: [i32; 3],
+    extent: [u32; 3],
+    occupied: impl Fn([i32; 3]) -> bool,
+) -> Option<([i32; 3], [i32; 3], f32)> {
+    let max = [0, 1, 2].map(|axis| min[axis] as f32 + extent[axis] as f32);
+    let (t_enter, mut normal) = entry(ray, min, max)?;
+    let contains = |cell: [i32; 3]| {
+        (0..3)
+            .all(|axis| (0..extent[axis] as i64).contains(&(cell[axis] as i64 - min[axis] as i64)))
+    };
+
+    let start = [0, 1, 2].map(|axis| ray.origin[axis] + ray.direction[axis] * t_enter);
+    let mut cell = [0, 1, 2].map(|axis| {
+        let last = min[axis] as i64 + extent[axis] as i64 - 1;
+        (start[axis].floor() as i64).clamp(min[axis] as i64, last) as i32
+    });
+
+    let step = ray.direction.map(|d| if d < 0.0 { -1 } else { 1 });
+    let t_delta = ray.direction.map(|d| (1.0 / d).abs());
+    let mut t_max = [0, 1, 2].map(|axis| {
+        let d = ray.direction[axis];
+        if d == 0.0 {
+            f32::INFINITY
+        } else {
+            let boundary = cell[axis] as f32 + if d > 0.0 { 1.0 } else { 0.0 };
+            (boundary - ray.origin[axis]) / d
+        }
+    });
+
+    let mut t = t_enter;
+    loop {
+        if !contains(cell) {
+            return None;
+        }
+        if occupied(cell) {
+            return Some((cell, normal, t));
+        }
+
+        let axis = if t_max[0] < t_max[1] {
+            if t_max[0] < t_max[2] {
+                0
+            } else {
+                2
+            }
+        } else if t_max[1] < t_max[2] {
+            1
+        } else {
+            2
+        };
+        t = t_max[axis];
+        cell[axis] = cell[axis].checked_add(step[axis])?;
+        t_max[axis] += t_delta[axis];
+        normal = [0; 3];
+        normal[axis] = -step[axis];
+    }
+}
+
+/// Distance along `ray` at which it enters the box between `min` and `max`,
+/// along with the normal of the face it enters through.
+fn entry(ray: &Ray, min: [i32; 3], max: [f32; 3]) -> Option<(f32, [i32; 3])> {
+    let mut t_enter = 0.0f32;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = [0; 3];
+    for axis in 0..3 {
+        let d = ray.direction[axis];
+        let (lo, hi) = (min[axis] as f32, max[axis]);
+        if d == 0.0 {
+            if ray.origin[axis] < lo || ray.origin[axis] >= hi {
+                return None;
+            }
+            continue;
+        }
+        let (t0, t1) = ((lo - ray.origin[axis]) / d, (hi - ray.origin[axis]) / d);
+        let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+        if near > t_enter {
+            t_enter = near;
+            normal = [0; 3];
+            normal[axis] = if d > 0.0 { -1 } else { 1 };
+        }
+        t_exit = t_exit.min(far);
+    }
+    (t_enter <= t_exit).then_some((t_enter, normal))
+}
+
+/// Simple diffuse shading of a hit, lit from a fixed direction above the
+/// scene.
+pub fn shade(hit: &Hit, palette: &[Color]) -> Color {
+    let color = palette.get(hit.index as usize).copied().unwrap_or(Color {
+        r: 255,
+        g: 0,
+        b: 255,
+        a: 255,
+    });
+    let light = normalize([0.3, 0.5, 1.0]);
+    let lambert = (hit.normal[0] as f32 * light[0]
+        + hit.normal[1] as f32 * light[1]
+        + hit.normal[2] as f32 * light[2])
+        .max(0.0);
+    let intensity = 0.4 + 0.6 * lambert;
+    let scale = |c: u8| (c as f32 * intensity).round() as u8;
+    Color {
+        r: scale(color.r),
+        g: scale(color.g),
+        b: scale(color.b),
+        a: color.a,
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length == 0.0 {
+        v
+    } else {
+        v.map(|c| c / length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_hits_placeholder_voxels() {
+        let data = crate::load("src/resources/placeholder.vox").unwrap();
+        let grid = VoxelGrid::from_scene(&data);
+        assert_eq!(grid.extent, [2, 2, 2]);
+
+        // Straight down onto the voxel at the top of the column at x, y = min.
+        let ray = Ray {
+            origin: [
+                grid.min[0] as f32 + 0.5,
+                grid.min[1] as f32 + 0.5,
+                grid.min[2] as f32 + 10.0,
+            ],
+            direction: [0.0, 0.0, -1.0],
+        };
+        let hit = grid.raycast(&ray).unwrap();
+        assert_eq!(hit.position, grid.min);
+        assert_eq!(hit.normal, [0, 0, 1]);
+        assert_eq!(hit.index, 225);
+        assert_eq!(hit.distance, 9.0);
+
+        let miss = Ray {
+            direction: [0.0, 0.0, 1.0],
+            ..ray
+        };
+        assert_eq!(grid.raycast(&miss), None);
+    }
+
+    #[test]
+    fn far_apart_models_need_no_dense_grid() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.models[0].voxels.truncate(1);
+        data.scenes.clear();
+        let root_group = crate::prefab::root_group(&mut data).unwrap();
+        for x in [i32::MIN + 10, -1_000_000, 1_000_000, i32::MAX - 10] {
+            let translation = crate::Position { x, y: 0, z: 0 };
+            crate::prefab::place_model(
+                &mut data,
+                root_group,
+                0,
+                translation,
+                crate::Rotation::IDENTITY,
+            );
+        }
+        let grid = VoxelGrid::from_scene(&data);
+        assert_eq!(grid.extent[1..], [1, 1]);
+        assert!(grid.extent[0] > 4_000_000_000);
+
+        let voxel = data
+            .instances()
+            .nth(2)
+            .unwrap()
+            .transform()
+            .voxel_position(data.models[0].size, &data.models[0].voxels[0]);
+        let ray = Ray {
+            origin: [voxel.x as f32 + 0.5, voxel.y as f32 + 0.5, 10.0],
+            direction: [0.0, 0.0, -1.0],
+        };
+        assert_eq!(
+            grid.raycast(&ray).unwrap().position,
+            [voxel.x, voxel.y, voxel.z]
+        );
+    }
+
+    #[test]
+    fn camera_center_ray_points_at_target() {
+        let camera = RayCamera {
+            position: [0.0, -10.0, 0.0],
+            target: [0.0, 0.0, 0.0],
+            up: [0.0, 0.0, 1.0],
+            fov_y: 1.0,
+        };
+        let ray = camera.ray(1, 1, 3, 3);
+        assert_eq!(ray.origin, camera.position);
+        assert!((ray.direction[1] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn local_position_inverts_voxel_position() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        for placed in data.placed_models() {
+            let model = &data.models[placed.model_id as usize];
+            for voxel in model.voxels.iter() {
+                let world = placed.voxel_position(model.size, voxel);
+                assert_eq!(
+                    placed.local_position(model.size, world),
+                    Some([voxel.x, voxel.y, voxel.z])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pick_matches_voxel_grid() {
+        for path in [
+            "src/resources/placeholder.vox",
+            "src/resources/transforms.vox",
+        ] {
+            let data = crate::load(path).unwrap();
+            let grid = VoxelGrid::from_scene(&data);
+            let placed = data.placed_models();
+            for x in 0..grid.extent[0] as i32 {
+                for y in 0..grid.extent[1] as i32 {
+                    let origin = [
+                        (grid.min[0] + x) as f32 + 0.5,
+                        (grid.min[1] + y) as f32 + 0.5,
+                        (grid.min[2] + grid.extent[2] as i32) as f32 + 10.0,
+                    ];
+                    let direction = [0.0, 0.0, -1.0];
+                    let expected = grid.raycast(&Ray { origin, direction });
+                    let pick = data.pick(origin, direction);
+                    assert_eq!(pick.map(|pick| pick.hit), expected, "{}", path);
+                    if let Some(pick) = pick {
+                        let instance = &placed[pick.instance];
+                        let model = &data.models[pick.model_id as usize];
+                        let voxel = &model.voxels[pick.voxel];
+                        assert_eq!(voxel.coord(), pick.coord);
+                        assert_eq!(
+                            instance.voxel_position(model.size, voxel),
+                            pick.hit.position
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pick_misses_empty_space() {
+        let data = crate::load("src/resources/placeholder.vox").unwrap();
+        assert_eq!(data.pick([100.0, 100.0, 100.0], [0.0, 0.0, 1.0]), None);
+    }
+}
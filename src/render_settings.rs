@@ -0,0 +1,239 @@
+use crate::{Color, Dict, DotVoxData};
+
+/// Typed view over the render settings of a file, stored by MagicaVoxel in
+/// `rOBJ` chunks and kept as raw dictionaries in
+/// [`DotVoxData::render_objects`].
+///
+/// Each group of settings is a dictionary identified by its `_type`, e.g.
+/// `_inf` for the sun or `_fog_uni` for fog. Accessors return `None` when the
+/// setting is missing or malformed.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderSettings<'a> {
+    objects: &'a [Dict],
+}
+
+impl DotVoxData {
+    /// Typed accessors for the render settings of this file.
+    pub fn render_settings(&self) -> RenderSettings<'_> {
+        RenderSettings {
+            objects: &self.render_objects,
+        }
+    }
+}
+
+impl<'a> RenderSettings<'a> {
+    /// The raw settings with the given `_type`, e.g. `_inf`.
+    pub fn object(&self, object_type: &str) -> Option<&'a Dict> {
+        self.objects
+            .iter()
+            .find(|object| object.get("_type").map(String::as_str) == Some(object_type))
+    }
+
+    /// The `_i` field of the `_inf` (sun) settings.
+    pub fn sun_intensity(&self) -> Option<f32> {
+        self.get_f32("_inf", "_i")
+    }
+
+    /// The `_k` field of the `_inf` (sun) settings.
+    pub fn sun_color(&self) -> Option<Color> {
+        self.get_color("_inf", "_k")
+    }
+
+    /// The `_angle` field of the `_inf` (sun) settings, as the elevation and
+    /// azimuth of the sun in degrees.
+    pub fn sun_angle(&self) -> Option<[f32; 2]> {
+        self.get_f32s("_inf", "_angle")
+    }
+
+    /// The `_i` field of the `_uni` (sky light) settings.
+    pub fn sky_intensity(&self) -> Option<f32> {
+        self.get_f32("_uni", "_i")
+    }
+
+    /// The `_k` field of the `_uni` (sky light) settings.
+    pub fn sky_color(&self) -> Option<Color> {
+        self.get_color("_uni", "_k")
+    }
+
+    /// The `_d` field of the `_fog_uni` settings.
+    pub fn fog_density(&self) -> Option<f32> {
+        self.get_f32("_fog_uni", "_d")
+    }
+
+    /// The `_k` field of the `_fog_uni` settings.
+    pub fn fog_color(&self) -> Option<Color> {
+        self.get_color("_fog_uni", "_k")
+    }
+
+    /// The `_fov` field of the `_lens` settings, in degrees.
+    pub fn field_of_view(&self) -> Option<f32> {
+        self.get_f32("_lens", "_fov")
+    }
+
+    /// The `_aperture` field of the `_lens` settings.
+    pub fn aperture(&self) -> Option<f32> {
+        self.get_f32("_lens", "_aperture")
+    }
+
+    /// The `_expo` field of the `_film` settings.
+    pub fn exposure(&self) -> Option<f32> {
+        self.get_f32("_film", "_expo")
+    }
+
+    /// The `_gam` field of the `_film` settings.
+    pub fn gamma(&self) -> Option<f32> {
+        self.get_f32("_film", "_gam")
+    }
+
+    /// The `_mix` field of the `_bloom` settings.
+    pub fn bloom_mix(&self) -> Option<f32> {
+        self.get_f32("_bloom", "_mix")
+    }
+
+    /// The `_threshold` field of the `_bloom` settings.
+    pub fn bloom_threshold(&self) -> Option<f32> {
+        self.get_f32("_bloom", "_threshold")
+    }
+
+    /// The `_color` field of the `_ground` settings.
+    pub fn ground_color(&self) -> Option<Color> {
+        self.get_color("_ground", "_color")
+    }
+
+    /// The `_color` field of the `_bg` (background) settings.
+    pub fn background_color(&self) -> Option<Color> {
+        self.get_color("_bg", "_color")
+    }
+
+    /// The `_color` field of the `_edge` settings.
+    pub fn edge_color(&self) -> Option<Color> {
+        self.get_color("_edge", "_color")
+    }
+
+    /// The `_width` field of the `_edge` settings.
+    pub fn edge_width(&self) -> Option<f32> {
+        self.get_f32("_edge", "_width")
+    }
+
+    /// The `_color` field of the `_grid` settings.
+    pub fn grid_color(&self) -> Option<Color> {
+        self.get_color("_grid", "_color")
+    }
+
+    /// The `_spacing` field of the `_grid` settings.
+    pub fn grid_spacing(&self) -> Option<f32> {
+        self.get_f32("_grid", "_spacing")
+    }
+
+    /// The `_width` field of the `_grid` settings.
+    pub fn grid_width(&self) -> Option<f32> {
+        self.get_f32("_grid", "_width")
+    }
+
+    /// Whether the ground, grid and edges are enabled, from the `_ground`,
+    /// `_grid` and `_edge` fields of the `_setting` settings.
+    pub fn ground_enabled(&self) -> Option<bool> {
+        self.get_bool("_setting", "_ground")
+    }
+
+    /// See [`ground_enabled`](Self::ground_enabled).
+    pub fn grid_enabled(&self) -> Option<bool> {
+        self.get_bool("_setting", "_grid")
+    }
+
+    /// See [`ground_enabled`](Self::ground_enabled).
+    pub fn edge_enabled(&self) -> Option<bool> {
+        self.get_bool("_setting", "_edge")
+    }
+
+    fn get(&self, object_type: &str, prop: &str) -> Option<&'a str> {
+        self.object(object_type)?.get(prop).map(String::as_str)
+    }
+
+    fn get_f32(&self, object_type: &str, prop: &str) -> Option<f32> {
+        let value = self.get(object_type, prop)?;
+        match value.parse::<f32>() {
+            Ok(x) => Some(x),
+            Err(_) => {
+                debug!("Could not parse float for property '{}': {}", prop, value);
+                None
+            }
+        }
+    }
+
+    fn get_f32s<const N: usize>(&self, object_type: &str, prop: &str) -> Option<[f32; N]> {
+        let value = self.get(object_type, prop)?;
+        let values: Option<Vec<f32>> = value.split_whitespace().map(|x| x.parse().ok()).collect();
+        match values.and_then(|values| values.try_into().ok()) {
+            Some(values) => Some(values),
+            None => {
+                debug!("Could not parse floats for property '{}': {}", prop, value);
+                None
+            }
+        }
+    }
+
+    fn get_color(&self, object_type: &str, prop: &str) -> Option<Color> {
+        let value = self.get(object_type, prop)?;
+        let rgb: Option<Vec<u8>> = value.split_whitespace().map(|x| x.parse().ok()).collect();
+        match rgb.as_deref() {
+            Some(&[r, g, b]) => Some(Color { r, g, b, a: 255 }),
+            _ => {
+                debug!("Could not parse color for property '{}': {}", prop, value);
+                None
+            }
+        }
+    }
+
+    fn get_bool(&self, object_type: &str, prop: &str) -> Option<bool> {
+        self.get(object_type, prop).map(|value| value == "1")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_render_settings() {
+        let data = crate::load("src/resources/axes.vox").unwrap();
+        assert_eq!(data.render_objects.len(), 15);
+        let settings = data.render_settings();
+        assert_eq!(settings.sun_intensity(), Some(0.7));
+        assert_eq!(settings.sun_angle(), Some([50.0, 50.0]));
+        assert_eq!(
+            settings.ground_color(),
+            Some(Color {
+                r: 80,
+                g: 80,
+                b: 80,
+                a: 255
+            })
+        );
+        assert_eq!(settings.fog_density(), Some(0.0));
+        assert_eq!(settings.bloom_mix(), Some(0.5));
+        assert_eq!(settings.edge_width(), Some(0.2));
+        assert_eq!(settings.grid_spacing(), Some(1.0));
+        assert_eq!(settings.field_of_view(), Some(45.0));
+        assert_eq!(settings.gamma(), Some(2.2));
+        assert_eq!(settings.ground_enabled(), Some(true));
+        assert_eq!(settings.grid_enabled(), Some(false));
+    }
+
+    #[test]
+    fn render_objects_round_trip() {
+        let data = crate::load("src/resources/axes.vox").unwrap();
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+        let reloaded = crate::load_bytes(&bytes).unwrap();
+        assert_eq!(reloaded.render_objects, data.render_objects);
+    }
+
+    #[test]
+    fn missing_settings_are_none() {
+        let data = crate::load("src/resources/placeholder.vox").unwrap();
+        let settings = data.render_settings();
+        assert!(settings.object("_inf").is_none());
+        assert_eq!(settings.sun_color(), None);
+    }
+}
@@ -1,6 +1,9 @@
-use std::mem::size_of;
+use std::{mem::size_of, ops::RangeInclusive};
 
-use crate::{parser::validate_count, Color, Dict, Rotation};
+use crate::{
+    parser::validate_count, prefab, BuildError, Color, Dict, DotVoxData, Model, Rotation, Size,
+    Transform, Voxel,
+};
 use nom::{
     multi::count,
     number::complete::{le_i32, le_u32},
@@ -9,7 +12,7 @@ use nom::{
     IResult,
 };
 
-use crate::parser::parse_dict;
+use crate::parser::{parse_dict, ParseOptions};
 /// Node header.
 #[derive(Debug, PartialEq, Eq)]
 pub struct NodeHeader {
@@ -93,6 +96,49 @@ pub struct Layer {
 }
 
 impl Layer {
+    /// A visible layer named `name`.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut layer = Layer::new("walls");
+    /// layer.set_hidden(true);
+    /// layer.set_color(Color { r: 255, g: 128, b: 0, a: 0 });
+    /// assert_eq!(layer.attributes["_color"], "255 128 0");
+    /// assert_eq!(layer.name().as_deref(), Some("walls"));
+    /// assert!(layer.hidden());
+    /// ```
+    pub fn new(name: &str) -> Layer {
+        let mut layer = Layer {
+            attributes: Dict::new(),
+        };
+        layer.set_name(name);
+        layer
+    }
+
+    /// Sets the [`name()`](Layer::name) of this layer.
+    pub fn set_name(&mut self, name: &str) {
+        self.attributes.insert("_name".to_owned(), name.to_owned());
+    }
+
+    /// Hides or shows this layer, see [`hidden()`](Layer::hidden).
+    pub fn set_hidden(&mut self, hidden: bool) {
+        if hidden {
+            self.attributes.insert("_hidden".to_owned(), "1".to_owned());
+        } else {
+            self.attributes.remove("_hidden");
+        }
+    }
+
+    /// Sets the [`color()`](Layer::color) of this layer. Its alpha is not
+    /// stored.
+    pub fn set_color(&mut self, color: Color) {
+        self.attributes.insert(
+            "_color".to_owned(),
+            format!("{} {} {}", color.r, color.g, color.b),
+        );
+    }
+
     /// Return the name for this layer, if it exists.
     pub fn name(&self) -> Option<String> {
         self.attributes.get("_name").cloned()
@@ -132,13 +178,16 @@ impl Layer {
     }
 }
 
-fn parse_node_header(i: &[u8]) -> IResult<&[u8], NodeHeader> {
-    let (i, (id, attributes)) = pair(le_u32, parse_dict)(i)?;
+fn parse_node_header<'a>(i: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], NodeHeader> {
+    let (i, (id, attributes)) = pair(le_u32, |i| parse_dict(i, options))(i)?;
     Ok((i, NodeHeader { id, attributes }))
 }
 
-fn parse_scene_shape_model(i: &[u8]) -> IResult<&[u8], ShapeModel> {
-    let (i, (model_id, attributes)) = pair(le_u32, parse_dict)(i)?;
+fn parse_scene_shape_model<'a>(
+    i: &'a [u8],
+    options: &ParseOptions,
+) -> IResult<&'a [u8], ShapeModel> {
+    let (i, (model_id, attributes)) = pair(le_u32, |i| parse_dict(i, options))(i)?;
     Ok((
         i,
         ShapeModel {
@@ -148,14 +197,18 @@ fn parse_scene_shape_model(i: &[u8]) -> IResult<&[u8], ShapeModel> {
     ))
 }
 
-pub fn parse_scene_transform(i: &[u8]) -> IResult<&[u8], SceneTransform> {
-    let (i, header) = parse_node_header(i)?;
+/// Parses the content of an `nTRN` chunk.
+pub fn parse_scene_transform<'a>(
+    i: &'a [u8],
+    options: &ParseOptions,
+) -> IResult<&'a [u8], SceneTransform> {
+    let (i, header) = parse_node_header(i, options)?;
     let (i, child) = le_u32(i)?;
     let (i, _ignored) = le_i32(i)?;
     let (i, layer_id) = le_u32(i)?;
     let (i, frame_count) = le_u32(i)?;
     let frame_count = validate_count(i, frame_count, size_of::<u32>())?;
-    let (i, frames) = count(parse_dict, frame_count)(i)?;
+    let (i, frames) = count(|i| parse_dict(i, options), frame_count)(i)?;
     Ok((
         i,
         SceneTransform {
@@ -167,31 +220,34 @@ pub fn parse_scene_transform(i: &[u8]) -> IResult<&[u8], SceneTransform> {
     ))
 }
 
-pub fn parse_scene_group(i: &[u8]) -> IResult<&[u8], SceneGroup> {
-    let (i, header) = parse_node_header(i)?;
+/// Parses the content of an `nGRP` chunk.
+pub fn parse_scene_group<'a>(i: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], SceneGroup> {
+    let (i, header) = parse_node_header(i, options)?;
     let (i, child_count) = le_u32(i)?;
     let child_count = validate_count(i, child_count, size_of::<u32>())?;
     let (i, children) = count(le_u32, child_count)(i)?;
     Ok((i, SceneGroup { header, children }))
 }
 
-pub fn parse_scene_shape(i: &[u8]) -> IResult<&[u8], SceneShape> {
-    let (i, header) = parse_node_header(i)?;
+/// Parses the content of an `nSHP` chunk.
+pub fn parse_scene_shape<'a>(i: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], SceneShape> {
+    let (i, header) = parse_node_header(i, options)?;
     let (i, model_count) = le_u32(i)?;
     let model_count = validate_count(i, model_count, size_of::<u32>() * 2)?;
-    let (i, models) = count(parse_scene_shape_model, model_count)(i)?;
+    let (i, models) = count(|i| parse_scene_shape_model(i, options), model_count)(i)?;
     Ok((i, SceneShape { header, models }))
 }
 
-pub fn parse_layer(i: &[u8]) -> IResult<&[u8], RawLayer> {
+/// Parses the content of a `LAYR` chunk.
+pub fn parse_layer<'a>(i: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], RawLayer> {
     let (i, id) = le_u32(i)?;
-    let (i, attributes) = parse_dict(i)?;
+    let (i, attributes) = parse_dict(i, options)?;
     let (i, _ignored) = le_u32(i)?;
     Ok((i, RawLayer { id, attributes }))
 }
 
 /// Represents a translation. Used to position a chunk relative to other chunks.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Position {
     /// The X coordinate of the translation.
     pub x: i32,
@@ -234,14 +290,55 @@ impl Frame {
         Frame { attributes }
     }
 
+    /// Sets the [`position()`](Frame::position) of this frame.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let position = Position { x: 1, y: -2, z: 3 };
+    /// let frame = Frame::default()
+    ///     .with_position(position)
+    ///     .with_orientation(Rotation::IDENTITY)
+    ///     .with_frame_index(5);
+    /// assert_eq!(frame.attributes["_t"], "1 -2 3");
+    /// assert_eq!(frame.position(), Some(position));
+    /// assert_eq!(frame.orientation(), Some(Rotation::IDENTITY));
+    /// assert_eq!(frame.frame_index(), Some(5));
+    /// ```
+    pub fn with_position(mut self, position: Position) -> Frame {
+        self.attributes.insert(
+            "_t".to_owned(),
+            format!("{} {} {}", position.x, position.y, position.z),
+        );
+        self
+    }
+
+    /// Sets the [`orientation()`](Frame::orientation) of this frame.
+    pub fn with_orientation(mut self, orientation: Rotation) -> Frame {
+        self.attributes
+            .insert("_r".to_owned(), orientation.to_byte().to_string());
+        self
+    }
+
+    /// Sets the [`frame_index()`](Frame::frame_index) of this frame.
+    pub fn with_frame_index(mut self, frame_index: u32) -> Frame {
+        self.attributes
+            .insert("_f".to_owned(), frame_index.to_string());
+        self
+    }
+
     /// The `_r` field in the `.vox` spec.  Represents the orientation of the
-    /// model.
+    /// model. Returns `None` if the field is missing or is not a valid
+    /// rotation.
     pub fn orientation(&self) -> Option<Rotation> {
         if let Some(value) = self.attributes.get("_r") {
             if let IResult::<&str, u8>::Ok((_, byte_rotation)) =
                 nom::character::complete::u8(value.as_str())
             {
-                return Some(Rotation::from_byte(byte_rotation));
+                match Rotation::try_from_byte(byte_rotation) {
+                    Ok(rotation) => return Some(rotation),
+                    Err(error) => debug!("'_r' attribute for Frame is invalid! {}", error),
+                }
             } else {
                 debug!("'_r' attribute for Frame could not be parsed! {}", value);
             }
@@ -274,6 +371,84 @@ impl Frame {
         None
     }
 
+    /// The [`orientation()`](Frame::orientation) and
+    /// [`position()`](Frame::position) of this frame as a [`Transform`],
+    /// with missing fields left as the identity. Fractional translations
+    /// (see [`position_f32()`](Frame::position_f32)) are rounded to the
+    /// nearest voxel. The [`scale()`](Frame::scale) cannot be represented,
+    /// and is ignored.
+    pub fn transform(&self) -> Transform {
+        let translation = self.position().or_else(|| {
+            self.position_f32().map(|[x, y, z]| Position {
+                x: x.round() as i32,
+                y: y.round() as i32,
+                z: z.round() as i32,
+            })
+        });
+        Transform {
+            rotation: self.orientation().unwrap_or(Rotation::IDENTITY),
+            translation: translation.unwrap_or(Transform::IDENTITY.translation),
+        }
+    }
+
+    /// The `_t` field parsed as floating point values. Some forks of
+    /// MagicaVoxel write fractional translations, which
+    /// [`position()`](Frame::position) rejects; integer translations are
+    /// accepted as well.
+    ///
+    /// Placement by the scene graph, e.g. [`DotVoxData::instances`], works on
+    /// whole voxels: [`transform()`](Frame::transform) rounds fractional
+    /// translations to the nearest one.
+    pub fn position_f32(&self) -> Option<[f32; 3]> {
+        if let Some(value) = self.attributes.get("_t") {
+            let mut components = value.split_whitespace().map(str::parse::<f32>);
+            match (
+                components.next(),
+                components.next(),
+                components.next(),
+                components.next(),
+            ) {
+                (Some(Ok(x)), Some(Ok(y)), Some(Ok(z)), None) => return Some([x, y, z]),
+                _ => debug!("'_t' attribute for Frame could not be parsed! {}", value),
+            }
+        }
+
+        None
+    }
+
+    /// The non-standard `_s` field, written by some forks of MagicaVoxel to
+    /// encode a uniform scale of world-scale scenes. A missing scale stands
+    /// for `1.0`.
+    ///
+    /// Placement by the scene graph, e.g. [`DotVoxData::instances`], works on
+    /// whole voxels and ignores the scale. Apply it on top of
+    /// [`transform()`](Frame::transform) where needed. Files using it are
+    /// reported with [`Warning::NonStandardScale`](crate::Warning::NonStandardScale).
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut frame = Frame::default();
+    /// assert_eq!(frame.scale(), None);
+    /// frame.set_scale(0.25);
+    /// assert_eq!(frame.scale(), Some(0.25));
+    /// ```
+    pub fn scale(&self) -> Option<f32> {
+        if let Some(value) = self.attributes.get("_s") {
+            match value.trim().parse::<f32>() {
+                Ok(scale) => return Some(scale),
+                Err(_) => debug!("'_s' attribute for Frame could not be parsed! {}", value),
+            }
+        }
+
+        None
+    }
+
+    /// Sets the non-standard `_s` field. See [`scale()`](Frame::scale).
+    pub fn set_scale(&mut self, scale: f32) {
+        self.attributes.insert("_s".to_owned(), scale.to_string());
+    }
+
     /// The `_f` field in the .vox spec.  Represents the frame number that this
     /// keyframe is located at.
     pub fn frame_index(&self) -> Option<u32> {
@@ -288,6 +463,69 @@ impl Frame {
         }
         None
     }
+
+    /// The keyframe of a transform node in effect at animation frame `frame`:
+    /// the last one whose [`frame_index()`](Frame::frame_index) is at most
+    /// `frame`, or the first one if there is none. Keyframes without an
+    /// index start at frame 0. Returns `None` if `frames` is empty.
+    pub fn keyframe_at(frames: &[Frame], frame: u32) -> Option<&Frame> {
+        keyframe_at(frames, frame, Frame::frame_index)
+    }
+
+    /// The [`position_f32()`](Frame::position_f32) of a transform node at
+    /// the fractional animation time `time`, linearly interpolated between
+    /// the keyframes before and after it. Before the first keyframe and
+    /// after the last one, their position is held. A keyframe without a
+    /// position is at the origin. Returns `None` if `frames` is empty.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let frames = [
+    ///     Frame::default().with_position(Position { x: 0, y: 0, z: 0 }),
+    ///     Frame::default()
+    ///         .with_position(Position { x: 10, y: -4, z: 2 })
+    ///         .with_frame_index(4),
+    /// ];
+    /// assert_eq!(Frame::position_at(&frames, 1.0), Some([2.5, -1.0, 0.5]));
+    /// assert_eq!(Frame::position_at(&frames, 9.0), Some([10.0, -4.0, 2.0]));
+    /// ```
+    pub fn position_at(frames: &[Frame], time: f32) -> Option<[f32; 3]> {
+        let mut keyframes: Vec<(f32, [f32; 3])> = frames
+            .iter()
+            .map(|frame| {
+                (
+                    frame.frame_index().unwrap_or(0) as f32,
+                    frame.position_f32().unwrap_or([0.0; 3]),
+                )
+            })
+            .collect();
+        // Stable, so that the first of keyframes with the same index is used
+        // before it, and the last one after it.
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let after = keyframes.partition_point(|(start, _)| *start <= time);
+        let position = match (after.checked_sub(1), keyframes.get(after)) {
+            (None, _) => keyframes.first()?.1,
+            (Some(before), None) => keyframes[before].1,
+            (Some(before), Some(&(end, to))) => {
+                let (start, from) = keyframes[before];
+                let t = (time - start) / (end - start);
+                [0, 1, 2].map(|k| from[k] + (to[k] - from[k]) * t)
+            }
+        };
+        Some(position)
+    }
+
+    /// The [`orientation()`](Frame::orientation) of a transform node at the
+    /// fractional animation time `time`. Rotations are not interpolated: the
+    /// one of the [keyframe in effect](Frame::keyframe_at) at the frame
+    /// containing `time` is used. A keyframe without a rotation is not
+    /// rotated. Returns `None` if `frames` is empty.
+    pub fn orientation_at(frames: &[Frame], time: f32) -> Option<Rotation> {
+        let frame = Frame::keyframe_at(frames, time.max(0.0).floor() as u32)?;
+        Some(frame.orientation().unwrap_or(Rotation::IDENTITY))
+    }
 }
 
 /// Scene graph nodes for representing a scene in
@@ -302,7 +540,7 @@ pub enum SceneNode {
         frames: Vec<Frame>,
         /// Child node of this transform node.
         child: u32,
-        /// Layer ID
+        /// Layer ID, `u32::MAX` for no layer. See [`SceneNode::layer`].
         layer_id: u32,
     },
     /// Group Node Chunk (nGRP)
@@ -320,3 +558,1099 @@ pub enum SceneNode {
         models: Vec<ShapeModel>,
     },
 }
+
+impl SceneNode {
+    /// The attributes of this node.
+    pub fn attributes(&self) -> &Dict {
+        match self {
+            SceneNode::Transform { attributes, .. }
+            | SceneNode::Group { attributes, .. }
+            | SceneNode::Shape { attributes, .. } => attributes,
+        }
+    }
+
+    /// Whether this node itself is hidden by its `_hidden` attribute. See
+    /// [`DotVoxData::is_visible`] for whether it is shown.
+    pub fn hidden(&self) -> bool {
+        self.attributes().get("_hidden").is_some_and(|x| x == "1")
+    }
+
+    /// The name of this node from its `_name` attribute, if it has one.
+    /// MagicaVoxel names transform nodes.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut data = load("src/resources/placeholder.vox").unwrap();
+    /// assert_eq!(data.scenes[2].name(), None);
+    /// data.scenes[2].set_name("chair");
+    /// data.scenes[2].set_hidden(true);
+    /// assert_eq!(data.scenes[2].name().as_deref(), Some("chair"));
+    /// assert!(data.scenes[2].hidden() && !data.is_visible(3));
+    /// ```
+    pub fn name(&self) -> Option<String> {
+        self.attributes().get("_name").cloned()
+    }
+
+    /// Sets the [`name()`](SceneNode::name) of this node.
+    pub fn set_name(&mut self, name: &str) {
+        self.attributes_mut()
+            .insert("_name".to_owned(), name.to_owned());
+    }
+
+    /// Hides or shows this node, see [`hidden()`](SceneNode::hidden).
+    pub fn set_hidden(&mut self, hidden: bool) {
+        if hidden {
+            self.attributes_mut()
+                .insert("_hidden".to_owned(), "1".to_owned());
+        } else {
+            self.attributes_mut().remove("_hidden");
+        }
+    }
+
+    /// The layer of this transform node, an index into
+    /// [`DotVoxData::layers`]. `None` for transform nodes on no layer, stored
+    /// as `u32::MAX` in the file, and for other nodes.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = load("src/resources/placeholder.vox").unwrap();
+    /// assert_eq!(data.scenes[0].layer(), None);
+    /// assert_eq!(data.scenes[2].layer(), Some(0));
+    /// assert_eq!(data.scenes[1].layer(), None);
+    /// ```
+    pub fn layer(&self) -> Option<u32> {
+        match self {
+            SceneNode::Transform { layer_id, .. } if *layer_id != u32::MAX => Some(*layer_id),
+            _ => None,
+        }
+    }
+
+    fn attributes_mut(&mut self) -> &mut Dict {
+        match self {
+            SceneNode::Transform { attributes, .. }
+            | SceneNode::Group { attributes, .. }
+            | SceneNode::Shape { attributes, .. } => attributes,
+        }
+    }
+}
+
+/// A model placed in the world by the scene graph, with the transforms of all
+/// of its ancestors folded in.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PlacedModel {
+    /// Index of the shape node placing the model.
+    #[cfg_attr(not(any(test, feature = "test-util")), allow(dead_code))]
+    pub node_id: u32,
+    /// Index of the model in [`DotVoxData::models`].
+    pub model_id: u32,
+    /// Accumulated rotation.
+    pub rotation: Rotation,
+    /// Accumulated translation of the center of the model.
+    pub translation: [i32; 3],
+    /// Layer of the closest transform node above the shape node.
+    pub layer_id: u32,
+    /// Index of the closest transform node above the shape node, or
+    /// `u32::MAX` for files without a scene graph.
+    pub transform_id: u32,
+}
+
+/// The keyframe of `keyframes` in effect at animation frame `frame`, see
+/// [`DotVoxData::visit_scene_at`]. `index` returns the frame index of a
+/// keyframe.
+fn keyframe_at<T>(keyframes: &[T], frame: u32, index: impl Fn(&T) -> Option<u32>) -> Option<&T> {
+    let mut current = keyframes.first()?;
+    let mut start = index(current).unwrap_or(0);
+    for keyframe in &keyframes[1..] {
+        let keyframe_start = index(keyframe).unwrap_or(0);
+        if keyframe_start <= frame && (keyframe_start > start || start > frame) {
+            current = keyframe;
+            start = keyframe_start;
+        }
+    }
+    Some(current)
+}
+
+/// Iterator over the models placed by the scene graph, created by
+/// [`DotVoxData::walk_scene`].
+#[derive(Clone, Debug)]
+pub(crate) struct SceneWalk<'a> {
+    pub data: &'a DotVoxData,
+    frame: u32,
+    /// Whether models come in the opposite order.
+    pub reversed: bool,
+    /// Nodes to visit, with the transform, layer and id of their closest
+    /// transform node ancestor, and their depth.
+    stack: Vec<(u32, Transform, u32, u32, usize)>,
+    /// Transform and group nodes on the path from the root to the node being
+    /// visited, and whether each node is on it.
+    path: Vec<u32>,
+    on_path: Vec<bool>,
+    /// Models left to place in files without a scene graph.
+    unplaced: std::ops::Range<usize>,
+}
+
+impl<'a> Iterator for SceneWalk<'a> {
+    type Item = PlacedModel;
+
+    fn next(&mut self) -> Option<PlacedModel> {
+        let scenes = &self.data.scenes;
+        if scenes.is_empty() {
+            let model_id = if self.reversed {
+                self.unplaced.next_back()
+            } else {
+                self.unplaced.next()
+            }?;
+            let size = self.data.models[model_id].size;
+            return Some(PlacedModel {
+                node_id: u32::MAX,
+                model_id: model_id as u32,
+                rotation: Rotation::IDENTITY,
+                translation: [size.x / 2, size.y / 2, size.z / 2].map(|s| s as i32),
+                layer_id: u32::MAX,
+                transform_id: u32::MAX,
+            });
+        }
+
+        while let Some((node, transform, layer, transform_id, depth)) = self.stack.pop() {
+            for ancestor in self.path.drain(depth..) {
+                self.on_path[ancestor as usize] = false;
+            }
+            // A node that is its own ancestor closes a cycle. Skipping it
+            // rather than bounding the depth keeps crafted graphs such as a
+            // group listing itself twice from expanding exponentially.
+            if self.on_path.get(node as usize).copied().unwrap_or(false) {
+                event!(debug, { node_id = node }, "Cycle detected in scene graph");
+                continue;
+            }
+            if self.data.node_hidden(node) {
+                continue;
+            }
+            if let Some(SceneNode::Transform { .. } | SceneNode::Group { .. }) =
+                scenes.get(node as usize)
+            {
+                self.path.push(node);
+                self.on_path[node as usize] = true;
+            }
+
+            match scenes.get(node as usize) {
+                Some(SceneNode::Transform {
+                    frames,
+                    child,
+                    layer_id,
+                    ..
+                }) => {
+                    let local = keyframe_at(frames, self.frame, Frame::frame_index)
+                        .map(Frame::transform)
+                        .unwrap_or_default();
+                    let Some(transform) = transform.checked_mul(local) else {
+                        event!(
+                            debug,
+                            { node_id = node },
+                            "Skipping transform node placing its children out of range"
+                        );
+                        continue;
+                    };
+                    self.stack
+                        .push((*child, transform, *layer_id, node, depth + 1));
+                }
+                Some(SceneNode::Group { children, .. }) => {
+                    let child = |child: &u32| (*child, transform, layer, transform_id, depth + 1);
+                    if self.reversed {
+                        self.stack.extend(children.iter().map(child));
+                    } else {
+                        self.stack.extend(children.iter().rev().map(child));
+                    }
+                }
+                Some(SceneNode::Shape { models, .. }) => {
+                    if let Some(model) = keyframe_at(models, self.frame, ShapeModel::frame_index) {
+                        let size = self
+                            .data
+                            .models
+                            .get(model.model_id as usize)
+                            .map(|m| m.size);
+                        if size.is_some_and(|size| !transform.fits(size)) {
+                            event!(
+                                debug,
+                                { node_id = node },
+                                "Skipping model placed out of range"
+                            );
+                            continue;
+                        }
+                        let Position { x, y, z } = transform.translation;
+                        return Some(PlacedModel {
+                            node_id: node,
+                            model_id: model.model_id,
+                            rotation: transform.rotation,
+                            translation: [x, y, z],
+                            layer_id: layer,
+                            transform_id,
+                        });
+                    }
+                }
+                None => event!(
+                    debug,
+                    { node_id = node },
+                    "Scene graph references missing node"
+                ),
+            }
+        }
+
+        None
+    }
+}
+
+impl PlacedModel {
+    /// The transform placing the center of the model.
+    pub fn transform(&self) -> Transform {
+        let [x, y, z] = self.translation;
+        Transform {
+            rotation: self.rotation,
+            translation: Position { x, y, z },
+        }
+    }
+
+    /// The world space position of a voxel of a model of the given size. See
+    /// [`Transform::voxel_position`].
+    pub fn voxel_position(&self, size: Size, voxel: &Voxel) -> [i32; 3] {
+        let Position { x, y, z } = self.transform().voxel_position(size, voxel);
+        [x, y, z]
+    }
+
+    /// Inverse of [`voxel_position`](Self::voxel_position): the model space
+    /// coordinates of the voxel at a world position, if within the model.
+    pub fn local_position(&self, size: Size, world: [i32; 3]) -> Option<[u8; 3]> {
+        let size = [size.x as i32, size.y as i32, size.z as i32];
+        // Doubled offsets are odd along axes of even size, and even otherwise.
+        let parity = self.rotation.transform_point(size.map(|s| (s + 1) % 2));
+        let rotated = [0, 1, 2].map(|k| 2 * (world[k] - self.translation[k]) + parity[k].abs());
+        let mut local = [0u8; 3];
+        for axis in 0..3 {
+            let mut basis = [0; 3];
+            basis[axis] = 1;
+            let column = self.rotation.transform_point(basis);
+            let doubled_offset: i32 = (0..3).map(|k| column[k] * rotated[k]).sum();
+            let v = (doubled_offset + size[axis] - 1) / 2;
+            if !(0..size[axis]).contains(&v) {
+                return None;
+            }
+            local[axis] = u8::try_from(v).ok()?;
+        }
+        Some(local)
+    }
+
+    /// Smallest and largest world coordinates of the voxels of the model.
+    pub fn world_bounds(&self, size: Size) -> ([i32; 3], [i32; 3]) {
+        let corner = |x: u32, y: u32, z: u32| {
+            let voxel = Voxel {
+                x: x.saturating_sub(1).min(u8::MAX as u32) as u8,
+                y: y.saturating_sub(1).min(u8::MAX as u32) as u8,
+                z: z.saturating_sub(1).min(u8::MAX as u32) as u8,
+                i: 0,
+            };
+            self.voxel_position(size, &voxel)
+        };
+        let (a, b) = (corner(1, 1, 1), corner(size.x, size.y, size.z));
+        (
+            [0, 1, 2].map(|k| a[k].min(b[k])),
+            [0, 1, 2].map(|k| a[k].max(b[k])),
+        )
+    }
+}
+
+impl DotVoxData {
+    /// Builds the scene graph MagicaVoxel uses for files without one, if
+    /// [`DotVoxData::scenes`] is empty: a root transform and group node, then
+    /// a transform and shape node per model placing its minimum corner at the
+    /// origin. Returns whether a scene graph was built, in which case
+    /// [`DotVoxData::synthetic_scene`] is set.
+    ///
+    /// Files are given one while parsing unless disabled with
+    /// [`ParseOptions::synthesize_scene`](crate::ParseOptions::synthesize_scene).
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut data = load("src/resources/placeholder.vox").unwrap();
+    /// data.scenes.clear();
+    /// assert!(data.synthesize_scene());
+    /// assert_eq!(data.instances().len(), 1);
+    /// assert!(!data.synthesize_scene());
+    /// ```
+    pub fn synthesize_scene(&mut self) -> bool {
+        if !self.scenes.is_empty() || self.models.is_empty() {
+            return false;
+        }
+        let root_group = prefab::root_group(self).expect("empty scene graphs have a root group");
+        for model_id in 0..self.models.len() {
+            let size = self.models[model_id].size;
+            let translation = Position {
+                x: (size.x / 2) as i32,
+                y: (size.y / 2) as i32,
+                z: (size.z / 2) as i32,
+            };
+            prefab::place_model(
+                self,
+                root_group,
+                model_id as u32,
+                translation,
+                Rotation::IDENTITY,
+            );
+        }
+        self.synthetic_scene = true;
+        true
+    }
+
+    /// Whether the scene node `node_id` is shown by MagicaVoxel, i.e. it is
+    /// reachable from the root and neither it nor any of its ancestors is
+    /// hidden. A node is hidden if:
+    /// 1. its [`_hidden`](SceneNode::hidden) attribute is set, for a
+    ///    transform, group or shape node,
+    /// 2. or, for a transform node, its layer is [hidden](Layer::hidden).
+    ///
+    /// [`DotVoxData::instances`] and everything built on it, such as
+    /// [`DotVoxData::world_voxels`], rendering and picking, skip models that
+    /// are not visible.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut data = load("src/resources/placeholder.vox").unwrap();
+    /// // Node 3 is the shape node of the model, under a transform on layer 0.
+    /// assert!(data.is_visible(3));
+    /// data.layers_mut().set_hidden(0, true);
+    /// assert!(data.is_visible(0) && !data.is_visible(3));
+    /// assert_eq!(data.instances().len(), 0);
+    /// ```
+    pub fn is_visible(&self, node_id: u32) -> bool {
+        // Every ancestor of a node has been visited before it, so skipping
+        // visited nodes breaks cycles, and visits each node at most once.
+        let mut visited = vec![false; self.scenes.len()];
+        let mut stack = vec![0u32];
+        while let Some(node) = stack.pop() {
+            match visited.get_mut(node as usize) {
+                Some(true) => continue,
+                Some(seen) => *seen = true,
+                None => {}
+            }
+            if self.node_hidden(node) {
+                continue;
+            }
+            if node == node_id {
+                return true;
+            }
+            match self.scenes.get(node as usize) {
+                Some(SceneNode::Transform { child, .. }) => stack.push(*child),
+                Some(SceneNode::Group { children, .. }) => stack.extend(children),
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Whether the node `node_id` is hidden by itself or its layer, not
+    /// taking its ancestors into account.
+    fn node_hidden(&self, node_id: u32) -> bool {
+        match self.scenes.get(node_id as usize) {
+            Some(node @ SceneNode::Transform { layer_id, .. }) => {
+                node.hidden()
+                    || self
+                        .layers
+                        .get(*layer_id as usize)
+                        .is_some_and(Layer::hidden)
+            }
+            Some(node) => node.hidden(),
+            None => false,
+        }
+    }
+
+    /// Calls `visit` with every model placed by the scene graph, along with
+    /// the world space position of its center, its rotation, and the layer of
+    /// the transform node placing it, accumulated through every transform
+    /// node above it. Models are visited in depth-first order, skipping those
+    /// that are not [visible](DotVoxData::is_visible), as by
+    /// [`DotVoxData::instances`].
+    ///
+    /// Transform nodes are evaluated at their first frame; see
+    /// [`visit_scene_at`](DotVoxData::visit_scene_at) for animations.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = load("src/resources/placeholder.vox").unwrap();
+    /// let mut visited = Vec::new();
+    /// data.visit_scene(|model, translation, rotation, layer_id| {
+    ///     visited.push((model.size, translation, rotation, layer_id));
+    /// });
+    /// assert_eq!(
+    ///     visited,
+    ///     [(
+    ///         Size { x: 2, y: 2, z: 2 },
+    ///         Position { x: 0, y: 0, z: 1 },
+    ///         Rotation::IDENTITY,
+    ///         0
+    ///     )]
+    /// );
+    /// ```
+    pub fn visit_scene(&self, visit: impl FnMut(&Model, Position, Rotation, u32)) {
+        self.visit_scene_at(0, visit)
+    }
+
+    /// Like [`visit_scene`](DotVoxData::visit_scene), but evaluates the scene
+    /// at animation frame `frame`. Each transform node uses its last
+    /// [`Frame`] whose [`frame_index()`](Frame::frame_index) is at most
+    /// `frame`, or its first frame if there is none, and each shape node its
+    /// [`ShapeModel`] chosen the same way. Keyframes without an index start at
+    /// frame 0. Keyframes are not interpolated.
+    pub fn visit_scene_at(
+        &self,
+        frame: u32,
+        mut visit: impl FnMut(&Model, Position, Rotation, u32),
+    ) {
+        for placed in self.walk_scene(frame, false) {
+            if let Some(model) = self.models.get(placed.model_id as usize) {
+                let [x, y, z] = placed.translation;
+                visit(
+                    model,
+                    Position { x, y, z },
+                    placed.rotation,
+                    placed.layer_id,
+                );
+            }
+        }
+    }
+
+    /// Walks the scene graph from its root, returning every model placed by a
+    /// shape node at animation frame 0. Models that are
+    /// not [visible](DotVoxData::is_visible) are skipped.
+    ///
+    /// Files without a scene graph place every model with its minimum corner
+    /// at the origin.
+    pub(crate) fn placed_models(&self) -> Vec<PlacedModel> {
+        self.placed_models_at(0)
+    }
+
+    /// Like [`placed_models`](DotVoxData::placed_models), with transform nodes
+    /// evaluated at animation frame `frame`, see
+    /// [`visit_scene_at`](DotVoxData::visit_scene_at).
+    pub(crate) fn placed_models_at(&self, frame: u32) -> Vec<PlacedModel> {
+        self.walk_scene(frame, false).collect()
+    }
+
+    /// Lazily walks the scene graph as
+    /// [`placed_models_at`](DotVoxData::placed_models_at) does, holding only
+    /// the nodes left to visit. With `reversed`, models come in the opposite
+    /// order.
+    pub(crate) fn walk_scene(&self, frame: u32, reversed: bool) -> SceneWalk<'_> {
+        let (stack, unplaced) = if self.scenes.is_empty() {
+            (Vec::new(), 0..self.models.len())
+        } else {
+            (vec![(0, Transform::IDENTITY, u32::MAX, u32::MAX, 0)], 0..0)
+        };
+        SceneWalk {
+            data: self,
+            frame,
+            reversed,
+            stack,
+            path: Vec::new(),
+            on_path: vec![false; self.scenes.len()],
+            unplaced,
+        }
+    }
+
+    /// Inclusive world space bounds of every model placed by the scene graph,
+    /// as the coordinates of the lowest and highest voxel that fit in them
+    /// along each axis. Models are placed around their center like
+    /// MagicaVoxel does, and their whole size is taken into account, whether
+    /// or not voxels fill it. Models that are not
+    /// [visible](DotVoxData::is_visible) are left out.
+    ///
+    /// Returns `None` if no model with a non-zero size is placed.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let data = load("src/resources/placeholder.vox").unwrap();
+    /// // The 2x2x2 model is centered on (0, 0, 1).
+    /// assert_eq!(
+    ///     data.bounding_box(),
+    ///     Some((Position { x: -1, y: -1, z: 0 }, Position { x: 0, y: 0, z: 1 }))
+    /// );
+    /// ```
+    pub fn bounding_box(&self) -> Option<(Position, Position)> {
+        let mut bounds: Option<([i32; 3], [i32; 3])> = None;
+        for placed in self.walk_scene(0, false) {
+            let Some(model) = self.models.get(placed.model_id as usize) else {
+                continue;
+            };
+            let size = model.size;
+            if size.x == 0 || size.y == 0 || size.z == 0 {
+                continue;
+            }
+            let (min, max) = placed.world_bounds(size);
+            bounds = Some(match bounds {
+                None => (min, max),
+                Some((low, high)) => (
+                    [0, 1, 2].map(|k| low[k].min(min[k])),
+                    [0, 1, 2].map(|k| high[k].max(max[k])),
+                ),
+            });
+        }
+        bounds.map(|([x, y, z], [u, v, w])| (Position { x, y, z }, Position { x: u, y: v, z: w }))
+    }
+
+    /// World space bounds of the voxels of every shape node, as computed by
+    /// this crate. Meant to compare placement against other engines.
+    #[cfg(feature = "test-util")]
+    pub fn debug_expected(&self) -> Vec<NodeBounds> {
+        self.node_bounds()
+    }
+
+    #[cfg(any(test, feature = "test-util"))]
+    pub(crate) fn node_bounds(&self) -> Vec<NodeBounds> {
+        self.placed_models()
+            .into_iter()
+            .filter(|placed| placed.node_id != u32::MAX)
+            .filter_map(|placed| {
+                let model = self.models.get(placed.model_id as usize)?;
+                let mut positions = model
+                    .voxels
+                    .iter()
+                    .map(|voxel| placed.voxel_position(model.size, voxel));
+                let first = positions.next()?;
+                let (min, max) = positions.fold((first, first), |(min, max), p| {
+                    (
+                        [min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+                        [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])],
+                    )
+                });
+                Some(NodeBounds {
+                    node_id: placed.node_id,
+                    model_id: placed.model_id,
+                    min,
+                    max,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Inclusive world space bounds of the voxels placed by a shape node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeBounds {
+    /// Index of the shape node in [`DotVoxData::scenes`].
+    pub node_id: u32,
+    /// Index of the model in [`DotVoxData::models`].
+    pub model_id: u32,
+    /// Coordinates of the lowest voxel along each axis.
+    pub min: [i32; 3],
+    /// Coordinates of the highest voxel along each axis.
+    pub max: [i32; 3],
+}
+
+/// Scatters instances of `prefab` across `region`, e.g. to decorate terrain,
+/// and returns the ids of the new transform nodes.
+///
+/// `density` is the number of instances per voxel of the region's XY
+/// footprint. The footprint is split into square cells holding one instance
+/// each, jittered within its cell, at a random height within the region and
+/// turned by a random multiple of 90° around the Z axis. The same `seed`
+/// always produces the same placements. A `density` that is not positive
+/// fails with [`BuildError::InvalidDensity`].
+///
+/// The model is added to `data` once, as with
+/// [`Prefab::instantiate_into`](crate::Prefab::instantiate_into), and every
+/// instance is placed under the root group node by its own transform node.
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let source = load("src/resources/placeholder.vox").unwrap();
+/// let prefab = Prefab::from_model(&source, 0).unwrap();
+/// let mut data = load("src/resources/placeholder.vox").unwrap();
+/// let region = Position { x: 0, y: 0, z: 0 }..=Position { x: 99, y: 99, z: 0 };
+/// let nodes = scatter(&mut data, &prefab, region, 0.01, 42).unwrap();
+/// assert_eq!(nodes.len(), 100);
+/// assert_eq!(data.instances().count(), 101);
+/// ```
+pub fn scatter(
+    data: &mut DotVoxData,
+    prefab: &crate::Prefab,
+    region: RangeInclusive<Position>,
+    density: f32,
+    seed: u64,
+) -> Result<Vec<u32>, BuildError> {
+    if !density.is_finite() || density <= 0.0 {
+        return Err(BuildError::InvalidDensity);
+    }
+    let (min, max) = (*region.start(), *region.end());
+    if min.x > max.x || min.y > max.y || min.z > max.z {
+        return Ok(Vec::new());
+    }
+
+    // Rotation by 90° around the Z axis.
+    let quarter_turn = Rotation::from_byte(0b0010001);
+    let spacing = 1.0 / (density as f64).sqrt();
+    let cells = |min: i32, max: i32| ((max as f64 - min as f64 + 1.0) / spacing).ceil() as i64;
+    let mut random = SplitMix64(seed);
+    let mut placements = Vec::new();
+    for j in 0..cells(min.y, max.y) {
+        for i in 0..cells(min.x, max.x) {
+            let x = min.x as f64 + ((i as f64 + random.next_f64()) * spacing).floor();
+            let y = min.y as f64 + ((j as f64 + random.next_f64()) * spacing).floor();
+            let z =
+                min.z as f64 + (random.next_f64() * (max.z as f64 - min.z as f64 + 1.0)).floor();
+            let turns = random.next_u64() % 4;
+            if x > max.x as f64 || y > max.y as f64 {
+                continue;
+            }
+            let rotation =
+                (0..turns).fold(Rotation::IDENTITY, |rotation, _| quarter_turn * rotation);
+            let translation = Position {
+                x: x as i32,
+                y: y as i32,
+                z: z as i32,
+            };
+            placements.push((translation, rotation));
+        }
+    }
+    if placements.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let root_group = prefab::root_group(data)?;
+    let model_id = prefab.add_model(data);
+    Ok(placements
+        .into_iter()
+        .map(|(translation, rotation)| {
+            prefab::place_model(data, root_group, model_id, translation, rotation)
+        })
+        .collect())
+}
+
+/// SplitMix64 pseudo-random number generator, small and stable across
+/// platforms and releases so that seeded results stay reproducible.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in `0.0..1.0`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Prefab;
+
+    fn scattered(seed: u64) -> (DotVoxData, Vec<u32>) {
+        let source = crate::load("src/resources/placeholder.vox").unwrap();
+        let prefab = Prefab::from_model(&source, 0).unwrap();
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        let region = Position {
+            x: -20,
+            y: 10,
+            z: 0,
+        }..=Position { x: 19, y: 49, z: 5 };
+        let nodes = scatter(&mut data, &prefab, region, 0.05, seed).unwrap();
+        (data, nodes)
+    }
+
+    #[test]
+    fn scatter_is_reproducible() {
+        let (first, nodes) = scattered(7);
+        let (second, _) = scattered(7);
+        let (other, _) = scattered(8);
+        assert_eq!(first, second);
+        assert_ne!(first.scenes, other.scenes);
+        assert_eq!(first.models.len(), 2);
+        // About 40 × 40 × 0.05 = 80 instances, at most one per cell of the
+        // 9 × 9 cells of side 1 / sqrt(0.05) ≈ 4.47 voxels.
+        assert!((70..=81).contains(&nodes.len()), "{}", nodes.len());
+    }
+
+    #[test]
+    fn scatter_stays_within_region() {
+        let (data, nodes) = scattered(3);
+        let instances: Vec<_> = data.instances().skip(1).collect();
+        assert_eq!(instances.len(), nodes.len());
+        let mut turns = std::collections::HashSet::new();
+        for instance in instances {
+            assert_eq!(instance.model_id, 1);
+            let Position { x, y, z } = instance.translation;
+            assert!((-20..=19).contains(&x) && (10..=49).contains(&y) && (0..=5).contains(&z));
+            assert_eq!(instance.rotation.transform_point([0, 0, 1]), [0, 0, 1]);
+            turns.insert(instance.rotation.to_byte());
+        }
+        assert_eq!(turns.len(), 4);
+    }
+
+    #[test]
+    fn scatter_rejects_invalid_density() {
+        let (mut data, _) = scattered(0);
+        let prefab = Prefab::from_model(&data, 0).unwrap();
+        let region = Position { x: 0, y: 0, z: 0 }..=Position { x: 9, y: 9, z: 0 };
+        assert_eq!(
+            scatter(&mut data, &prefab, region.clone(), 0.0, 0),
+            Err(BuildError::InvalidDensity)
+        );
+        assert_eq!(
+            scatter(&mut data, &prefab, region, f32::NAN, 0),
+            Err(BuildError::InvalidDensity)
+        );
+    }
+
+    /// The placeholder file, with its group node replaced by a chain of 40
+    /// groups, each listing itself twice before the next group. The last one
+    /// leads to the original transform and shape nodes.
+    pub(crate) fn self_referencing_groups() -> DotVoxData {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        let first = data.scenes.len() as u32;
+        for id in first..first + 40 {
+            let next = if id + 1 == first + 40 { 2 } else { id + 1 };
+            data.scenes.push(SceneNode::Group {
+                attributes: Dict::new(),
+                children: vec![id, id, next],
+            });
+        }
+        if let SceneNode::Group { children, .. } = &mut data.scenes[1] {
+            *children = vec![first];
+        }
+        data
+    }
+
+    #[test]
+    fn self_referencing_groups_are_walked_once() {
+        let data = self_referencing_groups();
+        assert_eq!(data.instances().len(), 1);
+        assert_eq!(data.instances().rev().count(), 1);
+        assert_eq!(data.world_voxels().count(), data.models[0].voxels.len());
+        assert_eq!(data.placed_models().len(), 1);
+    }
+
+    #[test]
+    fn visibility_of_self_referencing_groups() {
+        let mut data = self_referencing_groups();
+        assert!(data.is_visible(3));
+        assert!(!data.is_visible(data.scenes.len() as u32));
+        data.scenes[3].set_hidden(true);
+        assert!(!data.is_visible(3));
+    }
+
+    #[test]
+    fn legacy_files_get_a_synthetic_scene() {
+        let mut legacy = crate::load("src/resources/transforms.vox").unwrap();
+        legacy.scenes.clear();
+        legacy.layers.clear();
+        let mut bytes = Vec::new();
+        legacy.write_vox(&mut bytes).unwrap();
+
+        let data = crate::load_bytes(&bytes).unwrap();
+        assert!(data.synthetic_scene);
+        assert_eq!(data.scenes.len(), 2 + 2 * data.models.len());
+        assert!(data.equivalent(&legacy));
+        let voxels: Vec<_> = data.world_voxels().collect();
+        assert_eq!(voxels, legacy.world_voxels().collect::<Vec<_>>());
+
+        // The synthetic scene graph is not written back.
+        let mut written = Vec::new();
+        data.write_vox(&mut written).unwrap();
+        assert_eq!(written, bytes);
+
+        let options = crate::ParseOptions::default().synthesize_scene(false);
+        let unsynthesized = crate::load_bytes_with(&bytes, options).unwrap();
+        assert!(unsynthesized.scenes.is_empty() && !unsynthesized.synthetic_scene);
+    }
+
+    #[test]
+    fn placing_models_makes_the_scene_real() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.scenes.clear();
+        assert!(data.synthesize_scene());
+        let prefab = crate::Prefab::from_model(&data, 0).unwrap();
+        prefab
+            .instantiate_into(
+                &mut data,
+                Transform {
+                    rotation: Rotation::IDENTITY,
+                    translation: Position { x: 5, y: 0, z: 0 },
+                },
+            )
+            .unwrap();
+        assert!(!data.synthetic_scene);
+        assert_eq!(data.instances().len(), 2);
+    }
+
+    #[test]
+    fn hidden_nodes_layers_and_groups_hide_their_models() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        let Some(SceneNode::Group { children, .. }) = data.scenes.get(1) else {
+            panic!("Expected the root group");
+        };
+        let children = children.clone();
+        assert_eq!(data.instances().len(), 3);
+        assert!(children.iter().all(|&child| data.is_visible(child)));
+
+        // A hidden transform hides its model.
+        let mut hidden_node = data.clone();
+        if let SceneNode::Transform { attributes, .. } =
+            &mut hidden_node.scenes[children[0] as usize]
+        {
+            attributes.insert("_hidden".to_owned(), "1".to_owned());
+        }
+        assert!(!hidden_node.is_visible(children[0]));
+        assert_eq!(hidden_node.instances().len(), 2);
+
+        // So does the hidden layer of a transform.
+        let mut hidden_layer = data.clone();
+        let mut attributes = Dict::new();
+        attributes.insert("_hidden".to_owned(), "1".to_owned());
+        hidden_layer.layers.push(Layer { attributes });
+        let hidden_id = hidden_layer.layers.len() as u32 - 1;
+        if let SceneNode::Transform { layer_id, .. } =
+            &mut hidden_layer.scenes[children[1] as usize]
+        {
+            *layer_id = hidden_id;
+        }
+        assert!(!hidden_layer.is_visible(children[1]));
+        assert!(hidden_layer.is_visible(children[0]));
+        assert_eq!(
+            hidden_layer.world_voxels().len(),
+            data.world_voxels().len() - 32
+        );
+
+        // A hidden group hides everything below it.
+        let mut hidden_group = data.clone();
+        if let SceneNode::Group { attributes, .. } = &mut hidden_group.scenes[1] {
+            attributes.insert("_hidden".to_owned(), "1".to_owned());
+        }
+        assert!(children
+            .iter()
+            .all(|&child| !hidden_group.is_visible(child)));
+        assert_eq!(hidden_group.instances().len(), 0);
+        assert!(hidden_group.gpu_instances().is_empty());
+    }
+
+    #[test]
+    fn typed_frame_setters_round_trip() {
+        for rotation in Rotation::all() {
+            let frame = Frame::default().with_orientation(rotation);
+            assert_eq!(frame.orientation(), Some(rotation));
+        }
+        let position = Position {
+            x: i32::MIN,
+            y: 0,
+            z: i32::MAX,
+        };
+        let frame = Frame::default()
+            .with_position(position)
+            .with_frame_index(u32::MAX);
+        assert_eq!(frame.position(), Some(position));
+        assert_eq!(frame.frame_index(), Some(u32::MAX));
+        assert_eq!(frame.transform().translation, position);
+    }
+
+    #[test]
+    fn visit_scene_uses_the_keyframe_in_effect() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        let keyframe = |index: Option<u32>, x: i32| {
+            let frame = Frame::default().with_position(Position { x, y: 0, z: 0 });
+            match index {
+                Some(index) => frame.with_frame_index(index),
+                None => frame,
+            }
+        };
+        if let SceneNode::Transform { frames, .. } = &mut data.scenes[2] {
+            *frames = vec![
+                keyframe(Some(4), 40),
+                keyframe(None, 0),
+                keyframe(Some(10), 100),
+                keyframe(Some(2), 20),
+            ];
+        }
+        let at = |frame: u32| {
+            let mut translations = Vec::new();
+            data.visit_scene_at(frame, |_, translation, _, layer_id| {
+                assert_eq!(layer_id, 0);
+                translations.push(translation.x);
+            });
+            translations
+        };
+        assert_eq!(at(0), [0]);
+        assert_eq!(at(1), [0]);
+        assert_eq!(at(3), [20]);
+        assert_eq!(at(9), [40]);
+        assert_eq!(at(u32::MAX), [100]);
+
+        let mut visited = 0;
+        data.visit_scene(|_, translation, _, _| {
+            visited += 1;
+            assert_eq!(translation.x, 0);
+        });
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn visit_scene_reports_the_closest_layer() {
+        let mut scene = crate::SceneBuilder::new();
+        let group = scene.add_group(crate::SceneBuilder::ROOT).unwrap();
+        let outer = scene.add_shape(crate::SceneBuilder::ROOT, 0).unwrap();
+        let inner = scene.add_shape(group, 0).unwrap();
+        scene.set_layer(group, 1);
+        scene.set_layer(outer, 2);
+        scene.set_translation(group, Position { x: 5, y: 0, z: 0 });
+        scene.set_rotation(group, Rotation::from_byte(0b0010001));
+        scene.set_translation(inner, Position { x: 1, y: 2, z: 3 });
+
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.layers.truncate(3);
+        data.scenes = scene.build();
+        let mut visited = Vec::new();
+        data.visit_scene(|_, translation, rotation, layer_id| {
+            visited.push((translation, rotation, layer_id))
+        });
+        let group_transform = Transform {
+            rotation: Rotation::from_byte(0b0010001),
+            translation: Position { x: 5, y: 0, z: 0 },
+        };
+        assert_eq!(
+            visited,
+            [
+                (
+                    group_transform.transform_point(Position { x: 1, y: 2, z: 3 }),
+                    group_transform.rotation,
+                    u32::MAX
+                ),
+                (Position { x: 0, y: 0, z: 0 }, Rotation::IDENTITY, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn bounding_box_covers_every_model() {
+        let data = crate::load("src/resources/transforms.vox").unwrap();
+        let (min, max) = data.bounding_box().unwrap();
+        for voxel in data.world_voxels() {
+            let p = voxel.position;
+            assert!(min.x <= p.x && min.y <= p.y && min.z <= p.z);
+            assert!(p.x <= max.x && p.y <= max.y && p.z <= max.z);
+        }
+
+        // A rotated model filled at its corners has the same bounds as its
+        // voxels.
+        let corners = Model {
+            size: Size { x: 3, y: 4, z: 5 },
+            voxels: vec![
+                Voxel {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                    i: 1,
+                },
+                Voxel {
+                    x: 2,
+                    y: 3,
+                    z: 4,
+                    i: 1,
+                },
+            ],
+        };
+        let data = DotVoxData::builder()
+            .add_model_at(
+                corners,
+                Position { x: 7, y: -2, z: 3 },
+                Rotation::from_byte(0b0010001),
+            )
+            .build();
+        let voxels: Vec<_> = data.world_voxels().map(|voxel| voxel.position).collect();
+        let (min, max) = data.bounding_box().unwrap();
+        let low = |f: fn(&Position) -> i32| voxels.iter().map(f).min().unwrap();
+        let high = |f: fn(&Position) -> i32| voxels.iter().map(f).max().unwrap();
+        assert_eq!(
+            min,
+            Position {
+                x: low(|p| p.x),
+                y: low(|p| p.y),
+                z: low(|p| p.z)
+            }
+        );
+        assert_eq!(
+            max,
+            Position {
+                x: high(|p| p.x),
+                y: high(|p| p.y),
+                z: high(|p| p.z)
+            }
+        );
+
+        assert_eq!(DotVoxData::new().bounding_box(), None);
+    }
+
+    #[test]
+    fn keyframes_are_interpolated_and_stepped() {
+        let turn = Rotation::from_byte(0b0010001);
+        // Out of order, with the second keyframe left without a rotation.
+        let frames = [
+            Frame::default()
+                .with_position(Position { x: 20, y: 0, z: 0 })
+                .with_orientation(turn)
+                .with_frame_index(10),
+            Frame::default()
+                .with_position(Position { x: 0, y: 0, z: 8 })
+                .with_frame_index(2),
+            Frame::default(),
+        ];
+        assert_eq!(Frame::position_at(&frames, -1.0), Some([0.0, 0.0, 0.0]));
+        assert_eq!(Frame::position_at(&frames, 1.0), Some([0.0, 0.0, 4.0]));
+        assert_eq!(Frame::position_at(&frames, 2.0), Some([0.0, 0.0, 8.0]));
+        assert_eq!(Frame::position_at(&frames, 6.0), Some([10.0, 0.0, 4.0]));
+        assert_eq!(Frame::position_at(&frames, 30.0), Some([20.0, 0.0, 0.0]));
+
+        assert_eq!(
+            Frame::orientation_at(&frames, 9.9),
+            Some(Rotation::IDENTITY)
+        );
+        assert_eq!(Frame::orientation_at(&frames, 10.0), Some(turn));
+        assert_eq!(Frame::keyframe_at(&frames, 3), Some(&frames[1]));
+        assert_eq!(Frame::keyframe_at(&frames, 0), Some(&frames[2]));
+
+        assert_eq!(Frame::position_at(&[], 0.0), None);
+        assert_eq!(Frame::orientation_at(&[], 0.0), None);
+    }
+
+    #[test]
+    fn scene_node_names_and_visibility() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        for node in data.scenes.iter_mut() {
+            node.set_name("node");
+            node.set_hidden(true);
+            assert_eq!(node.name().as_deref(), Some("node"));
+            assert!(node.hidden());
+            node.set_hidden(false);
+            assert!(!node.hidden());
+            assert!(!node.attributes().contains_key("_hidden"));
+        }
+        assert!(data.is_visible(3));
+    }
+
+    #[test]
+    fn invalid_orientations_are_ignored() {
+        for value in ["0", "15", "132", "255", "-1", "r"] {
+            let mut frame = Frame::default();
+            frame.attributes.insert("_r".to_owned(), value.to_owned());
+            assert_eq!(frame.orientation(), None, "{value}");
+        }
+        let frame = Frame::default().with_orientation(Rotation::from_byte(0b0010001));
+        assert_eq!(frame.orientation(), Some(Rotation::from_byte(0b0010001)));
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{prefab, Dict, DotVoxData, Model, SceneNode, ShapeModel, Size, Voxel};
+
+/// A part of an oversized model, at `origin` within it.
+struct Tile {
+    origin: [u32; 3],
+    model: Model,
+}
+
+impl DotVoxData {
+    /// Splits every model larger than [`Size::MAX_AXIS`] along some axis,
+    /// which MagicaVoxel cannot open, into models that fit. Shape nodes
+    /// placing a split model become group nodes placing its parts, so that
+    /// every voxel stays at the same world position, including under
+    /// rotations. A scene graph is created first if there is none, or if it is
+    /// [synthetic](DotVoxData::synthetic_scene).
+    ///
+    /// Parts without voxels are left out, except the first one, which takes
+    /// the place of the split model in [`DotVoxData::models`]. The other parts
+    /// are appended. Returns the number of models split.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut data = load("src/resources/placeholder.vox").unwrap();
+    /// data.models[0].size = Size { x: 600, y: 1, z: 1 };
+    /// data.models[0].voxels = vec![Voxel { x: 255, y: 0, z: 0, i: 1 }];
+    /// let before: Vec<_> = data.world_voxels().map(|voxel| voxel.position).collect();
+    ///
+    /// assert_eq!(data.split_oversized_models(), 1);
+    /// assert!(data.models.iter().all(|model| model.size.is_within_limits()));
+    /// let after: Vec<_> = data.world_voxels().map(|voxel| voxel.position).collect();
+    /// assert_eq!(after, before);
+    /// ```
+    pub fn split_oversized_models(&mut self) -> usize {
+        if self
+            .models
+            .iter()
+            .all(|model| model.size.is_within_limits())
+        {
+            return 0;
+        }
+        self.synthesize_scene();
+        self.synthetic_scene = false;
+
+        let mut split = 0;
+        for model_id in 0..self.models.len() {
+            let size = self.models[model_id].size;
+            if size.is_within_limits() {
+                continue;
+            }
+            let mut tiles = tiles(&self.models[model_id]).into_iter();
+            let first = tiles.next().expect("every model has a first tile");
+            let mut parts = vec![(model_id as u32, first.origin, first.model.size)];
+            self.models[model_id] = first.model;
+            for tile in tiles {
+                parts.push((self.models.len() as u32, tile.origin, tile.model.size));
+                self.models.push(tile.model);
+            }
+            self.place_parts(model_id as u32, size, &parts);
+            split += 1;
+        }
+        split
+    }
+
+    /// Replaces each shape node placing `model_id`, of the given size before
+    /// splitting, with a group node placing its parts.
+    fn place_parts(&mut self, model_id: u32, size: Size, parts: &[(u32, [u32; 3], Size)]) {
+        for node_id in 0..self.scenes.len() {
+            let SceneNode::Shape { attributes, models } = &self.scenes[node_id] else {
+                continue;
+            };
+            if !models.iter().any(|model| model.model_id == model_id) {
+                continue;
+            }
+            let (attributes, models) = (attributes.clone(), models.clone());
+            let layer_id = self
+                .scenes
+                .iter()
+                .find_map(|node| match node {
+                    SceneNode::Transform {
+                        child, layer_id, ..
+                    } if *child as usize == node_id => Some(*layer_id),
+                    _ => None,
+                })
+                .unwrap_or(u32::MAX);
+
+            let mut children = Vec::new();
+            for &(part_id, origin, part_size) in parts {
+                // Offsets from the center of the model to the center of the
+                // part are whole voxels, as the parts have the same parity as
+                // the model along every axis.
+                let offset = |axis: usize, part: u32, whole: u32| {
+                    (2 * origin[axis] as i32 + part as i32 - whole as i32) / 2
+                };
+                let mut frame = Dict::new();
+                frame.insert(
+                    "_t".to_owned(),
+                    format!(
+                        "{} {} {}",
+                        offset(0, part_size.x, size.x),
+                        offset(1, part_size.y, size.y),
+                        offset(2, part_size.z, size.z)
+                    ),
+                );
+                let transform_id = self.scenes.len() as u32;
+                children.push(transform_id);
+                self.scenes
+                    .push(prefab::transform_node(frame, transform_id + 1, layer_id));
+                let models = models
+                    .iter()
+                    .map(|model| ShapeModel {
+                        model_id: if model.model_id == model_id {
+                            part_id
+                        } else {
+                            model.model_id
+                        },
+                        attributes: model.attributes.clone(),
+                    })
+                    .collect();
+                self.scenes.push(SceneNode::Shape {
+                    attributes: Dict::new(),
+                    models,
+                });
+            }
+            self.scenes[node_id] = SceneNode::Group {
+                attributes,
+                children,
+            };
+        }
+    }
+}
+
+/// Splits `model` into tiles that fit in [`Size::MAX_AXIS`], keeping only the
+/// first tile and those with voxels.
+///
+/// Along an axis of odd size, tiles are one voxel smaller, and the last one
+/// is padded if needed, so that every tile has the parity of the model. This
+/// keeps the offsets between their centers whole under any rotation.
+fn tiles(model: &Model) -> Vec<Tile> {
+    let axis_tiles = |size: u32| -> Vec<(u32, u32)> {
+        let stride = if size % 2 == 0 {
+            Size::MAX_AXIS
+        } else {
+            Size::MAX_AXIS - 1
+        };
+        (0..size.div_ceil(stride).max(1))
+            .map(|i| {
+                let origin = i * stride;
+                let len = stride.min(size - origin);
+                let padded = if len % 2 == size % 2 { len } else { len + 1 };
+                (origin, padded)
+            })
+            .collect()
+    };
+    let (xs, ys, zs) = (
+        axis_tiles(model.size.x),
+        axis_tiles(model.size.y),
+        axis_tiles(model.size.z),
+    );
+
+    let mut tiles = Vec::new();
+    for &(oz, sz) in zs.iter() {
+        for &(oy, sy) in ys.iter() {
+            for &(ox, sx) in xs.iter() {
+                let within =
+                    |v: u8, origin: u32, len: u32| (origin..origin + len).contains(&(v as u32));
+                let voxels: Vec<Voxel> = model
+                    .voxels
+                    .iter()
+                    .filter(|v| within(v.x, ox, sx) && within(v.y, oy, sy) && within(v.z, oz, sz))
+                    .map(|v| Voxel {
+                        x: v.x - ox as u8,
+                        y: v.y - oy as u8,
+                        z: v.z - oz as u8,
+                        i: v.i,
+                    })
+                    .collect();
+                if voxels.is_empty() && !tiles.is_empty() {
+                    continue;
+                }
+                tiles.push(Tile {
+                    origin: [ox, oy, oz],
+                    model: Model {
+                        size: Size {
+                            x: sx,
+                            y: sy,
+                            z: sz,
+                        },
+                        voxels,
+                    },
+                });
+            }
+        }
+    }
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn world(data: &DotVoxData) -> Vec<(Position, u8)> {
+        let mut voxels: Vec<_> = data
+            .world_voxels()
+            .map(|voxel| (voxel.position, voxel.i))
+            .collect();
+        voxels.sort_by_key(|(position, i)| (position.x, position.y, position.z, *i));
+        voxels
+    }
+
+    #[test]
+    fn split_models_keep_their_voxels_in_place() {
+        for size in [
+            Size { x: 300, y: 2, z: 2 },
+            Size { x: 2, y: 511, z: 3 },
+            Size {
+                x: 257,
+                y: 257,
+                z: 513,
+            },
+        ] {
+            let last = |axis: u32| (axis.min(Size::MAX_AXIS) - 1) as u8;
+            let (x, y, z) = (last(size.x), last(size.y), last(size.z));
+            let mut data = crate::load("src/resources/transforms.vox").unwrap();
+            data.models[0] = Model {
+                size,
+                voxels: vec![
+                    Voxel {
+                        x: 0,
+                        y: 0,
+                        z: 0,
+                        i: 1,
+                    },
+                    Voxel {
+                        x: 1,
+                        y: 1,
+                        z: 1,
+                        i: 2,
+                    },
+                    Voxel {
+                        x,
+                        y: 0,
+                        z: 1,
+                        i: 3,
+                    },
+                    Voxel { x, y, z, i: 4 },
+                ],
+            };
+            let before = world(&data);
+            assert_eq!(data.split_oversized_models(), 1);
+            assert!(data.validate().is_empty());
+            assert_eq!(world(&data), before, "{:?}", size);
+        }
+    }
+
+    #[test]
+    fn models_without_scene_graph_are_placed_first() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.scenes.clear();
+        data.models[0].size = Size { x: 1, y: 1, z: 400 };
+        data.models[0].voxels = vec![Voxel {
+            x: 0,
+            y: 0,
+            z: 255,
+            i: 1,
+        }];
+        let mut reference = data.clone();
+        reference.synthesize_scene();
+        let before = world(&reference);
+
+        let options = crate::WriteOptions {
+            split_oversized_models: true,
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        data.write_vox_with(&mut bytes, options).unwrap();
+        assert_eq!(world(&crate::load_bytes(&bytes).unwrap()), before);
+
+        assert_eq!(data.split_oversized_models(), 1);
+        assert!(!data.synthetic_scene);
+        assert_eq!(data.models[0].size, Size { x: 1, y: 1, z: 256 });
+        assert_eq!(world(&data), before);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::{DotVoxData, Model, Size, Voxel};
+
+#[cfg(feature = "ahash")]
+use ahash::{AHashMap as HashMap, AHashSet as HashSet};
+
+#[cfg(not(feature = "ahash"))]
+use std::collections::{HashMap, HashSet};
+
+/// Offsets to the six face neighbors of a voxel.
+pub(crate) const NEIGHBORS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+/// Dense occupancy bitmap of a model, for constant time neighbor lookups.
+pub(crate) struct Occupancy {
+    size: Size,
+    bits: Vec<u64>,
+}
+
+impl Occupancy {
+    pub fn new(model: &Model) -> Self {
+        let size = model.size;
+        let len = size.x as usize * size.y as usize * size.z as usize;
+        let mut occupancy = Occupancy {
+            size,
+            bits: vec![0; len.div_ceil(64)],
+        };
+        for voxel in model.voxels.iter() {
+            if let Some(index) = occupancy.index(voxel.x as i32, voxel.y as i32, voxel.z as i32) {
+                occupancy.bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+        occupancy
+    }
+
+    /// Whether the given position is occupied. Positions outside of the model
+    /// are empty.
+    pub fn contains(&self, x: i32, y: i32, z: i32) -> bool {
+        match self.index(x, y, z) {
+            Some(index) => self.bits[index / 64] & (1 << (index % 64)) != 0,
+            None => false,
+        }
+    }
+
+    /// Whether all six neighbors of the given position are occupied.
+    pub fn is_enclosed(&self, x: i32, y: i32, z: i32) -> bool {
+        NEIGHBORS
+            .iter()
+            .all(|[dx, dy, dz]| self.contains(x + dx, y + dy, z + dz))
+    }
+
+    fn index(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        let (x, y, z) = (
+            u32::try_from(x).ok()?,
+            u32::try_from(y).ok()?,
+            u32::try_from(z).ok()?,
+        );
+        if x >= self.size.x || y >= self.size.y || z >= self.size.z {
+            return None;
+        }
+        Some((z as usize * self.size.y as usize + y as usize) * self.size.x as usize + x as usize)
+    }
+}
+
+impl Model {
+    /// Computes which faces of each voxel are exposed, i.e. not covered by
+    /// another voxel of the model, in the order of [`Model::voxels`].
+    ///
+    /// Bits 0 to 5 of each mask are set for exposed faces facing +X, -X, +Y,
+    /// -Y, +Z and -Z respectively. Voxels with a mask of 0 are hidden.
+    pub fn face_visibility(&self) -> Vec<u8> {
+        let occupancy = Occupancy::new(self);
+        self.voxels
+            .iter()
+            .map(|voxel| {
+                let (x, y, z) = (voxel.x as i32, voxel.y as i32, voxel.z as i32);
+                NEIGHBORS
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, [dx, dy, dz])| !occupancy.contains(x + dx, y + dy, z + dz))
+                    .fold(0, |mask, (face, _)| mask | 1 << face)
+            })
+            .collect()
+    }
+}
+
+/// Removes the voxels of a model whose six neighbors within the model are all
+/// occupied.
+pub(crate) fn strip_interior(model: &mut Model) {
+    let occupancy = Occupancy::new(model);
+    model
+        .voxels
+        .retain(|voxel| !occupancy.is_enclosed(voxel.x as i32, voxel.y as i32, voxel.z as i32));
+}
+
+impl DotVoxData {
+    /// Removes every voxel that cannot be seen because all six of its
+    /// neighbors are occupied by opaque voxels, taking the placement of models
+    /// in the scene graph into account. Equivalent to
+    /// [`strip_hidden_with(true)`](DotVoxData::strip_hidden_with).
+    ///
+    /// Returns the number of voxels removed.
+    pub fn strip_hidden(&mut self) -> usize {
+        self.strip_hidden_with(true)
+    }
+
+    /// Removes every voxel that cannot be seen because all six of its
+    /// neighbors are occupied, taking the placement of models in the scene
+    /// graph into account.
+    ///
+    /// When `respect_transparency` is set, voxels with a transparent palette
+    /// color or a glass, blend or cloud material do not hide their neighbors.
+    /// A model instanced several times only loses the voxels hidden in every
+    /// instance.
+    ///
+    /// Returns the number of voxels removed.
+    pub fn strip_hidden_with(&mut self, respect_transparency: bool) -> usize {
+        let placed = self.placed_models();
+
+        let mut opaque = HashSet::new();
+        for instance in placed.iter() {
+            let Some(model) = self.models.get(instance.model_id as usize) else {
+                continue;
+            };
+            for voxel in model.voxels.iter() {
+                if !respect_transparency || self.is_opaque(voxel) {
+                    opaque.insert(instance.voxel_position(model.size, voxel));
+                }
+            }
+        }
+
+        // Number of instances in which each voxel is hidden, keyed by model and
+        // voxel index.
+        let mut hidden: HashMap<(u32, usize), usize> = HashMap::new();
+        let mut instance_counts: HashMap<u32, usize> = HashMap::new();
+        for instance in placed.iter() {
+            let Some(model) = self.models.get(instance.model_id as usize) else {
+                continue;
+            };
+            *instance_counts.entry(instance.model_id).or_default() += 1;
+            for (index, voxel) in model.voxels.iter().enumerate() {
+                let [x, y, z] = instance.voxel_position(model.size, voxel);
+                if NEIGHBORS
+                    .iter()
+                    .all(|[dx, dy, dz]| opaque.contains(&[x + dx, y + dy, z + dz]))
+                {
+                    *hidden.entry((instance.model_id, index)).or_default() += 1;
+                }
+            }
+        }
+
+        let mut removed = 0;
+        for (model_id, model) in self.models.iter_mut().enumerate() {
+            let model_id = model_id as u32;
+            let Some(&count) = instance_counts.get(&model_id) else {
+                continue;
+            };
+            let mut index = 0;
+            model.voxels.retain(|_| {
+                let keep = hidden.get(&(model_id, index)) != Some(&count);
+                index += 1;
+                keep
+            });
+            removed += index - model.voxels.len();
+        }
+
+        removed
+    }
+
+    /// Whether a voxel blocks the view of its neighbors.
+    pub(crate) fn is_opaque(&self, voxel: &Voxel) -> bool {
+        if let Some(color) = self.palette.get(voxel.i as usize) {
+            if color.a < u8::MAX {
+                return false;
+            }
+        }
+
+        let material = self
+            .materials
+            .iter()
+            .find(|material| material.id == voxel.i as u32 + 1);
+        match material {
+            Some(material) => {
+                !matches!(
+                    material.material_type(),
+                    Some("_glass") | Some("_blend") | Some("_cloud")
+                ) && material.transparency().unwrap_or(0.0) <= 0.0
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Model, Size, DEFAULT_PALETTE};
+
+    fn cube(side: u8, i: u8) -> Model {
+        let mut voxels = Vec::new();
+        for x in 0..side {
+            for y in 0..side {
+                for z in 0..side {
+                    voxels.push(Voxel { x, y, z, i });
+                }
+            }
+        }
+        Model {
+            size: Size {
+                x: side as u32,
+                y: side as u32,
+                z: side as u32,
+            },
+            voxels,
+        }
+    }
+
+    fn data(models: Vec<Model>) -> DotVoxData {
+        DotVoxData {
+            version: 150,
+            models,
+            palette: DEFAULT_PALETTE.to_vec(),
+            materials: Vec::new(),
+            scenes: Vec::new(),
+            synthetic_scene: false,
+            layers: Vec::new(),
+            palette_extension: None,
+            index_map: None,
+            render_objects: Vec::new(),
+            cameras: Vec::new(),
+            extension_chunks: Vec::new(),
+            unknown_chunks: Vec::new(),
+            chunk_order: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn strips_interior_of_solid_cube() {
+        let mut data = data(vec![cube(3, 0)]);
+        assert_eq!(data.strip_hidden(), 1);
+        assert_eq!(data.models[0].voxels.len(), 26);
+        assert!(!data.models[0].voxels.contains(&Voxel {
+            x: 1,
+            y: 1,
+            z: 1,
+            i: 0
+        }));
+    }
+
+    #[test]
+    fn transparent_neighbors_do_not_hide() {
+        let mut data = data(vec![cube(3, 0)]);
+        data.palette[0].a = 128;
+        assert_eq!(data.strip_hidden_with(true), 0);
+        assert_eq!(data.strip_hidden_with(false), 1);
+    }
+
+    #[test]
+    fn neighboring_models_hide_each_other() {
+        use crate::{Dict, Frame, SceneNode, ShapeModel};
+
+        let transform = |translation: &str, child| SceneNode::Transform {
+            attributes: Dict::new(),
+            frames: vec![Frame::new(
+                [("_t".to_owned(), translation.to_owned())]
+                    .into_iter()
+                    .collect(),
+            )],
+            child,
+            layer_id: 0,
+        };
+        let shape = |model_id| SceneNode::Shape {
+            attributes: Dict::new(),
+            models: vec![ShapeModel {
+                model_id,
+                attributes: Dict::new(),
+            }],
+        };
+
+        let mut data = data(vec![cube(3, 0), cube(3, 0)]);
+        data.scenes = vec![
+            transform("0 0 0", 1),
+            SceneNode::Group {
+                attributes: Dict::new(),
+                children: vec![2, 4],
+            },
+            transform("0 0 0", 3),
+            shape(0),
+            transform("3 0 0", 5),
+            shape(1),
+        ];
+        assert_eq!(data.strip_hidden(), 4);
+        assert!(!data.models[0].voxels.contains(&Voxel {
+            x: 2,
+            y: 1,
+            z: 1,
+            i: 0
+        }));
+        assert!(!data.models[1].voxels.contains(&Voxel {
+            x: 0,
+            y: 1,
+            z: 1,
+            i: 0
+        }));
+    }
+
+    #[test]
+    fn self_referencing_groups_strip_each_model_once() {
+        use crate::{Dict, Frame, SceneNode, ShapeModel};
+
+        // A chain of 40 groups, each listing itself twice before the next,
+        // the last one leading to a transform and shape node.
+        let mut data = data(vec![cube(3, 0)]);
+        data.scenes = vec![
+            SceneNode::Transform {
+                attributes: Dict::new(),
+                frames: vec![Frame::new(Dict::new())],
+                child: 3,
+                layer_id: 0,
+            },
+            SceneNode::Transform {
+                attributes: Dict::new(),
+                frames: vec![Frame::new(Dict::new())],
+                child: 2,
+                layer_id: 0,
+            },
+            SceneNode::Shape {
+                attributes: Dict::new(),
+                models: vec![ShapeModel {
+                    model_id: 0,
+                    attributes: Dict::new(),
+                }],
+            },
+        ];
+        for id in 3..43 {
+            let next = if id == 42 { 1 } else { id + 1 };
+            data.scenes.push(SceneNode::Group {
+                attributes: Dict::new(),
+                children: vec![id, id, next],
+            });
+        }
+        assert_eq!(data.strip_hidden(), 1);
+        assert_eq!(data.models[0].voxels.len(), 26);
+    }
+
+    #[test]
+    fn surface_only_parse_drops_interior_voxels() {
+        let mut buffer = Vec::new();
+        data(vec![cube(4, 0)]).write_vox(&mut buffer).unwrap();
+        let full = crate::load_bytes(&buffer).unwrap();
+        let surface =
+            crate::load_bytes_with(&buffer, crate::ParseOptions::default().surface_only(true))
+                .unwrap();
+        assert_eq!(full.models[0].voxels.len(), 64);
+        assert_eq!(surface.models[0].voxels.len(), 56);
+    }
+
+    #[test]
+    fn face_visibility_masks_exposed_faces() {
+        let model = cube(3, 0);
+        let masks = model.face_visibility();
+        assert_eq!(masks.len(), model.voxels.len());
+        for (voxel, mask) in model.voxels.iter().zip(masks) {
+            let expected = [
+                voxel.x == 2,
+                voxel.x == 0,
+                voxel.y == 2,
+                voxel.y == 0,
+                voxel.z == 2,
+                voxel.z == 0,
+            ]
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (face, exposed)| mask | (*exposed as u8) << face);
+            assert_eq!(mask, expected, "{:?}", voxel);
+        }
+
+        let single = Model {
+            size: Size { x: 1, y: 1, z: 1 },
+            voxels: vec![Voxel {
+                x: 0,
+                y: 0,
+                z: 0,
+                i: 0,
+            }],
+        };
+        assert_eq!(single.face_visibility(), [0b111111]);
+    }
+}
@@ -0,0 +1,52 @@
+//! Diagnostics with structured fields, emitted through `tracing` when the
+//! `tracing` feature is enabled, and through `log` otherwise.
+
+use std::fmt;
+
+/// Emits an event at `$level` with the given fields. With `tracing`, the
+/// fields are recorded as such; with `log`, they are appended to the message.
+///
+/// ```ignore
+/// event!(warn, { chunk_id = id, offset = offset }, "Unable to parse chunk");
+/// ```
+macro_rules! event {
+    ($level:ident, { $($field:ident = $value:expr),+ $(,)? }, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($field = %$value,)+ $($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::$level!(
+            "{}{}",
+            format_args!($($arg)+),
+            $crate::trace::Fields(&[$((stringify!($field), &$value as &dyn std::fmt::Display)),+])
+        );
+    }};
+}
+
+/// Fields of an [`event!`], formatted for `log` as ` (name=value, ...)`.
+#[cfg_attr(feature = "tracing", allow(dead_code))]
+pub(crate) struct Fields<'a>(pub &'a [(&'static str, &'a dyn fmt::Display)]);
+
+impl fmt::Display for Fields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            let separator = if i == 0 { " (" } else { ", " };
+            write!(f, "{}{}={}", separator, name, value)?;
+        }
+        if !self.0.is_empty() {
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_appended_to_log_messages() {
+        let fields = Fields(&[("chunk_id", &"XYZI"), ("offset", &12)]);
+        assert_eq!(fields.to_string(), " (chunk_id=XYZI, offset=12)");
+        assert_eq!(Fields(&[]).to_string(), "");
+    }
+}
@@ -1,3 +1,17 @@
+use crate::{Position, Size, Voxel};
+use std::ops::Mul;
+
+/// One of the three coordinate axes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// The X axis.
+    X,
+    /// The Y axis.
+    Y,
+    /// The Z axis, pointing up in MagicaVoxel.
+    Z,
+}
+
 /// A **[`Signed Permutation Matrix`]** [^note] encoded in a byte.
 ///
 /// # Encoding
@@ -24,7 +38,7 @@
 /// [`Signed Permutation Matrix`]: https://en.wikipedia.org/wiki/Generalized_permutation_matrix#Signed_permutation_group
 /// [ROTATION]: https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox-extension.txt#L24
 /// [^note]: A [`Signed Permutation Matrix`] is a square binary matrix that has exactly one entry of ±1 in each row and each column and 0s elsewhere.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Rotation(u8);
 
 pub type Quat = [f32; 4];
@@ -33,14 +47,38 @@ pub type Vec3 = [f32; 3];
 impl Rotation {
     pub const IDENTITY: Self = Rotation(0b0000100);
 
+    /// Creates a rotation from its byte representation, as stored in `_r`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte` is not a valid rotation, see
+    /// [`try_from_byte`](Rotation::try_from_byte).
     pub fn from_byte(byte: u8) -> Self {
+        Rotation::try_from_byte(byte).expect("Invalid Rotation")
+    }
+
+    /// Creates a rotation from its byte representation, as stored in `_r`.
+    /// Fails if the two rows index the same or a fourth column, or if the
+    /// unused highest bit is set.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// assert_eq!(Rotation::try_from_byte(0b0000100), Ok(Rotation::IDENTITY));
+    /// assert_eq!(Rotation::try_from_byte(0b0000000), Err(InvalidRotation(0)));
+    /// ```
+    pub fn try_from_byte(byte: u8) -> Result<Self, InvalidRotation> {
         let index_nz1 = byte & 0b11;
         let index_nz2 = (byte >> 2) & 0b11;
-        assert!(
-            (index_nz1 != index_nz2) && (index_nz1 != 0b11 && index_nz2 != 0b11),
-            "Invalid Rotation"
-        );
-        Rotation(byte)
+        if index_nz1 == index_nz2 || index_nz1 == 0b11 || index_nz2 == 0b11 || byte & 0x80 != 0 {
+            return Err(InvalidRotation(byte));
+        }
+        Ok(Rotation(byte))
+    }
+
+    /// The byte representation of this rotation, as stored in `_r`.
+    pub fn to_byte(self) -> u8 {
+        self.0
     }
 
     /// Decompose the Signed Permutation Matrix into a rotation component, represented by a Quaternion,
@@ -142,6 +180,170 @@ impl Rotation {
 
         cols
     }
+
+    /// Integer-only multiplication of this rotation with a column vector.
+    /// Exact, so results are identical on every platform.
+    pub fn transform_point(&self, point: [i32; 3]) -> [i32; 3] {
+        let index_nz1 = self.0 & 0b11;
+        let index_nz2 = (self.0 >> 2) & 0b11;
+        let index_nz3 = 3 - index_nz1 - index_nz2;
+
+        let row = |index_nz: u8, sign_bit: u8| {
+            let value = point[index_nz as usize];
+            if self.0 & (1 << sign_bit) == 0 {
+                value
+            } else {
+                value.wrapping_neg()
+            }
+        };
+
+        [row(index_nz1, 4), row(index_nz2, 5), row(index_nz3, 6)]
+    }
+
+    /// Euler angles in radians, `[a, b, c]`, such that the rotation is the
+    /// product of rotations by `a`, `b` then `c` about the axes of `order`,
+    /// taken left to right, e.g. `Rx(a) * Ry(b) * Rz(c)` for
+    /// [`EulerOrder::XYZ`]. [Reflections](Rotation::is_reflection) are not
+    /// rotations: the angles of their rotation part, the quaternion of
+    /// [`to_quat_scale`](Rotation::to_quat_scale), are returned.
+    ///
+    /// At gimbal lock, when `b` is a quarter turn, `c` is zero.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    /// use std::f32::consts::FRAC_PI_2;
+    ///
+    /// // Maps x to y, y to -x, and keeps z: a quarter turn about z.
+    /// let rotation = Rotation::from_byte(0b0010001);
+    /// let [a, b, c] = rotation.to_euler(EulerOrder::XYZ);
+    /// assert_eq!([a, b], [0.0, 0.0]);
+    /// assert!((c - FRAC_PI_2).abs() < 1e-6);
+    /// ```
+    pub fn to_euler(&self, order: EulerOrder) -> [f32; 3] {
+        let cols = self.to_cols_array_2d();
+        // A flip is a negated identity, so negating a reflection leaves its
+        // rotation part.
+        let flip = if self.is_reflection() { -1.0 } else { 1.0 };
+        let m = |row: usize, col: usize| flip * cols[col][row];
+
+        let [i, j, k] = order.axes();
+        // +1 for cyclic orders, -1 for the others.
+        let parity = if (j + 3 - i) % 3 == 1 { 1.0 } else { -1.0 };
+        let sin_b = (parity * m(i, k)).clamp(-1.0, 1.0);
+        let b = sin_b.asin();
+        if sin_b.abs() > 1.0 - 1e-6 {
+            let a = (parity * m(k, j)).atan2(m(j, j));
+            return [a, b, 0.0];
+        }
+        let a = (-parity * m(j, k)).atan2(m(k, k));
+        let c = (-parity * m(i, j)).atan2(m(i, i));
+        [a, b, c]
+    }
+
+    /// Every one of the 48 rotations, including reflections, ordered by
+    /// byte.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// assert_eq!(Rotation::all().count(), 48);
+    /// assert_eq!(Rotation::proper_rotations().count(), 24);
+    /// ```
+    pub fn all() -> impl Iterator<Item = Rotation> {
+        (0..0x80).filter_map(|byte| Rotation::try_from_byte(byte).ok())
+    }
+
+    /// The 24 rotations that are not [reflections](Rotation::is_reflection),
+    /// i.e. the orientations of a cube, ordered by byte.
+    pub fn proper_rotations() -> impl Iterator<Item = Rotation> {
+        Rotation::all().filter(|rotation| !rotation.is_reflection())
+    }
+
+    /// Whether this rotation includes a reflection, i.e. its determinant is
+    /// -1.
+    pub fn is_reflection(&self) -> bool {
+        let index_nz1 = self.0 & 0b11;
+        let index_nz2 = (self.0 >> 2) & 0b11;
+        // Odd permutations of the axes have a determinant of -1, as does
+        // each negated row.
+        let odd_permutation = matches!((index_nz1, index_nz2), (0, 2) | (1, 0) | (2, 1));
+        let negated_rows = (self.0 >> 4 & 0b111).count_ones();
+        odd_permutation != (negated_rows % 2 == 1)
+    }
+
+    /// The inverse of this rotation, i.e. its transpose, computed without
+    /// floating point. It takes points from world space back into model
+    /// space.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let rotation = Rotation::from_byte(0b0010001);
+    /// let model = Position { x: 1, y: 2, z: 3 };
+    /// let world = rotation * model;
+    /// assert_eq!(rotation.inverse() * world, model);
+    /// ```
+    pub fn inverse(self) -> Rotation {
+        let mut byte = 0;
+        for axis in 0..3 {
+            let mut basis = [0; 3];
+            basis[axis] = 1;
+            // Row `axis` of the inverse is column `axis` of this rotation.
+            let column = self.transform_point(basis);
+            let index = column.iter().position(|&value| value != 0).unwrap() as u8;
+            if axis < 2 {
+                byte |= index << (2 * axis);
+            }
+            if column[index as usize] < 0 {
+                byte |= 1 << (4 + axis);
+            }
+        }
+        Rotation(byte)
+    }
+}
+
+/// A byte that is not a valid [`Rotation`], returned by
+/// [`Rotation::try_from_byte`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidRotation(pub u8);
+
+impl std::fmt::Display for InvalidRotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#09b} is not a valid rotation", self.0)
+    }
+}
+
+impl std::error::Error for InvalidRotation {}
+
+/// Order of the axes of Euler angles, see [`Rotation::to_euler`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EulerOrder {
+    /// `Rx(a) * Ry(b) * Rz(c)`.
+    XYZ,
+    /// `Rx(a) * Rz(b) * Ry(c)`.
+    XZY,
+    /// `Ry(a) * Rx(b) * Rz(c)`.
+    YXZ,
+    /// `Ry(a) * Rz(b) * Rx(c)`.
+    YZX,
+    /// `Rz(a) * Rx(b) * Ry(c)`.
+    ZXY,
+    /// `Rz(a) * Ry(b) * Rx(c)`.
+    ZYX,
+}
+
+impl EulerOrder {
+    /// Indices of the axes, in order.
+    fn axes(self) -> [usize; 3] {
+        match self {
+            EulerOrder::XYZ => [0, 1, 2],
+            EulerOrder::XZY => [0, 2, 1],
+            EulerOrder::YXZ => [1, 0, 2],
+            EulerOrder::YZX => [1, 2, 0],
+            EulerOrder::ZXY => [2, 0, 1],
+            EulerOrder::ZYX => [2, 1, 0],
+        }
+    }
 }
 
 impl std::fmt::Debug for Rotation {
@@ -202,8 +404,175 @@ impl std::ops::Mul<Rotation> for Rotation {
     }
 }
 
+impl std::ops::Mul<Position> for Rotation {
+    type Output = Position;
+
+    /// Integer-only rotation of a point, see
+    /// [`transform_point`](Rotation::transform_point).
+    fn mul(self, rhs: Position) -> Position {
+        let [x, y, z] = self.transform_point([rhs.x, rhs.y, rhs.z]);
+        Position { x, y, z }
+    }
+}
+
+/// A rotation followed by a translation, as stored by the transform nodes of
+/// the scene graph.
+///
+/// Everything is computed with integers: composing transforms and placing
+/// voxels never rounds, so the world built from a file is identical on every
+/// platform, e.g. for hashing it in a deterministic multiplayer game.
+/// Coordinates wrap around on overflow, in debug and release builds alike;
+/// the `checked_` methods return `None` instead. The scene graph is walked
+/// with the latter, so that models placed out of range are skipped.
+///
+/// ```
+/// use dot_vox::*;
+///
+/// let turn = Transform {
+///     rotation: Rotation::from_byte(0b0010001),
+///     translation: Position { x: 10, y: 0, z: 0 },
+/// };
+/// let twice = turn * turn;
+/// assert_eq!(twice.transform_point(Position { x: 1, y: 0, z: 0 }), Position { x: 9, y: 10, z: 0 });
+/// assert_eq!(twice * twice.inverse(), Transform::IDENTITY);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Transform {
+    /// Rotation, applied first.
+    pub rotation: Rotation,
+    /// Translation, applied after the rotation.
+    pub translation: Position,
+}
+
+impl Transform {
+    /// The transform leaving every point in place.
+    pub const IDENTITY: Transform = Transform {
+        rotation: Rotation::IDENTITY,
+        translation: Position { x: 0, y: 0, z: 0 },
+    };
+
+    /// Rotates, then translates `point`, wrapping around on overflow.
+    pub fn transform_point(&self, point: Position) -> Position {
+        let [x, y, z] = self.rotation.transform_point([point.x, point.y, point.z]);
+        Position {
+            x: self.translation.x.wrapping_add(x),
+            y: self.translation.y.wrapping_add(y),
+            z: self.translation.z.wrapping_add(z),
+        }
+    }
+
+    /// Rotates, then translates `point`, or returns `None` on overflow.
+    pub fn checked_transform_point(&self, point: Position) -> Option<Position> {
+        // Rotating only overflows when negating `i32::MIN`.
+        if [point.x, point.y, point.z].contains(&i32::MIN) {
+            return None;
+        }
+        let [x, y, z] = self.rotation.transform_point([point.x, point.y, point.z]);
+        Some(Position {
+            x: self.translation.x.checked_add(x)?,
+            y: self.translation.y.checked_add(y)?,
+            z: self.translation.z.checked_add(z)?,
+        })
+    }
+
+    /// Applies `rhs` first, then `self`, like `self * rhs`, or returns
+    /// `None` on overflow.
+    pub fn checked_mul(self, rhs: Transform) -> Option<Transform> {
+        Some(Transform {
+            rotation: self.rotation * rhs.rotation,
+            translation: self.checked_transform_point(rhs.translation)?,
+        })
+    }
+
+    /// The transform undoing this one.
+    pub fn inverse(&self) -> Transform {
+        let rotation = self.rotation.inverse();
+        let Position { x, y, z } = self.translation;
+        let [x, y, z] = rotation.transform_point([x, y, z].map(i32::wrapping_neg));
+        Transform {
+            rotation,
+            translation: Position { x, y, z },
+        }
+    }
+
+    /// World space position of a voxel of a model of the given size, when
+    /// this transform places the center of the model.
+    ///
+    /// The offset of the voxel center from the model center is rotated
+    /// before translating. Offsets are doubled to keep the half-voxel offsets
+    /// of even sizes in integers, and rounded down afterwards as MagicaVoxel
+    /// does. Coordinates wrap around on overflow, which
+    /// [`fits`](Transform::fits) rules out.
+    pub fn voxel_position(&self, size: Size, voxel: &Voxel) -> Position {
+        let doubled_offset = [
+            2 * voxel.x as i32 + 1 - size.x as i32,
+            2 * voxel.y as i32 + 1 - size.y as i32,
+            2 * voxel.z as i32 + 1 - size.z as i32,
+        ];
+        let [x, y, z] = self.rotation.transform_point(doubled_offset);
+        Position {
+            x: self.translation.x.wrapping_add(x.div_euclid(2)),
+            y: self.translation.y.wrapping_add(y.div_euclid(2)),
+            z: self.translation.z.wrapping_add(z.div_euclid(2)),
+        }
+    }
+
+    /// Whether every voxel of a model of the given size placed by this
+    /// transform has coordinates within the range of `i32`, so that
+    /// [`voxel_position`](Transform::voxel_position) does not overflow.
+    pub fn fits(&self, size: Size) -> bool {
+        // No voxel is further from the center than the largest side.
+        let reach = size.x.max(size.y).max(size.z) as i64;
+        let Position { x, y, z } = self.translation;
+        [x, y, z].iter().all(|&c| {
+            i32::try_from(c as i64 - reach).is_ok() && i32::try_from(c as i64 + reach).is_ok()
+        })
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    /// Applies `rhs` first, then `self`, as a parent transform applies to
+    /// its child. Wraps around on overflow, see
+    /// [`checked_mul`](Transform::checked_mul).
+    fn mul(self, rhs: Transform) -> Transform {
+        Transform {
+            rotation: self.rotation * rhs.rotation,
+            translation: self.transform_point(rhs.translation),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn test_spm_mul_position() {
+        use super::Rotation as SPM;
+        use crate::Position;
+        let spms: [u8; 6] = [0b0001, 0b0010, 0b0100, 0b0110, 0b1000, 0b1001];
+        let point = Position { x: 2, y: -7, z: 5 };
+
+        for spm in spms {
+            for sign in 0..8 {
+                let rotation = SPM(spm | (sign << 4));
+                let matrix = glam::Mat3::from_cols_array_2d(&rotation.to_cols_array_2d());
+                let reference = matrix * glam::Vec3::new(2.0, -7.0, 5.0);
+                let rotated = rotation * point;
+                assert_eq!(
+                    glam::Vec3::new(rotated.x as f32, rotated.y as f32, rotated.z as f32),
+                    reference
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_spm_mul() {
         use super::Rotation as SPM;
@@ -235,15 +604,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_inverse() {
+        use super::Rotation as SPM;
+        let spms: [u8; 6] = [0b0001, 0b0010, 0b0100, 0b0110, 0b1000, 0b1001];
+        for spm in spms {
+            for sign in 0..8 {
+                let rotation = SPM(spm | (sign << 4));
+                assert_eq!(rotation * rotation.inverse(), SPM::IDENTITY);
+                assert_eq!(rotation.inverse() * rotation, SPM::IDENTITY);
+                let point = [3, -5, 7];
+                assert_eq!(
+                    rotation
+                        .inverse()
+                        .transform_point(rotation.transform_point(point)),
+                    point
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_to_quat_scale() {
         use super::Rotation as SPM;
         let spms: [u8; 6] = [0b0100, 0b1000, 0b1001, 0b0001, 0b0010, 0b0110];
 
         // Test for every possible spms
-        for i in 0..6 {
+        for spm_i in spms {
             for sign_i in 0..8 {
-                let spm = SPM(spms[i] | (sign_i << 4));
+                let spm = SPM(spm_i | (sign_i << 4));
                 let (rotation, scale) = spm.to_quat_scale();
                 let rotation = glam::Quat::from_array(rotation);
                 let scale: glam::Vec3 = scale.into();
@@ -266,4 +655,111 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_to_euler() {
+        use super::{EulerOrder, Rotation as SPM};
+        use glam::EulerRot;
+        let orders = [
+            (EulerOrder::XYZ, EulerRot::XYZ),
+            (EulerOrder::XZY, EulerRot::XZY),
+            (EulerOrder::YXZ, EulerRot::YXZ),
+            (EulerOrder::YZX, EulerRot::YZX),
+            (EulerOrder::ZXY, EulerRot::ZXY),
+            (EulerOrder::ZYX, EulerRot::ZYX),
+        ];
+        let spms: [u8; 6] = [0b0001, 0b0010, 0b0100, 0b0110, 0b1000, 0b1001];
+        for spm in spms {
+            for sign in 0..8 {
+                let rotation = SPM(spm | (sign << 4));
+                let (quat, scale) = rotation.to_quat_scale();
+                let expected = glam::Mat3::from_quat(glam::Quat::from_array(quat));
+                assert_eq!(rotation.is_reflection(), scale[0] < 0.0);
+                for (order, euler_rot) in orders {
+                    let [a, b, c] = rotation.to_euler(order);
+                    let actual = glam::Mat3::from_euler(euler_rot, a, b, c);
+                    assert!(
+                        actual.abs_diff_eq(expected, 1e-5),
+                        "{rotation:?} {order:?}: {actual} vs {expected}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_from_byte() {
+        use super::{InvalidRotation, Rotation as SPM};
+        let valid: Vec<u8> = (0..=u8::MAX)
+            .filter(|&byte| SPM::try_from_byte(byte).is_ok())
+            .collect();
+        assert_eq!(valid.len(), 48);
+        assert!(valid.iter().all(|&byte| byte < 0x80));
+        assert_eq!(SPM::try_from_byte(0x84), Err(InvalidRotation(0x84)));
+        assert_eq!(SPM::try_from_byte(0b0000101), Err(InvalidRotation(5)));
+    }
+
+    #[test]
+    fn test_all() {
+        use super::Rotation as SPM;
+        let all: Vec<SPM> = SPM::all().collect();
+        assert_eq!(all.len(), 48);
+        assert!(all
+            .windows(2)
+            .all(|pair| pair[0].to_byte() < pair[1].to_byte()));
+        assert!(all.contains(&SPM::IDENTITY));
+
+        let proper: Vec<SPM> = SPM::proper_rotations().collect();
+        assert_eq!(proper.len(), 24);
+        for &rotation in &all {
+            let det = glam::Mat3::from_cols_array_2d(&rotation.to_cols_array_2d()).determinant();
+            assert_eq!(rotation.is_reflection(), det < 0.0);
+            assert_eq!(proper.contains(&rotation), det > 0.0);
+        }
+        // Proper rotations form a group.
+        for &lhs in &proper {
+            for &rhs in &proper {
+                assert!(!(lhs * rhs).is_reflection());
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverse_is_transpose() {
+        use super::Rotation as SPM;
+        for rotation in SPM::all() {
+            let matrix = glam::Mat3::from_cols_array_2d(&rotation.to_cols_array_2d());
+            let inverse = glam::Mat3::from_cols_array_2d(&rotation.inverse().to_cols_array_2d());
+            assert_eq!(inverse, matrix.transpose(), "{rotation:?}");
+            assert_eq!(rotation.inverse().inverse(), rotation);
+        }
+    }
+
+    #[test]
+    fn composition_applies_the_child_first() {
+        use super::{Rotation, Transform};
+        use crate::Position;
+
+        let rotations: Vec<Rotation> = Rotation::all().collect();
+        assert_eq!(rotations.len(), 48);
+
+        let point = Position { x: 3, y: -5, z: 11 };
+        for (n, &rotation) in rotations.iter().enumerate() {
+            let parent = Transform {
+                rotation,
+                translation: Position { x: 1, y: 2, z: 3 },
+            };
+            let child = Transform {
+                rotation: rotations[(n * 7 + 5) % rotations.len()],
+                translation: Position { x: -4, y: 0, z: 9 },
+            };
+            let composed = parent * child;
+            assert_eq!(
+                composed.transform_point(point),
+                parent.transform_point(child.transform_point(point))
+            );
+            assert_eq!(composed.inverse(), child.inverse() * parent.inverse());
+            assert_eq!((composed * parent) * child, composed * (parent * child));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{DotVoxData, SceneNode, Size, Voxel};
+use std::{error, fmt};
+
+/// A problem with the content of a [`DotVoxData`] that would make the file
+/// written from it invalid, found by [`DotVoxData::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// A model is larger than [`Size::MAX_AXIS`] along some axis.
+    ModelTooLarge {
+        /// Index of the model in [`DotVoxData::models`].
+        model_id: usize,
+        /// Size of the model.
+        size: Size,
+    },
+    /// A voxel lies outside the size of its model.
+    VoxelOutOfBounds {
+        /// Index of the model in [`DotVoxData::models`].
+        model_id: usize,
+        /// The offending voxel.
+        voxel: Voxel,
+    },
+    /// A voxel uses palette index 255, which `.vox` files cannot store:
+    /// they hold [`Voxel::i`] plus one in a byte.
+    InvalidPaletteIndex {
+        /// Index of the model in [`DotVoxData::models`].
+        model_id: usize,
+        /// The offending voxel.
+        voxel: Voxel,
+    },
+    /// A transform or group node refers to a child past the end of
+    /// [`DotVoxData::scenes`].
+    MissingChild {
+        /// Index of the node in [`DotVoxData::scenes`].
+        node_id: u32,
+        /// The missing child.
+        child: u32,
+    },
+    /// A shape node refers to a model past the end of [`DotVoxData::models`].
+    MissingModel {
+        /// Index of the node in [`DotVoxData::scenes`].
+        node_id: u32,
+        /// The missing model.
+        model_id: u32,
+    },
+    /// A transform node refers to a layer past the end of
+    /// [`DotVoxData::layers`]. `u32::MAX`, used for no layer, is allowed.
+    MissingLayer {
+        /// Index of the node in [`DotVoxData::scenes`].
+        node_id: u32,
+        /// The missing layer.
+        layer_id: u32,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::ModelTooLarge { model_id, size } => write!(
+                f,
+                "model {} is {}x{}x{}, larger than the maximum of {} per axis",
+                model_id,
+                size.x,
+                size.y,
+                size.z,
+                Size::MAX_AXIS
+            ),
+            ValidationError::VoxelOutOfBounds { model_id, voxel } => write!(
+                f,
+                "voxel at ({}, {}, {}) lies outside model {}",
+                voxel.x, voxel.y, voxel.z, model_id
+            ),
+            ValidationError::InvalidPaletteIndex { model_id, voxel } => write!(
+                f,
+                "voxel at ({}, {}, {}) in model {} uses invalid palette index {}",
+                voxel.x, voxel.y, voxel.z, model_id, voxel.i
+            ),
+            ValidationError::MissingChild { node_id, child } => {
+                write!(f, "node {} refers to missing child node {}", node_id, child)
+            }
+            ValidationError::MissingModel { node_id, model_id } => {
+                write!(f, "node {} refers to missing model {}", node_id, model_id)
+            }
+            ValidationError::MissingLayer { node_id, layer_id } => {
+                write!(f, "node {} refers to missing layer {}", node_id, layer_id)
+            }
+        }
+    }
+}
+
+impl error::Error for ValidationError {}
+
+impl DotVoxData {
+    /// Checks that the content of `self` is consistent: models fit in the
+    /// format, voxels lie within their model and use a palette index the
+    /// format can store, and scene nodes refer to nodes, models and layers
+    /// that exist. Returns every problem found, in order.
+    ///
+    /// [`WriteOptions::validate`](crate::WriteOptions::validate) runs this
+    /// check before writing.
+    ///
+    /// ```
+    /// use dot_vox::*;
+    ///
+    /// let mut data = load("src/resources/placeholder.vox").unwrap();
+    /// assert!(data.validate().is_empty());
+    ///
+    /// data.models.clear();
+    /// assert!(matches!(
+    ///     data.validate()[..],
+    ///     [ValidationError::MissingModel { model_id: 0, .. }]
+    /// ));
+    /// ```
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for (model_id, model) in self.models.iter().enumerate() {
+            let size = model.size;
+            if !size.is_within_limits() {
+                errors.push(ValidationError::ModelTooLarge { model_id, size });
+            }
+            for voxel in model.voxels.iter() {
+                if voxel.x as u32 >= size.x || voxel.y as u32 >= size.y || voxel.z as u32 >= size.z
+                {
+                    errors.push(ValidationError::VoxelOutOfBounds {
+                        model_id,
+                        voxel: *voxel,
+                    });
+                }
+                if voxel.i == u8::MAX {
+                    errors.push(ValidationError::InvalidPaletteIndex {
+                        model_id,
+                        voxel: *voxel,
+                    });
+                }
+            }
+        }
+
+        let node_count = self.scenes.len();
+        for (node_id, node) in self.scenes.iter().enumerate() {
+            let node_id = node_id as u32;
+            let children: &[u32] = match node {
+                SceneNode::Transform { child, .. } => {
+                    if let Some(layer_id) = node.layer() {
+                        if layer_id as usize >= self.layers.len() {
+                            errors.push(ValidationError::MissingLayer { node_id, layer_id });
+                        }
+                    }
+                    std::slice::from_ref(child)
+                }
+                SceneNode::Group { children, .. } => children,
+                SceneNode::Shape { models, .. } => {
+                    for model in models {
+                        if model.model_id as usize >= self.models.len() {
+                            errors.push(ValidationError::MissingModel {
+                                node_id,
+                                model_id: model.model_id,
+                            });
+                        }
+                    }
+                    &[]
+                }
+            };
+            for &child in children {
+                if child as usize >= node_count {
+                    errors.push(ValidationError::MissingChild { node_id, child });
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_files_are_valid() {
+        for path in ["axes", "placeholder", "transforms"] {
+            let data = crate::load(&format!("src/resources/{}.vox", path)).unwrap();
+            assert_eq!(data.validate(), [], "{}", path);
+        }
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let mut data = crate::load("src/resources/placeholder.vox").unwrap();
+        data.models[0].size = Size { x: 1, y: 1, z: 300 };
+        data.models[0].voxels.truncate(2);
+        data.models[0].voxels[0] = Voxel {
+            x: 0,
+            y: 0,
+            z: 255,
+            i: 1,
+        };
+        data.models[0].voxels[1].x = 1;
+        data.models[0].voxels[1].i = 255;
+        let voxel = data.models[0].voxels[1];
+        if let SceneNode::Transform {
+            child, layer_id, ..
+        } = &mut data.scenes[0]
+        {
+            *child = 10;
+            *layer_id = 8;
+        }
+
+        assert_eq!(
+            data.validate(),
+            [
+                ValidationError::ModelTooLarge {
+                    model_id: 0,
+                    size: data.models[0].size
+                },
+                ValidationError::VoxelOutOfBounds { model_id: 0, voxel },
+                ValidationError::InvalidPaletteIndex { model_id: 0, voxel },
+                ValidationError::MissingLayer {
+                    node_id: 0,
+                    layer_id: 8
+                },
+                ValidationError::MissingChild {
+                    node_id: 0,
+                    child: 10
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{
+    model, palette,
+    parser::{parse_material, ChunkLocation, DEFAULT_MAX_DEPTH, MAGIC_NUMBER},
+    Color, Error, Limit, Material, ParseOptions, Size, Voxel,
+};
+use std::iter::FusedIterator;
+
+/// Callbacks invoked by [`parse_chunks`] for each chunk of a `.vox` file, in
+/// file order. Every method does nothing by default.
+pub trait ChunkVisitor {
+    /// Called for each `SIZE` chunk, which precedes the `XYZI` chunk of the
+    /// same model.
+    fn on_size(&mut self, _size: Size) {}
+
+    /// Called for each `XYZI` chunk with its voxels, borrowed from the input.
+    fn on_voxels(&mut self, _voxels: RawVoxels<'_>) {}
+
+    /// Called for the `RGBA` chunk.
+    fn on_palette(&mut self, _palette: &[Color]) {}
+
+    /// Called for each `MATL` chunk.
+    fn on_material(&mut self, _material: Material) {}
+
+    /// Called for every other chunk within `MAIN`, including the scene graph
+    /// chunks, which can be decoded with the parsers of [`raw`](crate::raw).
+    fn on_unknown(&mut self, _id: &str, _content: &[u8], _children: &[u8]) {}
+}
+
+/// The voxels of an `XYZI` chunk, as passed to [`ChunkVisitor::on_voxels`].
+///
+/// Iterates over [`Voxel`]s, or exposes the raw bytes for copying straight
+/// into a buffer.
+#[derive(Clone, Debug)]
+pub struct RawVoxels<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> RawVoxels<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        RawVoxels { bytes }
+    }
+
+    /// The voxels as stored in the file: four bytes each, `x`, `y`, `z`,
+    /// then the 1-based palette index, unlike [`Voxel::i`].
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl Iterator for RawVoxels<'_> {
+    type Item = Voxel;
+
+    fn next(&mut self) -> Option<Voxel> {
+        let (voxel, rest) = self.bytes.split_first_chunk::<4>()?;
+        self.bytes = rest;
+        Some(Voxel {
+            x: voxel[0],
+            y: voxel[1],
+            z: voxel[2],
+            i: voxel[3].saturating_sub(1),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bytes.len() / 4;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for RawVoxels<'_> {}
+
+impl FusedIterator for RawVoxels<'_> {}
+
+/// Walks the chunks of a `.vox` file, calling `visitor` for each, without
+/// building a [`DotVoxData`](crate::DotVoxData). Returns the file version.
+///
+/// This is the lowest level way to read a file: voxels are not copied, so an
+/// engine can stream them into its own buffers, and chunks it does not need
+/// cost nothing beyond skipping their header.
+///
+/// ```
+/// use dot_vox::*;
+///
+/// #[derive(Default)]
+/// struct CountVoxels(usize);
+///
+/// impl ChunkVisitor for CountVoxels {
+///     fn on_voxels(&mut self, voxels: RawVoxels<'_>) {
+///         self.0 += voxels.len();
+///     }
+/// }
+///
+/// let mut counter = CountVoxels::default();
+/// parse_chunks(include_bytes!("resources/placeholder.vox"), &mut counter).unwrap();
+/// assert_eq!(counter.0, 4);
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidMagic`] for data that is not a `.vox` file,
+/// [`Error::UnexpectedEof`] if a chunk is cut off, or
+/// [`Error::InvalidChunk`] if a chunk with a callback cannot be decoded.
+/// Chunks before the faulty one have been visited by then.
+pub fn parse_chunks(bytes: &[u8], visitor: &mut impl ChunkVisitor) -> Result<u32, Error> {
+    let options = ParseOptions::default();
+    for_each_chunk(bytes, |id, content, children, location| {
+        match id {
+            b"SIZE" => {
+                let (_, size) = model::parse_size(content).map_err(|_| location())?;
+                visitor.on_size(size);
+            }
+            b"XYZI" => visitor.on_voxels(voxels_of(content).ok_or_else(location)?),
+            b"RGBA" => {
+                let (_, colors) = palette::extract_palette(content).map_err(|_| location())?;
+                visitor.on_palette(&colors);
+            }
+            b"MATL" => {
+                let (_, material) = parse_material(content, &options).map_err(|_| location())?;
+                visitor.on_material(material);
+            }
+            _ => visitor.on_unknown(&String::from_utf8_lossy(id), content, children),
+        }
+        Ok(())
+    })
+}
+
+/// Calls `f` with the id, content and children of each chunk within `MAIN`,
+/// and a function returning its location for errors. Returns the file
+/// version.
+pub(crate) fn for_each_chunk<'a>(
+    bytes: &'a [u8],
+    mut f: impl FnMut(&'a [u8], &'a [u8], &'a [u8], &dyn Fn() -> ChunkLocation) -> Result<(), Error>,
+) -> Result<u32, Error> {
+    let header = MAGIC_NUMBER.len() + 4;
+    if !bytes.starts_with(MAGIC_NUMBER.as_bytes()) {
+        return Err(Error::InvalidMagic);
+    }
+    let version = read_u32(bytes, MAGIC_NUMBER.len()).ok_or(Error::UnexpectedEof)?;
+    let (_, _, children) = chunk_at(bytes, header)?;
+
+    let mut offset = 0;
+    while offset < children.len() {
+        let (id, content, grandchildren) = chunk_at(children, offset)?;
+        let location = || ChunkLocation {
+            id: String::from_utf8_lossy(id).into_owned(),
+            offset: header + 12 + offset,
+            path: vec!["MAIN".to_owned()],
+        };
+        f(id, content, grandchildren, &location)?;
+        offset += 12 + content.len() + grandchildren.len();
+    }
+    Ok(version)
+}
+
+/// A chunk of a `.vox` file as laid out in the file, see [`read_chunks`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileChunk<'a> {
+    /// Id of the chunk.
+    pub id: [u8; 4],
+    /// Offset of the chunk header in the file.
+    pub offset: usize,
+    /// Content of the chunk.
+    pub content: &'a [u8],
+    /// Children of the chunk, in file order.
+    pub children: Vec<FileChunk<'a>>,
+}
+
+impl FileChunk<'_> {
+    /// Number of bytes taken by the chunk in the file, including its header
+    /// and children.
+    pub fn byte_len(&self) -> usize {
+        12 + self.content.len() + self.children.iter().map(FileChunk::byte_len).sum::<usize>()
+    }
+
+    /// Id of the chunk as a string, with invalid UTF-8 replaced.
+    pub fn id_str(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.id)
+    }
+}
+
+/// Reads the chunk structure of a `.vox` file without decoding any chunk, for
+/// tools inspecting the ids, sizes and order of chunks. Returns the chunks
+/// following the header, normally a single `MAIN` chunk.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidMagic`] if the header is missing,
+/// [`Error::UnexpectedEof`] if a chunk extends past the end of its parent,
+/// and [`Error::LimitExceeded`] if chunks are nested more than 64 deep.
+///
+/// ```
+/// use dot_vox::raw::read_chunks;
+///
+/// let bytes = include_bytes!("resources/placeholder.vox");
+/// let chunks = read_chunks(bytes).unwrap();
+/// assert_eq!(chunks.len(), 1);
+/// assert_eq!(&chunks[0].id, b"MAIN");
+/// assert_eq!(chunks[0].byte_len(), bytes.len() - 8);
+/// assert_eq!(&chunks[0].children[0].id, b"SIZE");
+/// ```
+pub fn read_chunks(bytes: &[u8]) -> Result<Vec<FileChunk<'_>>, Error> {
+    if !bytes.starts_with(MAGIC_NUMBER.as_bytes()) {
+        return Err(Error::InvalidMagic);
+    }
+    let header = MAGIC_NUMBER.len() + 4;
+    let body = bytes.get(header..).ok_or(Error::UnexpectedEof)?;
+    read_chunk_list(body, header, 1)
+}
+
+/// Reads the chunks filling `bytes`, which starts at `offset` in the file and
+/// is nested `depth` deep.
+fn read_chunk_list(bytes: &[u8], offset: usize, depth: usize) -> Result<Vec<FileChunk<'_>>, Error> {
+    if !bytes.is_empty() && depth > DEFAULT_MAX_DEPTH {
+        return Err(Error::LimitExceeded(Limit::Depth));
+    }
+    let mut chunks = Vec::new();
+    let mut position = 0;
+    while position < bytes.len() {
+        let (id, content, children) = chunk_at(bytes, position)?;
+        let children_offset = offset + position + 12 + content.len();
+        chunks.push(FileChunk {
+            id: id.try_into().expect("chunk ids are 4 bytes"),
+            offset: offset + position,
+            content,
+            children: read_chunk_list(children, children_offset, depth + 1)?,
+        });
+        position += 12 + content.len() + children.len();
+    }
+    Ok(chunks)
+}
+
+/// The voxels of the content of an `XYZI` chunk, if it holds as many as it
+/// declares.
+pub(crate) fn voxels_of(content: &[u8]) -> Option<RawVoxels<'_>> {
+    let count = read_u32(content, 0)? as usize;
+    let bytes = content.get(4..count.checked_mul(4)?.checked_add(4)?)?;
+    Some(RawVoxels::new(bytes))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..)?.first_chunk::<4>()?;
+    Some(u32::from_le_bytes(*bytes))
+}
+
+/// The id, content and children of a chunk.
+type ChunkParts<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+/// Splits the chunk at `offset` into its parts.
+fn chunk_at(bytes: &[u8], offset: usize) -> Result<ChunkParts<'_>, Error> {
+    let eof = || Error::UnexpectedEof;
+    let id = bytes.get(offset..offset + 4).ok_or_else(eof)?;
+    let content_size = read_u32(bytes, offset + 4).ok_or_else(eof)? as usize;
+    let children_size = read_u32(bytes, offset + 8).ok_or_else(eof)? as usize;
+    let content_start = offset + 12;
+    let children_start = content_start.checked_add(content_size).ok_or_else(eof)?;
+    let end = children_start.checked_add(children_size).ok_or_else(eof)?;
+    let content = bytes.get(content_start..children_start).ok_or_else(eof)?;
+    let children = bytes.get(children_start..end).ok_or_else(eof)?;
+    Ok((id, content, children))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        sizes: Vec<Size>,
+        voxels: Vec<Vec<Voxel>>,
+        palette: Vec<Color>,
+        materials: Vec<Material>,
+        unknown: Vec<String>,
+    }
+
+    impl ChunkVisitor for Recorder {
+        fn on_size(&mut self, size: Size) {
+            self.sizes.push(size);
+        }
+
+        fn on_voxels(&mut self, voxels: RawVoxels<'_>) {
+            assert_eq!(voxels.as_bytes().len(), voxels.len() * 4);
+            self.voxels.push(voxels.collect());
+        }
+
+        fn on_palette(&mut self, palette: &[Color]) {
+            self.palette = palette.to_vec();
+        }
+
+        fn on_material(&mut self, material: Material) {
+            self.materials.push(material);
+        }
+
+        fn on_unknown(&mut self, id: &str, _content: &[u8], _children: &[u8]) {
+            self.unknown.push(id.to_owned());
+        }
+    }
+
+    #[test]
+    fn visits_the_same_content_as_load() {
+        let bytes = include_bytes!("resources/placeholder-with-materials.vox");
+        let data = crate::load_bytes(bytes).unwrap();
+        let mut recorder = Recorder::default();
+        assert_eq!(parse_chunks(bytes, &mut recorder).unwrap(), data.version);
+
+        let sizes: Vec<Size> = data.models.iter().map(|model| model.size).collect();
+        let voxels: Vec<Vec<Voxel>> = data.models.iter().map(|m| m.voxels.clone()).collect();
+        assert_eq!(recorder.sizes, sizes);
+        assert_eq!(recorder.voxels, voxels);
+        assert_eq!(recorder.palette, data.palette);
+        assert_eq!(recorder.materials, data.materials);
+        assert!(recorder.unknown.iter().any(|id| id == "nTRN"));
+    }
+
+    #[test]
+    fn reports_malformed_files() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        let mut recorder = Recorder::default();
+        assert!(matches!(
+            parse_chunks(&bytes[..bytes.len() - 1], &mut recorder),
+            Err(Error::UnexpectedEof)
+        ));
+        assert!(matches!(
+            parse_chunks(include_bytes!("resources/not_a.vox"), &mut recorder),
+            Err(Error::InvalidMagic)
+        ));
+
+        // Claim more voxels than the XYZI chunk holds.
+        let mut bytes = bytes.to_vec();
+        let xyzi = bytes.windows(4).position(|id| id == b"XYZI").unwrap();
+        bytes[xyzi + 12] = 200;
+        match parse_chunks(&bytes, &mut recorder) {
+            Err(Error::InvalidChunk { id, offset, path }) => {
+                assert_eq!(id, "XYZI");
+                assert_eq!(offset, xyzi);
+                assert_eq!(path, ["MAIN"]);
+            }
+            result => panic!("Expected an invalid chunk, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn read_chunks_reports_the_structure_of_a_file() {
+        let bytes = include_bytes!("resources/placeholder.vox");
+        let chunks = read_chunks(bytes).unwrap();
+        let main = &chunks[0];
+        assert_eq!(main.offset, 8);
+        assert!(main.content.is_empty());
+
+        let ids: Vec<_> = main.children.iter().map(|chunk| chunk.id_str()).collect();
+        assert_eq!(ids[..2], ["SIZE", "XYZI"]);
+        assert!(ids.contains(&"RGBA".into()));
+        // Children are laid out back to back.
+        for pair in main.children.windows(2) {
+            assert_eq!(pair[0].offset + pair[0].byte_len(), pair[1].offset);
+        }
+        let last = main.children.last().unwrap();
+        assert_eq!(last.offset + last.byte_len(), bytes.len());
+
+        assert!(matches!(
+            read_chunks(&bytes[..bytes.len() - 1]),
+            Err(Error::UnexpectedEof)
+        ));
+        assert!(matches!(read_chunks(b"RIFF"), Err(Error::InvalidMagic)));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{load_bytes, DotVoxData, DotVoxDiff, Error};
+use std::{
+    fs, io,
+    path::Path,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+/// Options controlling how [`watch_file_with`] checks for modifications.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// How often the modification time and size of the file are checked.
+    pub poll_interval: Duration,
+    /// How long the file must stay unchanged after a modification before it
+    /// is parsed again, so that a file still being written is not reloaded
+    /// once per write.
+    pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            poll_interval: Duration::from_millis(250),
+            debounce: Duration::from_millis(100),
+        }
+    }
+}
+
+/// A new version of a watched file, delivered by [`watch_file`].
+#[derive(Debug)]
+pub struct Reload {
+    /// The new content of the file.
+    pub data: DotVoxData,
+    /// What changed since the previous version successfully loaded.
+    pub diff: DotVoxDiff,
+}
+
+/// Handle to the thread watching a file, returned by [`watch_file`]. The
+/// file stops being watched when this is dropped.
+#[derive(Debug)]
+pub struct FileWatcher {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Watches a `.vox` file for modifications, e.g. to hot-reload assets while
+/// they are edited. Requires the `watch` feature. Equivalent to
+/// [`watch_file_with`] with the default [`WatchOptions`].
+pub fn watch_file<P, F>(path: P, callback: F) -> Result<FileWatcher, Error>
+where
+    P: AsRef<Path>,
+    F: FnMut(Result<Reload, Error>) + Send + 'static,
+{
+    watch_file_with(path, WatchOptions::default(), callback)
+}
+
+/// Watches a `.vox` file for modifications, e.g. to hot-reload assets while
+/// they are edited. Requires the `watch` feature.
+///
+/// The file is loaded once up front, then polled from a background thread.
+/// Whenever it is modified, it is parsed again and `callback` receives the
+/// new content along with its [`diff`](DotVoxData::diff) from the previous
+/// version successfully loaded. Failures to read or parse the file are
+/// passed to `callback` as well, and the file keeps being watched.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if the file cannot be loaded initially.
+pub fn watch_file_with<P, F>(
+    path: P,
+    options: WatchOptions,
+    mut callback: F,
+) -> Result<FileWatcher, Error>
+where
+    P: AsRef<Path>,
+    F: FnMut(Result<Reload, Error>) + Send + 'static,
+{
+    let path = path.as_ref().to_path_buf();
+    let mut last = Some(stamp(&path)?);
+    let mut current = load_bytes(&fs::read(&path)?)?;
+
+    let (stop, stopped) = mpsc::channel();
+    let wait = move |duration| {
+        matches!(
+            stopped.recv_timeout(duration),
+            Err(RecvTimeoutError::Timeout)
+        )
+    };
+    let thread = thread::spawn(move || {
+        while wait(options.poll_interval) {
+            let mut seen = stamp(&path).ok();
+            if seen == last {
+                continue;
+            }
+            // Wait for the file to settle.
+            loop {
+                if !wait(options.debounce) {
+                    return;
+                }
+                let now = stamp(&path).ok();
+                if now == seen {
+                    break;
+                }
+                seen = now;
+            }
+            last = seen;
+            callback(reload(&path, &mut current));
+        }
+    });
+
+    Ok(FileWatcher {
+        stop: Some(stop),
+        thread: Some(thread),
+    })
+}
+
+/// Modification time and size of a file.
+fn stamp(path: &Path) -> io::Result<(SystemTime, u64)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.modified()?, metadata.len()))
+}
+
+fn reload(path: &Path, current: &mut DotVoxData) -> Result<Reload, Error> {
+    let data = load_bytes(&fs::read(path)?)?;
+    let diff = current.diff(&data);
+    *current = data.clone();
+    Ok(Reload { data, diff })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn modifications_are_reloaded() {
+        let path = std::env::temp_dir().join(format!(
+            "dot_vox_modifications_are_reloaded_{}.vox",
+            std::process::id()
+        ));
+        let original = include_bytes!("resources/placeholder.vox");
+        fs::write(&path, original).unwrap();
+
+        let reloads = Arc::new(Mutex::new(Vec::new()));
+        let options = WatchOptions {
+            poll_interval: Duration::from_millis(10),
+            debounce: Duration::from_millis(10),
+        };
+        let received = reloads.clone();
+        let watcher = watch_file_with(&path, options, move |reload| {
+            received.lock().unwrap().push(reload);
+        })
+        .unwrap();
+
+        let mut data = load_bytes(original).unwrap();
+        data.models[0].voxels.pop();
+        let mut bytes = Vec::new();
+        data.write_vox(&mut bytes).unwrap();
+        fs::write(&path, &bytes).unwrap();
+        for _ in 0..200 {
+            if !reloads.lock().unwrap().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        drop(watcher);
+        fs::remove_file(&path).unwrap();
+
+        let reloads = reloads.lock().unwrap();
+        let Some(Ok(reload)) = reloads.first() else {
+            panic!("Expected a reload, got {:?}", reloads);
+        };
+        assert_eq!(reload.data, data);
+        assert!(matches!(
+            &reload.diff.models[..],
+            [crate::ModelChange::Voxels { added, removed, .. }] if added.is_empty() && removed.len() == 1
+        ));
+    }
+
+    #[test]
+    fn missing_file_fails_to_watch() {
+        assert!(watch_file("src/resources/not_here.vox", |_| {}).is_err());
+    }
+}